@@ -0,0 +1,535 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{self, SeekFrom},
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use futures::io::{AsyncRead, AsyncSeek};
+use ps_datachunk::DataChunk;
+use ps_hash::Hash;
+
+use crate::{
+    reader::{child_path, find_item, retain_path, seek_position, ListPath, Segment},
+    AsyncStore, Hkey, HkeyError, LongHkey, LongHkeyExpanded,
+};
+
+type Pending<S, E> = Pin<Box<dyn Future<Output = (Box<ReaderState<S>>, Result<(), E>)> + Send>>;
+
+/// Asynchronous counterpart of [`HkeyReader`](crate::HkeyReader), reading the data an [`Hkey`]
+/// refers to from an [`AsyncStore`], one leaf at a time.
+pub struct AsyncHkeyReader<S: AsyncStore> {
+    state: Option<Box<ReaderState<S>>>,
+    pending: Option<Pending<S, S::Error>>,
+    position: usize,
+}
+
+struct ReaderState<S: AsyncStore> {
+    store: S,
+    root: Hkey,
+    length: Option<usize>,
+    segment: Option<Segment>,
+    nodes: HashMap<Hash, LongHkeyExpanded>,
+    lists: HashMap<Hash, Hkey>,
+    ends: HashMap<ListPath, Arc<[usize]>>,
+}
+
+impl<S: AsyncStore> AsyncHkeyReader<S> {
+    pub fn new(store: S, hkey: Hkey) -> Self {
+        let state = ReaderState {
+            store,
+            root: hkey,
+            length: None,
+            segment: None,
+            nodes: HashMap::new(),
+            lists: HashMap::new(),
+            ends: HashMap::new(),
+        };
+
+        Self {
+            state: Some(Box::new(state)),
+            pending: None,
+            position: 0,
+        }
+    }
+
+    /// Returns the offset of the cursor.
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bytes the [`Hkey`] refers to.
+    pub async fn length(&mut self) -> Result<usize, S::Error> {
+        if let Some(pending) = self.pending.take() {
+            let (state, result) = pending.await;
+
+            self.state = Some(state);
+
+            result?;
+        }
+
+        let state = self.state.as_mut().ok_or(HkeyError::ReaderPoisoned)?;
+
+        state.length().await
+    }
+
+    fn poll_pending(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&mut ReaderState<S>, S::Error>> {
+        if let Some(pending) = &mut self.pending {
+            let (state, result) = ready!(pending.as_mut().poll(cx));
+
+            self.pending = None;
+            self.state = Some(state);
+
+            result?;
+        }
+
+        let state = self.state.as_deref_mut().ok_or(HkeyError::ReaderPoisoned)?;
+
+        Poll::Ready(Ok(state))
+    }
+
+    /// Starts fetching the leaf containing `position`, to be polled by subsequent calls.
+    fn start_load(&mut self, position: usize) {
+        if let Some(mut state) = self.state.take() {
+            self.pending = Some(Box::pin(async move {
+                let result = state.load(position).await;
+
+                (state, result)
+            }));
+        }
+    }
+
+    /// Starts computing the length, to be polled by subsequent calls.
+    fn start_length(&mut self) {
+        if let Some(mut state) = self.state.take() {
+            self.pending = Some(Box::pin(async move {
+                let result = state.length().await.map(|_| ());
+
+                (state, result)
+            }));
+        }
+    }
+}
+
+impl<S: AsyncStore> ReaderState<S> {
+    async fn length(&mut self) -> Result<usize, S::Error> {
+        if let Some(length) = self.length {
+            return Ok(length);
+        }
+
+        let length = self
+            .length_of((None, Vec::new()), self.root.clone())
+            .await?;
+
+        self.length = Some(length);
+
+        Ok(length)
+    }
+
+    /// Fetches the leaf containing `position`, unless it lies past the end.
+    async fn load(&mut self, position: usize) -> Result<(), S::Error> {
+        if position < self.length().await? {
+            self.segment = Some(self.locate(position).await?);
+        }
+
+        Ok(())
+    }
+
+    /// Finds the leaf containing `position`, descending through the index nodes.
+    async fn locate(&mut self, position: usize) -> Result<Segment, S::Error> {
+        let mut hkey = self.root.clone();
+        let mut start = 0;
+        let mut offset = position;
+        let mut limit = usize::MAX;
+        let mut path: ListPath = (None, Vec::new());
+        let mut visited = Vec::new();
+
+        loop {
+            hkey = match hkey {
+                Hkey::LongHkey(lhkey) => {
+                    path = (Some(lhkey.hash()), Vec::new());
+                    visited.push(lhkey.hash());
+
+                    Hkey::LongHkeyExpanded(self.expand(&lhkey).await?)
+                }
+                Hkey::ListRef(hash, key) => {
+                    path = (Some(hash), Vec::new());
+                    visited.push(hash);
+
+                    self.list(&hash, &key).await?
+                }
+                Hkey::LongHkeyExpanded(lhkey) => {
+                    // Part ranges may overrun the end of the node.
+                    let size = lhkey.size().min(limit);
+
+                    let (index, (range, part)) = lhkey
+                        .parts()
+                        .iter()
+                        .enumerate()
+                        .find(|(_, (range, _))| {
+                            range.start <= offset && offset < range.end.min(size)
+                        })
+                        .ok_or(HkeyError::Range(size))?;
+
+                    start += range.start;
+                    offset -= range.start;
+                    limit = range.end.min(size) - range.start;
+                    path.1.push(index);
+
+                    part.clone()
+                }
+                Hkey::List(items) => {
+                    let ends = self.item_ends(&path, &items).await?;
+
+                    let (index, begin) = find_item(&ends, offset)
+                        .ok_or_else(|| HkeyError::Range(start + ends.last().unwrap_or(&0)))?;
+
+                    start += begin;
+                    offset -= begin;
+                    limit = (ends[index] - begin).min(limit);
+                    path.1.push(index);
+
+                    items[index].clone()
+                }
+                leaf => {
+                    let data = leaf.resolve_async(self.store.clone()).await?;
+                    let data = data.slice(..data.len().min(limit));
+
+                    if offset >= data.len() {
+                        Err(HkeyError::Range(data.len()))?;
+                    }
+
+                    retain_path(&visited, &mut self.nodes, &mut self.lists, &mut self.ends);
+
+                    return Ok(Segment { start, data });
+                }
+            }
+        }
+    }
+
+    /// Measures `hkey`, found at `path`, fetching leaves only if [`Hkey::len_hint`] cannot tell
+    /// their length.
+    fn length_of(
+        &mut self,
+        path: ListPath,
+        hkey: Hkey,
+    ) -> Pin<Box<dyn Future<Output = Result<usize, S::Error>> + Send + '_>> {
+        Box::pin(async move {
+            if let Some(length) = hkey.len_hint() {
+                return Ok(length);
+            }
+
+            let length = match hkey {
+                Hkey::LongHkey(lhkey) => self.expand(&lhkey).await?.size(),
+                Hkey::ListRef(hash, key) => {
+                    let list = self.list(&hash, &key).await?;
+
+                    self.length_of((Some(hash), Vec::new()), list).await?
+                }
+                Hkey::List(items) => self
+                    .item_ends(&path, &items)
+                    .await?
+                    .last()
+                    .copied()
+                    .unwrap_or_default(),
+                leaf => leaf.len_async(self.store.clone()).await?,
+            };
+
+            Ok(length)
+        })
+    }
+
+    /// Returns the offsets at which the items of the list at `path` end.
+    async fn item_ends(
+        &mut self,
+        path: &ListPath,
+        items: &[Hkey],
+    ) -> Result<Arc<[usize]>, S::Error> {
+        if let Some(ends) = self.ends.get(path) {
+            return Ok(ends.clone());
+        }
+
+        let mut ends = Vec::with_capacity(items.len());
+        let mut end = 0;
+
+        for (index, item) in items.iter().enumerate() {
+            end += self
+                .length_of(child_path(path, index), item.clone())
+                .await?;
+            ends.push(end);
+        }
+
+        let ends: Arc<[usize]> = ends.into();
+
+        self.ends.insert(path.clone(), ends.clone());
+
+        Ok(ends)
+    }
+
+    async fn expand(&mut self, lhkey: &LongHkey) -> Result<LongHkeyExpanded, S::Error> {
+        if let Some(expanded) = self.nodes.get(lhkey.hash_ref()) {
+            return Ok(expanded.clone());
+        }
+
+        let expanded = lhkey.expand_async(self.store.clone()).await?;
+
+        self.nodes.insert(lhkey.hash(), expanded.clone());
+
+        Ok(expanded)
+    }
+
+    async fn list(&mut self, hash: &Hash, key: &Hash) -> Result<Hkey, S::Error> {
+        if let Some(list) = self.lists.get(hash) {
+            return Ok(list.clone());
+        }
+
+        let chunk = self.store.get(hash).await?;
+        let decrypted = chunk.decrypt(key)?;
+        let list = Hkey::parse(decrypted.data_ref()).map_err(HkeyError::Construction)?;
+
+        self.lists.insert(*hash, list.clone());
+
+        Ok(list)
+    }
+}
+
+impl<S> AsyncRead for AsyncHkeyReader<S>
+where
+    S: AsyncStore,
+    S::Error: std::error::Error + Sync + 'static,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            let position = this.position;
+            let state = ready!(this.poll_pending(cx)).map_err(io::Error::other)?;
+
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            if let Some(count) = state
+                .segment
+                .as_ref()
+                .and_then(|segment| segment.read_at(position, buf))
+            {
+                this.position += count;
+
+                return Poll::Ready(Ok(count));
+            }
+
+            if state.length.is_some_and(|length| position >= length) {
+                return Poll::Ready(Ok(0));
+            }
+
+            this.start_load(position);
+        }
+    }
+}
+
+impl<S> AsyncSeek for AsyncHkeyReader<S>
+where
+    S: AsyncStore,
+    S::Error: std::error::Error + Sync + 'static,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        target: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        loop {
+            let state = ready!(this.poll_pending(cx)).map_err(io::Error::other)?;
+
+            if matches!(target, SeekFrom::End(_)) && state.length.is_none() {
+                this.start_length();
+
+                continue;
+            }
+
+            let length = state.length;
+
+            this.position = seek_position(this.position, length, target)?;
+
+            return Poll::Ready(Ok(this.position as u64));
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{io::SeekFrom, sync::Arc};
+
+    use futures::{executor::block_on, AsyncReadExt, AsyncSeekExt};
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncHkeyReader, AsyncStore, Hkey, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn reads_and_seeks() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(200_000);
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let mut reader = AsyncHkeyReader::new(store.clone(), hkey);
+            let mut buffer = Vec::new();
+
+            reader
+                .read_to_end(&mut buffer)
+                .await
+                .expect("Failed to read");
+
+            assert_eq!(buffer, data);
+            assert_eq!(reader.length().await.expect("Failed to measure"), 200_000);
+
+            let mut window = [0; 100];
+
+            reader
+                .seek(SeekFrom::Start(65_500))
+                .await
+                .expect("Failed to seek");
+            reader
+                .read_exact(&mut window)
+                .await
+                .expect("Failed to read");
+
+            assert_eq!(window, data[65_500..65_600]);
+
+            let position = reader
+                .seek(SeekFrom::End(-5))
+                .await
+                .expect("Failed to seek");
+            let mut tail = Vec::new();
+
+            reader.read_to_end(&mut tail).await.expect("Failed to read");
+
+            assert_eq!(position, 199_995);
+            assert_eq!(tail, &data[199_995..]);
+        });
+    }
+
+    #[test]
+    fn seeks_from_the_end_before_reading() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(5_000);
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let mut reader = AsyncHkeyReader::new(store.clone(), hkey);
+            let mut tail = Vec::new();
+
+            reader
+                .seek(SeekFrom::End(-1000))
+                .await
+                .expect("Failed to seek");
+            reader.read_to_end(&mut tail).await.expect("Failed to read");
+
+            assert_eq!(tail, &data[4_000..]);
+        });
+    }
+
+    #[test]
+    fn reads_nested_lists() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(12_000);
+
+        block_on(async {
+            let mut items = Vec::new();
+
+            for chunk in data.chunks(300) {
+                let hkey = store
+                    .put(Bytes::copy_from_slice(chunk))
+                    .await
+                    .expect("Failed to put");
+
+                items.push(hkey);
+            }
+
+            let nested: Arc<[Hkey]> = items.split_off(30).into();
+
+            items.push(Hkey::List(nested));
+
+            let mut reader = AsyncHkeyReader::new(store.clone(), Hkey::List(items.into()));
+            let mut buffer = Vec::new();
+
+            reader
+                .read_to_end(&mut buffer)
+                .await
+                .expect("Failed to read");
+
+            assert_eq!(buffer, data);
+
+            let mut window = [0; 20];
+
+            reader
+                .seek(SeekFrom::Start(9_290))
+                .await
+                .expect("Failed to seek");
+            reader
+                .read_exact(&mut window)
+                .await
+                .expect("Failed to read");
+
+            assert_eq!(window, data[9_290..9_310]);
+        });
+    }
+
+    #[test]
+    fn caches_only_the_path_to_the_cursor() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(3_000_000);
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let mut reader = AsyncHkeyReader::new(store.clone(), hkey);
+            let mut buffer = vec![0; 10_000];
+            let mut read = Vec::new();
+
+            loop {
+                let count = reader.read(&mut buffer).await.expect("Failed to read");
+
+                if count == 0 {
+                    break;
+                }
+
+                read.extend_from_slice(&buffer[..count]);
+
+                let state = reader.state.as_ref().expect("Expected an idle reader");
+
+                assert!(state.nodes.len() + state.lists.len() <= 3);
+            }
+
+            assert_eq!(read, data);
+        });
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use ps_datachunk::{DataChunk, OwnedDataChunk};
+use ps_hash::Hash;
+use ps_promise::Promise;
+
+use crate::{
+    store::caching::CacheCore, AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove,
+    CachingStoreError, PutPolicy, TenantSecret,
+};
+
+/// Asynchronous counterpart of [`CachingStore`](crate::CachingStore), e.g. for keeping an
+/// [`InMemoryAsyncStore`](crate::InMemoryAsyncStore) in front of a
+/// [`MixedStore`](crate::MixedStore).
+#[derive(Clone, Debug)]
+pub struct AsyncCachingStore<F, S> {
+    fast: F,
+    slow: S,
+    core: Arc<CacheCore>,
+}
+
+impl<F, S> AsyncCachingStore<F, S> {
+    /// Creates a cache keeping at most `budget` bytes of chunks in `fast`.
+    pub fn new(fast: F, slow: S, budget: usize) -> Self {
+        Self {
+            fast,
+            slow,
+            core: Arc::new(CacheCore::new(budget)),
+        }
+    }
+
+    pub const fn fast(&self) -> &F {
+        &self.fast
+    }
+
+    pub const fn slow(&self) -> &S {
+        &self.slow
+    }
+
+    /// Returns the number of chunks served from the fast store.
+    pub fn hits(&self) -> u64 {
+        self.core.hits()
+    }
+
+    /// Returns the number of chunks fetched from the slow store.
+    pub fn misses(&self) -> u64 {
+        self.core.misses()
+    }
+
+    /// Returns the total size of the chunks this cache keeps in the fast store.
+    pub fn cached_bytes(&self) -> usize {
+        self.core.cached_bytes()
+    }
+}
+
+impl<F, S> AsyncCachingStore<F, S>
+where
+    F: AsyncStoreRemove,
+    S: AsyncStore,
+{
+    /// Starts removing the evicted chunks from the fast store.
+    fn evict(&self, evicted: &[Hash]) -> Vec<Promise<bool, F::Error>> {
+        evicted.iter().map(|hash| self.fast.remove(hash)).collect()
+    }
+
+    async fn fetch(
+        self,
+        hash: Hash,
+    ) -> Result<OwnedDataChunk, CachingStoreError<F::Error, S::Error>> {
+        if let Ok(chunk) = self.fast.get(&hash).await {
+            self.core.hit(&hash);
+
+            return Ok(chunk.into_owned());
+        }
+
+        self.core.miss();
+
+        let chunk = self
+            .slow
+            .get(&hash)
+            .await
+            .map_err(CachingStoreError::Slow)?
+            .into_owned();
+        let size = chunk.data_ref().len();
+
+        if self.core.fits(size) && self.fast.put_encrypted(chunk.clone()).await.is_ok() {
+            for removal in self.evict(&self.core.insert(hash, size)) {
+                let _ = removal.await;
+            }
+        }
+
+        Ok(chunk)
+    }
+}
+
+impl<F, S> AsyncStore for AsyncCachingStore<F, S>
+where
+    F: AsyncStoreRemove,
+    S: AsyncStore,
+{
+    type Chunk = OwnedDataChunk;
+    type Error = CachingStoreError<F::Error, S::Error>;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        Promise::lazy(self.clone().fetch(*hash))
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        let promise = self.slow.put_encrypted(chunk);
+
+        Promise::lazy(async move { promise.await.map_err(CachingStoreError::Slow) })
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.slow.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.slow.secret()
+    }
+}
+
+impl<F, S> AsyncStoreContains for AsyncCachingStore<F, S>
+where
+    F: AsyncStoreRemove,
+    S: AsyncStoreContains,
+{
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        let promise = self.slow.contains(hash);
+
+        Promise::lazy(async move { promise.await.map_err(CachingStoreError::Slow) })
+    }
+}
+
+impl<F, S> AsyncStoreIter for AsyncCachingStore<F, S>
+where
+    F: AsyncStoreRemove,
+    S: AsyncStoreIter,
+{
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        let promise = self.slow.hashes();
+
+        Promise::lazy(async move { promise.await.map_err(CachingStoreError::Slow) })
+    }
+}
+
+/// Removes the chunk from both stores, returning whether the slow store held it.
+impl<F, S> AsyncStoreRemove for AsyncCachingStore<F, S>
+where
+    F: AsyncStoreRemove,
+    S: AsyncStoreRemove,
+{
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.core.forget(hash);
+
+        let fast = self.fast.remove(hash);
+        let slow = self.slow.remove(hash);
+
+        Promise::lazy(async move {
+            fast.await.map_err(CachingStoreError::Fast)?;
+            slow.await.map_err(CachingStoreError::Slow)
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncCachingStore, AsyncStore, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn populates_fast_store_on_miss() {
+        let store = AsyncCachingStore::new(
+            InMemoryAsyncStore::default(),
+            InMemoryAsyncStore::default(),
+            1 << 20,
+        );
+        let data = sequential_bytes(100_000);
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let resolved = hkey
+                .resolve_async(store.clone())
+                .await
+                .expect("Failed to resolve");
+
+            assert_eq!(resolved, data);
+            assert_eq!(store.hits(), 0);
+
+            let misses = store.misses();
+
+            let resolved = hkey
+                .resolve_async(store.clone())
+                .await
+                .expect("Failed to resolve");
+
+            assert_eq!(resolved, data);
+            assert_eq!((store.hits(), store.misses()), (misses, misses));
+        });
+    }
+}
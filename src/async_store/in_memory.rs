@@ -4,10 +4,10 @@ use ps_promise::{Promise, PromiseRejection, TaskFailure};
 
 use crate::{
     store::in_memory::{InMemoryStore, InMemoryStoreError},
-    HkeyError, Store,
+    HkeyError, Store, StoreContains, StoreIter, StoreRemove,
 };
 
-use super::AsyncStore;
+use super::{AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove};
 
 #[derive(Clone, Debug, Default)]
 pub struct InMemoryAsyncStore {
@@ -33,6 +33,33 @@ impl AsyncStore for InMemoryAsyncStore {
     }
 }
 
+impl AsyncStoreContains for InMemoryAsyncStore {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        match self.store.contains(hash) {
+            Ok(contains) => Promise::resolve(contains),
+            Err(err) => Promise::reject(err.into()),
+        }
+    }
+}
+
+impl AsyncStoreIter for InMemoryAsyncStore {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        match self.store.hashes() {
+            Ok(hashes) => Promise::resolve(hashes),
+            Err(err) => Promise::reject(err.into()),
+        }
+    }
+}
+
+impl AsyncStoreRemove for InMemoryAsyncStore {
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        match self.store.remove(hash) {
+            Ok(removed) => Promise::resolve(removed),
+            Err(err) => Promise::reject(err.into()),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum InMemoryAsyncStoreError {
     #[error(transparent)]
@@ -1,17 +1,46 @@
-use std::{ops::Deref, sync::Arc};
+use std::{collections::HashSet, ops::Deref, sync::Arc};
 
 use parking_lot::RwLock;
 use ps_datachunk::{DataChunk, DataChunkError, OwnedDataChunk};
 use ps_hash::Hash;
 use ps_promise::{Promise, PromiseRejection};
 
-use crate::{store::combined::DynStore, AsyncStore, HkeyError, Store};
+use crate::{
+    store::combined::{unsupported, DynStore, Extended},
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove, HkeyError, PutPolicy, Store,
+    StoreContains, StoreIter, StoreRemove, TenantSecret,
+};
 
 pub trait DynAsyncStore: Send + Sync {
     type Error: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'static;
 
     fn get(&self, hash: Hash) -> Promise<OwnedDataChunk, Self::Error>;
     fn put_encrypted(&self, chunk: OwnedDataChunk) -> Promise<(), Self::Error>;
+
+    /// Returns the store's [`AsyncStore::policy`].
+    fn policy(&self) -> PutPolicy {
+        PutPolicy::default()
+    }
+
+    /// Returns the store's [`AsyncStore::secret`].
+    fn secret(&self) -> Option<TenantSecret> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`AsyncStoreContains`].
+    fn contains(&self, _hash: Hash) -> Option<Promise<bool, Self::Error>> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`AsyncStoreIter`].
+    fn hashes(&self) -> Option<Promise<Vec<Hash>, Self::Error>> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`AsyncStoreRemove`].
+    fn remove(&self, _hash: Hash) -> Option<Promise<bool, Self::Error>> {
+        None
+    }
 }
 
 impl<T> DynAsyncStore for T
@@ -29,6 +58,51 @@ where
     fn put_encrypted(&self, chunk: OwnedDataChunk) -> Promise<(), Self::Error> {
         AsyncStore::put_encrypted(self, chunk)
     }
+
+    fn policy(&self) -> PutPolicy {
+        AsyncStore::policy(self)
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        AsyncStore::secret(self)
+    }
+}
+
+impl<A> DynAsyncStore for Extended<A>
+where
+    A: AsyncStore + AsyncStoreContains + AsyncStoreIter + AsyncStoreRemove + Send + Sync + 'static,
+{
+    type Error = A::Error;
+
+    fn get(&self, hash: Hash) -> Promise<OwnedDataChunk, Self::Error> {
+        let store = self.0.clone();
+
+        Promise::lazy(async move { Ok(AsyncStore::get(&store, &hash).await?.into_owned()) })
+    }
+
+    fn put_encrypted(&self, chunk: OwnedDataChunk) -> Promise<(), Self::Error> {
+        AsyncStore::put_encrypted(&self.0, chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        AsyncStore::policy(&self.0)
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        AsyncStore::secret(&self.0)
+    }
+
+    fn contains(&self, hash: Hash) -> Option<Promise<bool, Self::Error>> {
+        Some(AsyncStoreContains::contains(&self.0, &hash))
+    }
+
+    fn hashes(&self) -> Option<Promise<Vec<Hash>, Self::Error>> {
+        Some(AsyncStoreIter::hashes(&self.0))
+    }
+
+    fn remove(&self, hash: Hash) -> Option<Promise<bool, Self::Error>> {
+        Some(AsyncStoreRemove::remove(&self.0, &hash))
+    }
 }
 
 #[derive(Default)]
@@ -37,6 +111,11 @@ pub struct MixedStoreInner<E: MixedStoreError> {
     pub stores: Vec<Box<dyn DynStore<Error = E>>>,
 }
 
+/// Combines synchronous and asynchronous stores, trying the synchronous ones first.
+///
+/// Writes are tried on the first store first, so its [`PutPolicy`] and [`TenantSecret`] apply
+/// to the whole combination. Through [`AsyncStore`], that is the first asynchronous store if
+/// there are no synchronous ones.
 #[derive(Clone, Default)]
 pub struct MixedStore<E: MixedStoreError, const WRITE_TO_ALL: bool> {
     inner: Arc<RwLock<MixedStoreInner<E>>>,
@@ -102,6 +181,44 @@ impl<E: MixedStoreError, const WRITE_TO_ALL: bool> MixedStore<E, WRITE_TO_ALL> {
             .extend(iter.into_iter().map(|s| Box::new(s) as _));
     }
 
+    /// Like [`push_sync`](Self::push_sync), but keeps the store's extension traits.
+    pub fn push_sync_with_extensions<S>(&mut self, store: S)
+    where
+        S: Store<Error = E> + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+    {
+        self.write().stores.push(Box::new(Extended(store)));
+    }
+
+    /// Like [`push_async`](Self::push_async), but keeps the store's extension traits.
+    pub fn push_async_with_extensions<A>(&mut self, store: A)
+    where
+        A: AsyncStore<Error = E> + AsyncStoreContains + AsyncStoreIter + AsyncStoreRemove + Sync,
+    {
+        self.write().async_stores.push(Box::new(Extended(store)));
+    }
+
+    /// Like [`extend_sync`](Self::extend_sync), but keeps the stores' extension traits.
+    pub fn extend_sync_with_extensions<S, I>(&mut self, iter: I)
+    where
+        S: Store<Error = E> + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+        I: IntoIterator<Item = S>,
+    {
+        self.write()
+            .stores
+            .extend(iter.into_iter().map(|s| Box::new(Extended(s)) as _));
+    }
+
+    /// Like [`extend_async`](Self::extend_async), but keeps the stores' extension traits.
+    pub fn extend_async_with_extensions<A, I>(&mut self, iter: I)
+    where
+        A: AsyncStore<Error = E> + AsyncStoreContains + AsyncStoreIter + AsyncStoreRemove + Sync,
+        I: IntoIterator<Item = A>,
+    {
+        self.write()
+            .async_stores
+            .extend(iter.into_iter().map(|s| Box::new(Extended(s)) as _));
+    }
+
     #[must_use]
     pub fn write_to_all(self) -> MixedStore<E, true> {
         MixedStore { inner: self.inner }
@@ -112,6 +229,37 @@ impl<E: MixedStoreError, const WRITE_TO_ALL: bool> MixedStore<E, WRITE_TO_ALL> {
         MixedStore { inner: self.inner }
     }
 
+    fn policy_sync(&self) -> PutPolicy {
+        self.read()
+            .stores
+            .first()
+            .map_or_else(PutPolicy::default, |store| store.policy())
+    }
+
+    fn secret_sync(&self) -> Option<TenantSecret> {
+        self.read().stores.first().and_then(|store| store.secret())
+    }
+
+    fn policy_async(&self) -> PutPolicy {
+        let guard = self.read();
+
+        match (guard.stores.first(), guard.async_stores.first()) {
+            (Some(store), _) => store.policy(),
+            (None, Some(store)) => store.policy(),
+            (None, None) => PutPolicy::default(),
+        }
+    }
+
+    fn secret_async(&self) -> Option<TenantSecret> {
+        let guard = self.read();
+
+        match (guard.stores.first(), guard.async_stores.first()) {
+            (Some(store), _) => store.secret(),
+            (None, Some(store)) => store.secret(),
+            (None, None) => None,
+        }
+    }
+
     fn get_sync(&self, hash: &Hash) -> Result<OwnedDataChunk, E> {
         let mut last_err = None;
 
@@ -125,6 +273,164 @@ impl<E: MixedStoreError, const WRITE_TO_ALL: bool> MixedStore<E, WRITE_TO_ALL> {
         Err(last_err.unwrap_or_else(E::no_stores))
     }
 
+    fn contains_sync(&self, hash: &Hash) -> Result<bool, E> {
+        let mut last_err = None;
+
+        for s in &self.read().stores {
+            match s.contains(hash) {
+                None => last_err = Some(unsupported("StoreContains")),
+                Some(Ok(false)) => {}
+                Some(Ok(true)) => return Ok(true),
+                Some(Err(err)) => last_err = Some(err),
+            }
+        }
+
+        last_err.map_or(Ok(false), Err)
+    }
+
+    fn hashes_sync(&self) -> Result<Vec<Hash>, E> {
+        let mut seen = HashSet::new();
+        let mut hashes = Vec::new();
+
+        for s in &self.read().stores {
+            let batch = s
+                .hashes()
+                .unwrap_or_else(|| Err(unsupported("StoreIter")))?;
+
+            for hash in batch {
+                if seen.insert(hash) {
+                    hashes.push(hash);
+                }
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    fn remove_sync(&self, hash: &Hash) -> Result<bool, E> {
+        let mut removed = false;
+        let mut first_err = None;
+
+        for s in &self.read().stores {
+            match s.remove(hash) {
+                None => {
+                    first_err.get_or_insert_with(|| unsupported("StoreRemove"));
+                }
+                Some(Ok(was_present)) => removed |= was_present,
+                Some(Err(err)) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        first_err.map_or(Ok(removed), Err)
+    }
+
+    fn contains_async(&self, hash: &Hash) -> Promise<bool, E> {
+        let sync_result = self.contains_sync(hash);
+
+        if let Ok(true) = sync_result {
+            return Promise::resolve(true);
+        }
+
+        let promises: Vec<Promise<bool, E>> = self
+            .read()
+            .async_stores
+            .iter()
+            .map(|store| {
+                store
+                    .contains(*hash)
+                    .unwrap_or_else(|| Promise::reject(unsupported("AsyncStoreContains")))
+            })
+            .collect();
+
+        Promise::lazy(async move {
+            let mut last_err = sync_result.err();
+
+            for result in Promise::all_settled(promises).await? {
+                match result {
+                    Ok(true) => return Ok(true),
+                    Ok(false) => {}
+                    Err(err) => last_err = Some(err),
+                }
+            }
+
+            last_err.map_or(Ok(false), Err)
+        })
+    }
+
+    fn hashes_async(&self) -> Promise<Vec<Hash>, E> {
+        let sync_hashes = match self.hashes_sync() {
+            Ok(hashes) => hashes,
+            Err(err) => return Promise::reject(err),
+        };
+
+        let promises: Vec<Promise<Vec<Hash>, E>> = self
+            .read()
+            .async_stores
+            .iter()
+            .map(|store| {
+                store
+                    .hashes()
+                    .unwrap_or_else(|| Promise::reject(unsupported("AsyncStoreIter")))
+            })
+            .collect();
+
+        Promise::lazy(async move {
+            let mut seen = HashSet::new();
+            let mut hashes = Vec::new();
+
+            let mut push = |batch: Vec<Hash>| {
+                for hash in batch {
+                    if seen.insert(hash) {
+                        hashes.push(hash);
+                    }
+                }
+            };
+
+            push(sync_hashes);
+
+            for result in Promise::all_settled(promises).await? {
+                push(result?);
+            }
+
+            Ok(hashes)
+        })
+    }
+
+    fn remove_async(&self, hash: &Hash) -> Promise<bool, E> {
+        let sync_result = self.remove_sync(hash);
+
+        let promises: Vec<Promise<bool, E>> = self
+            .read()
+            .async_stores
+            .iter()
+            .map(|store| {
+                store
+                    .remove(*hash)
+                    .unwrap_or_else(|| Promise::reject(unsupported("AsyncStoreRemove")))
+            })
+            .collect();
+
+        Promise::lazy(async move {
+            let (mut removed, mut first_err) = match sync_result {
+                Ok(removed) => (removed, None),
+                Err(err) => (false, Some(err)),
+            };
+
+            for result in Promise::all_settled(promises).await? {
+                match result {
+                    Ok(was_present) => removed |= was_present,
+                    Err(err) => {
+                        first_err.get_or_insert(err);
+                    }
+                }
+            }
+
+            first_err.map_or(Ok(removed), Err)
+        })
+    }
+
     fn get_async(&self, hash: &Hash) -> Promise<OwnedDataChunk, E> {
         let mut last_err = E::no_stores();
         let guard = self.read();
@@ -161,6 +467,14 @@ impl<E: MixedStoreError> Store for MixedStore<E, true> {
         self.get_sync(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy_sync()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret_sync()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
         let guard = self.read();
 
@@ -186,6 +500,14 @@ impl<E: MixedStoreError> Store for MixedStore<E, false> {
         self.get_sync(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy_sync()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret_sync()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
         let mut last_err = E::no_stores();
 
@@ -208,6 +530,14 @@ impl<E: MixedStoreError> AsyncStore for MixedStore<E, true> {
         self.get_async(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy_async()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret_async()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
         let this = self.clone();
         let chunk = chunk.into_owned();
@@ -250,6 +580,14 @@ impl<E: MixedStoreError> AsyncStore for MixedStore<E, false> {
         self.get_async(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy_async()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret_async()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
         let this = self.clone();
         let chunk = chunk.into_owned();
@@ -297,6 +635,83 @@ impl<E: MixedStoreError> AsyncStore for MixedStore<E, false> {
     }
 }
 
+/// Reports whether any of the synchronous stores holds the chunk.
+///
+/// Fails if none of the stores that can tell holds it, but some were added without their
+/// extension traits.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> StoreContains for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.contains_sync(hash)
+    }
+}
+
+/// Enumerates the chunks of the synchronous stores, each hash once.
+///
+/// Fails if any of them was added without its extension traits.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> StoreIter for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.hashes_sync()
+    }
+}
+
+/// Removes the chunk from every synchronous store, returning whether any of them held it.
+///
+/// Fails if any of them was added without its extension traits, as the chunk may remain in
+/// them.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> StoreRemove for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.remove_sync(hash)
+    }
+}
+
+/// Reports whether any of the stores holds the chunk.
+///
+/// Fails if none of the stores that can tell holds it, but some were added without their
+/// extension traits.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> AsyncStoreContains
+    for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: AsyncStore<Error = E>,
+{
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.contains_async(hash)
+    }
+}
+
+/// Enumerates the chunks of all stores, each hash once.
+///
+/// Fails if any of the stores was added without its extension traits.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> AsyncStoreIter for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: AsyncStore<Error = E>,
+{
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        self.hashes_async()
+    }
+}
+
+/// Removes the chunk from every store, returning whether any of them held it.
+///
+/// Fails if any of the stores was added without its extension traits, as the chunk may remain
+/// in them.
+impl<E: MixedStoreError, const WRITE_TO_ALL: bool> AsyncStoreRemove for MixedStore<E, WRITE_TO_ALL>
+where
+    Self: AsyncStore<Error = E>,
+{
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.remove_async(hash)
+    }
+}
+
 pub trait MixedStoreError:
     Clone + From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'static
 {
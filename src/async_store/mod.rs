@@ -1,3 +1,4 @@
+pub mod caching;
 pub mod in_memory;
 pub mod mixed;
 
@@ -7,8 +8,9 @@ use ps_hash::Hash;
 use ps_promise::{Promise, PromiseRejection};
 
 use crate::{
-    constants::{MAX_DECRYPTED_SIZE, MAX_ENCRYPTED_SIZE, MAX_SIZE_RAW},
-    Hkey, HkeyError, LongHkeyExpanded,
+    constants::{MAX_DECRYPTED_SIZE, MAX_ENCRYPTED_SIZE},
+    store::keyed::encrypt,
+    Hkey, HkeyError, LongHkeyExpanded, PutPolicy, TenantSecret,
 };
 
 pub trait AsyncStore
@@ -22,8 +24,24 @@ where
 
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error>;
 
+    /// Asynchronous counterpart of [`Store::policy`](crate::Store::policy).
+    fn policy(&self) -> PutPolicy {
+        PutPolicy::default()
+    }
+
+    /// Asynchronous counterpart of [`Store::secret`](crate::Store::secret).
+    fn secret(&self) -> Option<TenantSecret> {
+        None
+    }
+
+    /// Asynchronous counterpart of [`Store::put`](crate::Store::put).
     fn put(&self, data: Bytes) -> Promise<Hkey, Self::Error> {
-        if data.len() <= MAX_SIZE_RAW {
+        self.put_with(data, self.policy())
+    }
+
+    /// Asynchronous counterpart of [`Store::put_with`](crate::Store::put_with).
+    fn put_with(&self, data: Bytes, policy: PutPolicy) -> Promise<Hkey, Self::Error> {
+        if data.len() <= policy.inline_threshold() {
             return match Hkey::from_raw(&data) {
                 Ok(hkey) => Promise::resolve(hkey),
                 Err(err) => Promise::reject(Self::Error::from(HkeyError::Construction(err))),
@@ -31,9 +49,14 @@ where
         }
 
         let this = self.clone();
+        let secret = self.secret();
 
         Promise::lazy(async move {
-            if data.len() <= MAX_ENCRYPTED_SIZE && validate_ecc(&data) {
+            if policy.store_direct()
+                && secret.is_none()
+                && data.len() <= MAX_ENCRYPTED_SIZE
+                && validate_ecc(&data)
+            {
                 let chunk = OwnedDataChunk::from_bytes(data)?;
                 let hash = chunk.hash();
 
@@ -41,15 +64,14 @@ where
 
                 Ok(Hkey::Direct(hash))
             } else if data.len() <= MAX_DECRYPTED_SIZE {
-                let chunk = OwnedDataChunk::from_bytes(data)?;
-                let encrypted = chunk.encrypt()?;
+                let encrypted = encrypt(&data, secret.as_ref())?;
                 let hkey = Hkey::Encrypted(encrypted.hash(), encrypted.key());
 
                 this.put_encrypted(encrypted).await?;
 
                 Ok(hkey)
             } else {
-                LongHkeyExpanded::from_blob_async(this.clone(), &data)
+                LongHkeyExpanded::from_blob_with_async(this.clone(), &data, policy)
                     .await?
                     .shrink_async(this)
                     .await
@@ -57,3 +79,21 @@ where
         })
     }
 }
+
+/// An [`AsyncStore`] that can tell whether it holds a chunk without fetching it.
+pub trait AsyncStoreContains: AsyncStore {
+    /// Returns whether a chunk with this hash is in this store.
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error>;
+}
+
+/// An [`AsyncStore`] that can enumerate the chunks it holds.
+pub trait AsyncStoreIter: AsyncStore {
+    /// Returns the hashes of all chunks in this store.
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error>;
+}
+
+/// An [`AsyncStore`] that chunks can be deleted from.
+pub trait AsyncStoreRemove: AsyncStore {
+    /// Removes the chunk with this hash, returning whether it was present.
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error>;
+}
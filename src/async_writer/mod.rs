@@ -0,0 +1,364 @@
+use std::{
+    future::Future,
+    io,
+    mem::take,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::io::AsyncWrite;
+use ps_datachunk::Bytes;
+
+use crate::{
+    constants::MAX_ENCRYPTED_SIZE,
+    long::LHKEY_PART_COUNT,
+    writer::{combine, depth_of, level_length, ranged},
+    AsyncStore, Hkey, HkeyError, LongHkeyExpanded, PutPolicy,
+};
+
+type Pending<S, E> = Pin<Box<dyn Future<Output = (Box<WriterState<S>>, Result<(), E>)> + Send>>;
+
+/// Asynchronous counterpart of [`HkeyWriter`](crate::HkeyWriter), producing the same [`Hkey`]
+/// as [`AsyncStore::put`] would for everything written.
+///
+/// Segments are stored in the background of subsequent writes; [`AsyncHkeyWriter::finish`]
+/// waits for them before storing the rest.
+pub struct AsyncHkeyWriter<S: AsyncStore> {
+    state: Option<Box<WriterState<S>>>,
+    pending: Option<Pending<S, S::Error>>,
+}
+
+struct WriterState<S: AsyncStore> {
+    store: S,
+    policy: PutPolicy,
+    buffer: Vec<u8>,
+    length: usize,
+    levels: Vec<Vec<Hkey>>,
+}
+
+impl<S: AsyncStore> AsyncHkeyWriter<S> {
+    pub fn new(store: S) -> Self {
+        let state = WriterState {
+            policy: store.policy(),
+            store,
+            buffer: Vec::new(),
+            length: 0,
+            levels: Vec::new(),
+        };
+
+        Self {
+            state: Some(Box::new(state)),
+            pending: None,
+        }
+    }
+
+    /// Appends `data`, storing every segment that is followed by further input.
+    pub async fn push(&mut self, data: &[u8]) -> Result<(), S::Error> {
+        let mut state = self.settle().await?;
+
+        let result = state.push(data).await;
+
+        self.state = Some(state);
+
+        result
+    }
+
+    /// Stores whatever remains buffered and returns the shrunk [`Hkey`] of everything written.
+    pub async fn finish(mut self) -> Result<Hkey, S::Error> {
+        self.settle().await?.finish().await
+    }
+
+    /// Waits for the segments being stored, and takes the state they return.
+    async fn settle(&mut self) -> Result<Box<WriterState<S>>, S::Error> {
+        if let Some(pending) = self.pending.take() {
+            let (state, result) = pending.await;
+
+            self.state = Some(state);
+
+            result?;
+        }
+
+        Ok(self.state.take().ok_or(HkeyError::WriterPoisoned)?)
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        if let Some(pending) = &mut self.pending {
+            let (state, result) = ready!(pending.as_mut().poll(cx));
+
+            self.pending = None;
+            self.state = Some(state);
+
+            return Poll::Ready(result);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncStore> WriterState<S> {
+    async fn push(&mut self, data: &[u8]) -> Result<(), S::Error> {
+        for piece in data.chunks(self.policy.segment_size()) {
+            self.buffer.extend_from_slice(piece);
+            self.length += piece.len();
+
+            self.store_segments().await?;
+        }
+
+        Ok(())
+    }
+
+    const fn has_segments(&self) -> bool {
+        self.length > MAX_ENCRYPTED_SIZE && self.buffer.len() > self.policy.segment_size()
+    }
+
+    async fn store_segments(&mut self) -> Result<(), S::Error> {
+        // Input that fits into a single chunk is stored as such by `finish`.
+        let segment_size = self.policy.segment_size();
+
+        while self.has_segments() {
+            let segment = Bytes::copy_from_slice(&self.buffer[..segment_size]);
+            let hkey = self.store.put(segment).await?;
+
+            self.buffer.drain(..segment_size);
+            self.push_to_level(0, hkey).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<Hkey, S::Error> {
+        if self.length <= MAX_ENCRYPTED_SIZE {
+            return self.store.put(take(&mut self.buffer).into()).await;
+        }
+
+        let remainder_length = self.buffer.len();
+        let mut remainder = self.store.put(take(&mut self.buffer).into()).await?;
+
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+
+        // The final segment lies below every level, so none of them may remain full.
+        let mut level = 0;
+
+        while level < self.levels.len() {
+            if self.levels[level].len() == LHKEY_PART_COUNT {
+                self.carry(level).await?;
+            }
+
+            level += 1;
+        }
+
+        let policy = self.policy;
+        let leaves = take(&mut self.levels[0]);
+        let mut size = leaves.len() * level_length(policy, 0) + remainder_length;
+        let mut node = LongHkeyExpanded::new(
+            0,
+            size,
+            ranged(
+                leaves,
+                level_length(policy, 0),
+                Some((remainder_length, remainder)),
+            ),
+        );
+
+        for level in 1..self.levels.len() {
+            let items = take(&mut self.levels[level]);
+
+            if items.is_empty() {
+                continue;
+            }
+
+            let remainder_length = size;
+
+            remainder = node.shrink_async(self.store.clone()).await?;
+            size += items.len() * level_length(policy, level);
+            node = LongHkeyExpanded::new(
+                depth_of(level),
+                size,
+                ranged(
+                    items,
+                    level_length(policy, level),
+                    Some((remainder_length, remainder)),
+                ),
+            );
+        }
+
+        node.shrink_async(self.store.clone()).await
+    }
+
+    async fn push_to_level(&mut self, level: usize, hkey: Hkey) -> Result<(), S::Error> {
+        let mut level = level;
+        let mut hkey = hkey;
+
+        loop {
+            if self.levels.len() <= level {
+                self.levels.resize_with(level + 1, Vec::new);
+            }
+
+            if self.levels[level].len() < LHKEY_PART_COUNT {
+                self.levels[level].push(hkey);
+
+                return Ok(());
+            }
+
+            let items = take(&mut self.levels[level]);
+
+            self.levels[level].push(hkey);
+
+            hkey = combine(self.policy, level, items)
+                .shrink_async(self.store.clone())
+                .await?;
+            level += 1;
+        }
+    }
+
+    /// Combines the sixteen subtrees of a full level into one subtree of the level above.
+    async fn carry(&mut self, level: usize) -> Result<(), S::Error> {
+        let items = take(&mut self.levels[level]);
+        let hkey = combine(self.policy, level, items)
+            .shrink_async(self.store.clone())
+            .await?;
+
+        self.push_to_level(level + 1, hkey).await
+    }
+}
+
+impl<S> AsyncWrite for AsyncHkeyWriter<S>
+where
+    S: AsyncStore,
+    S::Error: std::error::Error + Sync + 'static,
+{
+    /// Accepts up to one segment, and starts storing whatever segments it completes.
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_pending(cx)).map_err(io::Error::other)?;
+
+        let Some(mut state) = this.state.take() else {
+            return Poll::Ready(Err(io::Error::other(HkeyError::WriterPoisoned)));
+        };
+
+        let accepted = buf.len().min(state.policy.segment_size());
+
+        state.buffer.extend_from_slice(&buf[..accepted]);
+        state.length += accepted;
+
+        if state.has_segments() {
+            this.pending = Some(Box::pin(async move {
+                let result = state.store_segments().await;
+
+                (state, result)
+            }));
+        } else {
+            this.state = Some(state);
+        }
+
+        Poll::Ready(Ok(accepted))
+    }
+
+    /// Waits for the segments being stored; the final one is only stored by
+    /// [`AsyncHkeyWriter::finish`].
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_pending(cx).map_err(io::Error::other)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::{executor::block_on, AsyncWriteExt};
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncHkeyWriter, AsyncStore, InMemoryAsyncStore, PolicyStore, PutPolicy};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn assert_matches_put(len: usize, write_size: usize) {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(len);
+
+        block_on(async {
+            let mut writer = AsyncHkeyWriter::new(store.clone());
+
+            for piece in data.chunks(write_size) {
+                writer.write_all(piece).await.expect("Failed to write");
+            }
+
+            writer.flush().await.expect("Failed to flush");
+
+            let hkey = writer.finish().await.expect("Failed to finish");
+            let expected = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            assert_eq!(hkey, expected, "length {len}, writes of {write_size}");
+        });
+    }
+
+    #[test]
+    fn matches_put() {
+        for len in [0, 10, 4096, 4629, 4700, 65_536, 65_537, 70_000] {
+            assert_matches_put(len, 1000);
+        }
+
+        assert_matches_put(16 * 65_536 + 4096 * 3 + 5, 50_000);
+    }
+
+    #[test]
+    fn follows_segment_size() {
+        let policy = PutPolicy::new()
+            .with_segment_size(1024)
+            .expect("Valid segment size");
+        let store = PolicyStore::new(InMemoryAsyncStore::default(), policy);
+        let data = sequential_bytes(300_000);
+
+        block_on(async {
+            let mut writer = AsyncHkeyWriter::new(store.clone());
+
+            writer.write_all(&data).await.expect("Failed to write");
+
+            assert_eq!(
+                writer.finish().await.expect("Failed to finish"),
+                store
+                    .put(Bytes::copy_from_slice(&data))
+                    .await
+                    .expect("Failed to put")
+            );
+        });
+    }
+
+    #[test]
+    fn push_matches_write() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(100_000);
+
+        block_on(async {
+            let mut pushed = AsyncHkeyWriter::new(store.clone());
+
+            pushed.push(&data[..12_345]).await.expect("Failed to push");
+            pushed.push(&data[12_345..]).await.expect("Failed to push");
+
+            let mut written = AsyncHkeyWriter::new(store.clone());
+
+            written.write_all(&data).await.expect("Failed to write");
+
+            assert_eq!(
+                pushed.finish().await.expect("Failed to finish"),
+                written.finish().await.expect("Failed to finish")
+            );
+        });
+    }
+}
@@ -1,6 +1,11 @@
 pub use ps_hash::HASH_SIZE_COMPACT;
 pub const DOUBLE_HASH_SIZE_COMPACT: usize = 2 * HASH_SIZE_COMPACT;
 
+/// The first byte of the compact form of a [`LongHkey`](crate::LongHkey).
+pub const LONG_HKEY_COMPACT_PREFIX: u8 = b'L';
+/// The length of the compact form of a [`LongHkey`](crate::LongHkey): the prefix, its hash and its key.
+pub const LONG_HKEY_SIZE_COMPACT: usize = DOUBLE_HASH_SIZE_COMPACT + 1;
+
 pub use ps_hash::HASH_SIZE;
 pub const DOUBLE_HASH_SIZE: usize = HASH_SIZE * 2;
 
@@ -15,3 +20,10 @@ pub const MAX_SIZE_BASE64: usize = MAX_SIZE_RAW * 4 / 3;
 
 pub const MAX_DECRYPTED_SIZE: usize = 4096;
 pub const MAX_ENCRYPTED_SIZE: usize = 4629;
+
+/// The version byte leading every [`Hkey::encode_binary`](crate::Hkey::encode_binary) output.
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// The deepest nesting of lists and long hkeys [`Hkey::decode_binary`](crate::Hkey::decode_binary)
+/// accepts.
+pub const MAX_BINARY_DEPTH: usize = 256;
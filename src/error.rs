@@ -1,6 +1,6 @@
 use ps_buffer::BufferError;
 use ps_datachunk::DataChunkError;
-use ps_hash::{HashError, HashValidationError};
+use ps_hash::{Hash, HashError, HashValidationError};
 use std::num::ParseIntError;
 use std::str::Utf8Error;
 use thiserror::Error;
@@ -8,6 +8,8 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum HkeyError {
+    #[error(transparent)]
+    Binary(#[from] HkeyBinaryError),
     #[error(transparent)]
     Buffer(#[from] BufferError),
     #[error(transparent)]
@@ -17,6 +19,8 @@ pub enum HkeyError {
     #[error(transparent)]
     HashValidation(#[from] HashValidationError),
     #[error(transparent)]
+    Parse(#[from] HkeyParseError),
+    #[error(transparent)]
     ParseInt(#[from] ParseIntError),
     #[error(transparent)]
     DataChunk(#[from] DataChunkError),
@@ -35,9 +39,35 @@ pub enum HkeyError {
     Storage,
     #[error("While storing a List or LongHkey, expected Hkey::Encrypted, got {0}")]
     EncryptedIntoListRef(crate::Hkey),
+    #[error("Chunk hash mismatch, expected {expected}, got {actual}")]
+    HashMismatch { expected: Hash, actual: Hash },
+    #[error("The writer is unusable after a write was interrupted")]
+    WriterPoisoned,
+    #[error("The reader is unusable after a read was interrupted")]
+    ReaderPoisoned,
+    #[error("A combined store holds a store added without {0}")]
+    ExtensionUnsupported(&'static str),
+    #[error("Invalid chunking bounds {min_size}/{avg_size}/{max_size}, expected 0 < min <= avg <= max <= 4096")]
+    ChunkingBounds {
+        min_size: usize,
+        avg_size: usize,
+        max_size: usize,
+    },
+    #[error("Invalid inline threshold {0}, expected at most {max}", max = crate::MAX_SIZE_RAW)]
+    InlineThreshold(usize),
+    #[error("A list of {0} bytes in string form exceeds the chunk size of {max} bytes", max = crate::MAX_DECRYPTED_SIZE)]
+    ListTooLong(usize),
+    #[error("Invalid segment size {0}, expected a power of two from 64 to 4096")]
+    SegmentSize(usize),
+    #[error("Chunks of up to {max_size} bytes exceed the segment size of {segment_size} bytes")]
+    ChunkExceedsSegment {
+        max_size: usize,
+        segment_size: usize,
+    },
 }
 
 #[derive(Error, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum HkeyBug {
     #[error("LongHkeyExpanded::resolve_slice returned more bytes than requested")]
@@ -49,6 +79,7 @@ pub enum HkeyBug {
 pub type Result<T, E = HkeyError> = std::result::Result<T, E>;
 
 #[derive(Error, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HkeyConstructionError {
     #[error("Maximum length exceeded.")]
     TooLong,
@@ -60,4 +91,70 @@ pub enum HkeyFromCompactError {
     Construction(#[from] HkeyConstructionError),
     #[error("Hash validation error: {0}")]
     HashValidation(#[from] ps_hash::HashValidationError),
+    #[error("Invalid prefix {0:#04x} in a compact LongHkey")]
+    LongHkeyPrefix(u8),
+}
+
+/// Describes why [`Hkey::parse_strict`](crate::Hkey::parse_strict) rejected its input.
+///
+/// Every variant carries the position of the offending byte.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HkeyParseError {
+    #[error("Unexpected prefix {byte:?} at byte {position}")]
+    UnexpectedPrefix { position: usize, byte: char },
+    #[error("Expected {expected} hash characters at byte {position}, found {found}")]
+    HashLength {
+        position: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Invalid hash character {byte:?} at byte {position}")]
+    InvalidHashCharacter { position: usize, byte: char },
+    #[error("Invalid hash at byte {position}")]
+    InvalidHash { position: usize },
+    #[error("Non-canonical hash, first differing at byte {position}")]
+    NonCanonicalHash { position: usize },
+    #[error("Invalid base64 character {byte:?} at byte {position}")]
+    InvalidBase64Character { position: usize, byte: char },
+    #[error("Non-canonical base64 at byte {position}")]
+    NonCanonicalBase64 { position: usize },
+    #[error("Malformed long hkey at byte {position}: {reason}")]
+    MalformedLongHkey {
+        position: usize,
+        reason: &'static str,
+    },
+    #[error("Expected ',' or ']' at byte {position}, found {found:?}")]
+    ListSeparator {
+        position: usize,
+        found: Option<char>,
+    },
+    #[error("Unexpected trailing input at byte {position}")]
+    TrailingInput { position: usize },
+}
+
+/// Describes why [`Hkey::decode_binary`](crate::Hkey::decode_binary) rejected its input.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum HkeyBinaryError {
+    #[error(transparent)]
+    Construction(#[from] HkeyConstructionError),
+    #[error(transparent)]
+    HashValidation(#[from] HashValidationError),
+    #[error("Unsupported binary format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unexpected end of input at byte {0}")]
+    UnexpectedEnd(usize),
+    #[error("Unknown tag {tag} at byte {position}")]
+    UnknownTag { position: usize, tag: u8 },
+    #[error("Invalid varint at byte {0}")]
+    InvalidVarint(usize),
+    #[error("Invalid UTF-8 in a Base64 value at byte {0}")]
+    InvalidBase64(usize),
+    #[error("Invalid range at byte {0}")]
+    InvalidRange(usize),
+    #[error("Nesting exceeds {0} levels")]
+    TooDeep(usize),
+    #[error("Unexpected trailing bytes at byte {0}")]
+    TrailingBytes(usize),
 }
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+use ps_promise::PromiseRejection;
+
+use crate::{AsyncStoreIter, Hkey, HkeyError, StoreIter};
+
+/// Returns the hashes of the chunks in `store` which none of `roots` depend on.
+///
+/// The store is enumerated before the roots are walked, so chunks stored in the meantime are
+/// never reported.
+pub fn collect_garbage<'a, 'r, C, E, S, I>(roots: I, store: &'a S) -> Result<HashSet<Hash>, E>
+where
+    C: DataChunk,
+    E: From<DataChunkError> + From<HkeyError> + Send,
+    S: StoreIter<Chunk<'a> = C, Error = E> + Sync + 'a,
+    I: IntoIterator<Item = &'r Hkey>,
+{
+    let mut garbage: HashSet<Hash> = store.hashes()?.into_iter().collect();
+    let mut reachable = HashSet::new();
+
+    for root in roots {
+        root.visit_chunks(store, &mut |hash| reachable.insert(*hash))?;
+    }
+
+    garbage.retain(|hash| !reachable.contains(hash));
+
+    Ok(garbage)
+}
+
+/// Asynchronous counterpart of [`collect_garbage`].
+pub async fn collect_garbage_async<'r, C, E, S, I>(roots: I, store: S) -> Result<HashSet<Hash>, E>
+where
+    C: DataChunk + Send,
+    E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+    S: AsyncStoreIter<Chunk = C, Error = E>,
+    I: IntoIterator<Item = &'r Hkey>,
+{
+    let mut garbage: HashSet<Hash> = store.hashes().await?.into_iter().collect();
+    let mut reachable = HashSet::new();
+
+    for root in roots {
+        root.visit_chunks_async(store.clone(), &mut |hash| reachable.insert(*hash))
+            .await?;
+    }
+
+    garbage.retain(|hash| !reachable.contains(hash));
+
+    Ok(garbage)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{
+        collect_garbage, collect_garbage_async, AsyncStore, Hkey, InMemoryAsyncStore,
+        InMemoryStore, Store, StoreIter,
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i + seed) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn reports_unreachable_chunks() {
+        let store = InMemoryStore::default();
+
+        let live = store
+            .put(&sequential_bytes(100_000, 0))
+            .expect("Failed to put");
+        let dead = store
+            .put(&sequential_bytes(100_000, 1))
+            .expect("Failed to put");
+
+        let garbage = collect_garbage([&live], &store).expect("Failed to collect");
+
+        let mut dead_chunks = Vec::new();
+
+        dead.visit_chunks(&store, &mut |hash| {
+            dead_chunks.push(*hash);
+            true
+        })
+        .expect("Failed to visit");
+
+        assert_eq!(garbage.len(), dead_chunks.len());
+        assert!(dead_chunks.iter().all(|hash| garbage.contains(hash)));
+
+        let garbage = collect_garbage([&live, &dead], &store).expect("Failed to collect");
+
+        assert!(garbage.is_empty());
+
+        let garbage = collect_garbage(&[] as &[Hkey], &store).expect("Failed to collect");
+
+        assert_eq!(
+            garbage.len(),
+            store.hashes().expect("Failed to enumerate").len()
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_chunks_async() {
+        let store = InMemoryAsyncStore::default();
+
+        block_on(async {
+            let live = store
+                .put(Bytes::from_owner(sequential_bytes(100_000, 0)))
+                .await
+                .expect("Failed to put");
+            let dead = store
+                .put(Bytes::from_owner(sequential_bytes(3_000, 1)))
+                .await
+                .expect("Failed to put");
+
+            let garbage = collect_garbage_async([&live], store.clone())
+                .await
+                .expect("Failed to collect");
+
+            match dead {
+                Hkey::Encrypted(hash, _) => assert!(garbage.contains(&hash)),
+                other => panic!("Expected Hkey::Encrypted, got {other}"),
+            }
+
+            assert_eq!(garbage.len(), 1);
+        });
+    }
+}
@@ -1,27 +1,51 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::type_complexity)]
+mod async_reader;
 mod async_store;
+mod async_writer;
 mod constants;
 mod error;
+mod gc;
 mod long;
 mod methods;
+mod reader;
+#[cfg(feature = "serde")]
+mod serialization;
 mod store;
+mod writer;
 use arrayvec::ArrayString;
 use arrayvec::ArrayVec;
+pub use async_reader::AsyncHkeyReader;
 pub use async_store::AsyncStore;
+pub use async_store::AsyncStoreContains;
+pub use async_store::AsyncStoreIter;
+pub use async_store::AsyncStoreRemove;
+pub use async_writer::AsyncHkeyWriter;
 pub use constants::*;
+pub use error::HkeyBinaryError;
 pub use error::HkeyBug;
 pub use error::HkeyConstructionError;
 pub use error::HkeyError;
 pub use error::HkeyFromCompactError;
+pub use error::HkeyParseError;
 pub use error::Result;
+pub use gc::collect_garbage;
+pub use gc::collect_garbage_async;
+pub use long::Chunking;
+pub use long::FastCdc;
 pub use long::LongHkey;
+pub use long::LongHkeyBuilder;
+pub use long::LongHkeyDiff;
 pub use long::LongHkeyExpanded;
+use methods::split_top_level;
+pub use methods::TransferError;
+pub use methods::TransferReport;
 use ps_buffer::Buffer;
 use ps_datachunk::Bytes;
 use ps_datachunk::DataChunk;
 use ps_datachunk::DataChunkError;
+use ps_datachunk::EncryptedDataChunk;
 use ps_datachunk::OwnedDataChunk;
 use ps_datachunk::SerializedDataChunk;
 pub use ps_hash::Hash;
@@ -29,20 +53,41 @@ use ps_promise::PromiseRejection;
 use ps_util::ToResult;
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
+pub use reader::HkeyReader;
 use std::future::Future;
 use std::pin::Pin;
 use std::result::Result as TResult;
 use std::sync::Arc;
 pub use store::Store;
+pub use store::StoreContains;
+pub use store::StoreIter;
+pub use store::StoreRemove;
+pub use writer::HkeyWriter;
 
+pub use crate::async_store::caching::AsyncCachingStore;
 pub use crate::async_store::in_memory::InMemoryAsyncStore;
 pub use crate::async_store::in_memory::InMemoryAsyncStoreError;
 pub use crate::async_store::mixed::MixedStore;
 pub use crate::async_store::mixed::MixedStoreError;
+pub use crate::store::caching::CachingStore;
+pub use crate::store::caching::CachingStoreError;
 pub use crate::store::combined::CombinedStore;
 pub use crate::store::combined::CombinedStoreError;
+pub use crate::store::fs::FsStore;
+pub use crate::store::fs::FsStoreError;
 pub use crate::store::in_memory::InMemoryStore;
 pub use crate::store::in_memory::InMemoryStoreError;
+pub use crate::store::keyed::KeyedStore;
+pub use crate::store::keyed::TenantSecret;
+pub use crate::store::keyed::TENANT_SECRET_SIZE;
+pub use crate::store::pack::PackStore;
+pub use crate::store::pack::PackStoreError;
+pub use crate::store::pack::PackStoreOptions;
+pub use crate::store::policy::PolicyStore;
+pub use crate::store::policy::PutPolicy;
+pub use crate::store::policy::MIN_SEGMENT_SIZE;
+pub use crate::store::verifying::verify_chunk;
+pub use crate::store::verifying::VerifyingStore;
 
 pub type Range = std::ops::Range<usize>;
 
@@ -118,17 +163,29 @@ impl Hkey {
     }
 
     pub fn try_as_list(list: &[u8]) -> Result<Self> {
+        // A lone "[" is both the first and the last byte.
+        if list.len() < 2 {
+            Err(HkeyError::Format)?;
+        }
+
         let last_index = list.len() - 1;
-        let first_byte = *list.first().ok_or(HkeyError::Format)?;
-        let last_byte = *list.get(last_index).ok_or(HkeyError::Format)?;
+        let first_byte = list[0];
+        let last_byte = list[last_index];
         let content = &list[1..last_index];
 
         if first_byte != b'[' || last_byte != b']' {
             Err(HkeyError::Format)?;
         }
 
-        let parts = content.split(|c| *c == b',');
-        let items = parts.map(|item| Self::parse(item).map_err(Into::into));
+        // "[]" is the empty list, as formatted by Hkey::format_list.
+        if content.is_empty() {
+            return Ok(Self::List(Arc::from([])));
+        }
+
+        let parts = split_top_level(content, b',');
+        let items = parts
+            .into_iter()
+            .map(|item| Self::parse(item).map_err(Into::into));
         let items: Result<Vec<Self>> = items.collect();
         let items: Vec<Self> = items?;
         let items: Arc<[Self]> = Arc::from(items.into_boxed_slice());
@@ -555,22 +612,46 @@ impl Hkey {
         }
     }
 
+    /// Encrypts the textual form of a list, returning the [`Hkey::ListRef`] to it.
+    ///
+    /// The list is always encrypted, as [`Store::put`] would inline a short one, and split a long
+    /// one into a [`Hkey::LongHkey`]. Like every chunk, it holds at most
+    /// [`MAX_DECRYPTED_SIZE`] bytes, so longer lists are rejected.
+    fn encrypt_list(
+        list: &[Self],
+        secret: Option<&TenantSecret>,
+    ) -> Result<(Self, EncryptedDataChunk)> {
+        let string = Self::format_list(list);
+
+        if string.len() > MAX_DECRYPTED_SIZE {
+            return HkeyError::ListTooLong(string.len()).err();
+        }
+
+        let encrypted = store::keyed::encrypt(string.as_bytes(), secret)?;
+
+        Ok((Self::ListRef(encrypted.hash(), encrypted.key()), encrypted))
+    }
+
+    /// Stores whatever `self` holds inline beyond the limits of [`Store::policy`], returning the
+    /// key referencing it, or `None` if `self` is short enough already.
     pub fn shrink_or_not<'a, C, E, S>(&self, store: &S) -> TResult<Option<Self>, E>
     where
         C: DataChunk,
         E: From<HkeyError> + Send,
         S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
     {
+        let policy = store.policy();
+
         match self {
             Self::Raw(raw) => {
-                if raw.len() <= MAX_SIZE_RAW {
+                if raw.len() <= policy.inline_threshold() {
                     None
                 } else {
                     store.put(raw)?.shrink_into(store)?.some()
                 }
             }
             Self::Base64(base64) => {
-                if base64.len() <= MAX_SIZE_BASE64 {
+                if policy.inline_base64() && base64.len() <= MAX_SIZE_BASE64 {
                     None
                 } else {
                     store
@@ -580,12 +661,11 @@ impl Hkey {
                 }
             }
             Self::List(list) => {
-                let stored = store.put(Self::format_list(list).as_bytes())?;
+                let (list_ref, encrypted) = Self::encrypt_list(list, store.secret().as_ref())?;
 
-                match stored.encrypted_into_list_ref() {
-                    Ok(hkey) => Some(hkey),
-                    Err(err) => Err(err)?,
-                }
+                store.put_encrypted(encrypted)?;
+
+                Some(list_ref)
             }
             Self::LongHkeyExpanded(lhkey) => Self::LongHkey(lhkey.store(store)?).some(),
             _ => None,
@@ -593,53 +673,48 @@ impl Hkey {
         .ok()
     }
 
+    /// Asynchronous counterpart of [`Hkey::shrink_or_not`].
     pub async fn shrink_or_not_async<C, E, S>(&self, store: S) -> TResult<Option<Self>, E>
     where
         C: DataChunk + Send,
-        E: From<HkeyError> + PromiseRejection,
+        E: From<HkeyError> + PromiseRejection + Send,
         S: AsyncStore<Chunk = C, Error = E>,
     {
+        let policy = store.policy();
+
         match self {
             Self::Raw(raw) => {
-                if raw.len() <= MAX_SIZE_RAW {
+                if raw.len() <= policy.inline_threshold() {
                     None
                 } else {
                     store
                         .put(Bytes::from_owner(raw.clone()))
                         .await?
-                        .shrink_into_async(store)
+                        .shrink_into_async_box(store)
                         .await?
                         .some()
                 }
             }
             Self::Base64(base64) => {
-                if base64.len() <= MAX_SIZE_BASE64 {
+                if policy.inline_base64() && base64.len() <= MAX_SIZE_BASE64 {
                     None
                 } else {
                     store
                         .put(Bytes::from_owner(ps_base64::decode(base64.as_bytes())))
                         .await?
-                        .shrink_into_async(store)
+                        .shrink_into_async_box(store)
                         .await?
                         .some()
                 }
             }
             Self::List(list) => {
-                let stored = store
-                    .put(Bytes::from_owner(Self::format_list(list)))
-                    .await?;
+                let (list_ref, encrypted) = Self::encrypt_list(list, store.secret().as_ref())?;
 
-                match stored.encrypted_into_list_ref() {
-                    Ok(hkey) => Some(hkey),
-                    Err(err) => Err(err)?,
-                }
-            }
-            Self::LongHkeyExpanded(lhkey) => {
-                match store.put(Bytes::from_owner(lhkey.to_string())).await? {
-                    Self::Encrypted(hash, key) => Self::ListRef(hash, key).some(),
-                    _ => Err(HkeyError::Storage)?,
-                }
+                store.put_encrypted(encrypted).await?;
+
+                Some(list_ref)
             }
+            Self::LongHkeyExpanded(lhkey) => Self::LongHkey(lhkey.store_async(store).await?).some(),
             _ => None,
         }
         .ok()
@@ -654,6 +729,18 @@ impl Hkey {
         (self.shrink_or_not(store)?).map_or_else(|| Ok(self), Ok)
     }
 
+    pub fn shrink_into_async_box<C, E, S>(
+        self,
+        store: S,
+    ) -> Pin<Box<dyn Future<Output = TResult<Self, E>> + Send>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.shrink_into_async(store).await })
+    }
+
     pub async fn shrink_into_async<C, E, S>(self, store: S) -> TResult<Self, E>
     where
         C: DataChunk + Send,
@@ -982,4 +1069,34 @@ mod tests {
             Err(InMemoryAsyncStoreError::Hkey(HkeyError::InvalidRange(_)))
         ));
     }
+
+    #[test]
+    fn shrink_list_into_one_chunk() {
+        let store = InMemoryStore::default();
+
+        // Short enough for `Store::put` to inline, but stored as a list all the same.
+        let list_ref = five_byte_list()
+            .shrink_or_not(&store)
+            .expect("Failed to shrink")
+            .expect("List was not stored");
+
+        assert!(matches!(list_ref, Hkey::ListRef(..)));
+        assert_eq!(
+            list_ref.resolve(&store).expect("Failed to resolve"),
+            [1, 2, 3, 4, 5].as_slice()
+        );
+
+        let items: Vec<Hkey> = (0..50u8)
+            .map(|seed| store.put(&[seed; 1000]).expect("Failed to put"))
+            .collect();
+        let list = Hkey::List(items.into());
+        let count = store.hashes().expect("Failed to list").len();
+
+        assert!(list.to_string().len() > MAX_DECRYPTED_SIZE);
+        assert!(matches!(
+            list.shrink_or_not(&store),
+            Err(InMemoryStoreError::Hkey(HkeyError::ListTooLong(_)))
+        ));
+        assert_eq!(store.hashes().expect("Failed to list").len(), count);
+    }
 }
@@ -5,7 +5,7 @@ use ps_hash::Hash;
 use ps_promise::PromiseRejection;
 use ps_util::ToResult;
 
-use crate::{AsyncStore, Hkey, HkeyError, Store};
+use crate::{methods::split_top_level, AsyncStore, Hkey, HkeyError, Store};
 
 use super::LongHkeyExpanded;
 
@@ -61,7 +61,8 @@ impl LongHkey {
         let parts_data = std::str::from_utf8(parts_data);
         let parts_data = parts_data.map_err(HkeyError::from)?;
 
-        let parts: Vec<&str> = parts_data.split(';').collect();
+        // Parts may contain nested long hkeys, so only the first two separators count.
+        let parts: Vec<&str> = parts_data.splitn(3, ';').collect();
 
         if parts.len() != 3 {
             Err(HkeyError::Format)?;
@@ -70,7 +71,14 @@ impl LongHkey {
         let depth: u32 = parts[0].parse().map_err(HkeyError::from)?;
         let size: usize = parts[1].parse().map_err(HkeyError::from)?;
 
-        let parts = parts[2].split(',').map(|part| {
+        let parts = if parts[2].is_empty() {
+            Vec::new()
+        } else {
+            split_top_level(parts[2].as_bytes(), b',')
+        };
+
+        let parts = parts.into_iter().map(|part| {
+            let part = std::str::from_utf8(part)?;
             let (range, hkey) = part.split_once(':').ok_or(HkeyError::Format)?;
             let (start, end) = range.split_once('-').ok_or(HkeyError::Format)?;
             let start: usize = start.parse()?;
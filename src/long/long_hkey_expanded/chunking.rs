@@ -0,0 +1,254 @@
+use crate::{long::LHKEY_SEGMENT_MAX_LENGTH, HkeyError, PutPolicy, Range};
+
+/// How [`LongHkeyBuilder`](crate::LongHkeyBuilder) cuts data into segments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chunking {
+    /// Segments of the policy's [`segment_size`](PutPolicy::segment_size), as produced by
+    /// [`LongHkeyExpanded::from_blob`](crate::LongHkeyExpanded::from_blob).
+    #[default]
+    Fixed,
+    /// Segments cut where the content matches, see [`FastCdc`].
+    ContentDefined(FastCdc),
+}
+
+/// Content-defined chunking after FastCDC.
+///
+/// A gear hash rolls over the data, and a segment ends where its top bits are all zero. Since
+/// cut points depend only on the preceding bytes, inserting or removing bytes only changes the
+/// segments around the edit, and the others deduplicate against earlier versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FastCdc {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+}
+
+impl FastCdc {
+    /// Creates a chunker cutting segments of `min_size..=max_size` bytes, averaging
+    /// `avg_size`.
+    ///
+    /// The bounds must satisfy `0 < min_size <= avg_size <= max_size <= 4096`, so that every
+    /// segment fits a single chunk.
+    pub const fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self, HkeyError> {
+        if min_size == 0
+            || min_size > avg_size
+            || avg_size > max_size
+            || max_size > LHKEY_SEGMENT_MAX_LENGTH
+        {
+            return Err(HkeyError::ChunkingBounds {
+                min_size,
+                avg_size,
+                max_size,
+            });
+        }
+
+        Ok(Self {
+            min_size,
+            avg_size,
+            max_size,
+        })
+    }
+
+    /// Returns the chunker cutting segments of a quarter to all of the policy's segment size,
+    /// averaging half of it.
+    #[must_use]
+    pub const fn for_policy(policy: &PutPolicy) -> Self {
+        let segment_size = policy.segment_size();
+
+        Self {
+            min_size: segment_size / 4,
+            avg_size: segment_size / 2,
+            max_size: segment_size,
+        }
+    }
+
+    #[must_use]
+    pub const fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    #[must_use]
+    pub const fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    #[must_use]
+    pub const fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns this chunker if its segments fit the policy's segment size.
+    pub(crate) const fn check(self, policy: &PutPolicy) -> Result<Self, HkeyError> {
+        if self.max_size > policy.segment_size() {
+            return Err(HkeyError::ChunkExceedsSegment {
+                max_size: self.max_size,
+                segment_size: policy.segment_size(),
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the ranges of the segments `data` is cut into.
+    #[must_use]
+    pub fn split(&self, data: &[u8]) -> Vec<Range> {
+        let mut ranges = Vec::with_capacity(data.len() / self.avg_size + 1);
+        let mut start = 0;
+
+        while start < data.len() {
+            let end = start + self.cut(&data[start..]);
+
+            ranges.push(start..end);
+            start = end;
+        }
+
+        ranges
+    }
+
+    /// Returns the length of the first segment of `data`.
+    ///
+    /// Below the average size, a mask with more bits makes cuts less likely, and above it a
+    /// mask with fewer bits makes them more likely, which narrows the size distribution.
+    fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+
+        let end = data.len().min(self.max_size);
+        let normal = end.min(self.avg_size);
+        let bits = self.avg_size.ilog2();
+        let mask_small = top_bits(bits + 1);
+        let mask_large = top_bits(bits.saturating_sub(1));
+
+        let mut fingerprint = 0u64;
+
+        for (index, &byte) in data.iter().enumerate().take(end).skip(self.min_size) {
+            fingerprint = (fingerprint << 1).wrapping_add(GEAR[usize::from(byte)]);
+
+            let mask = if index < normal {
+                mask_small
+            } else {
+                mask_large
+            };
+
+            if fingerprint & mask == 0 {
+                return index + 1;
+            }
+        }
+
+        end
+    }
+}
+
+impl Default for FastCdc {
+    /// Segments of 1 to 4 KiB, averaging 2 KiB, as for the default [`PutPolicy`].
+    fn default() -> Self {
+        Self::for_policy(&PutPolicy::new())
+    }
+}
+
+/// Returns a mask of the `bits` most significant bits.
+const fn top_bits(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        !0 << (u64::BITS - bits)
+    }
+}
+
+/// Pseudorandom values for each byte, generated by splitmix64 so that cut points are stable.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut index = 0;
+
+    while index < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut value = state;
+
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::FastCdc;
+    use crate::HkeyError;
+
+    fn pseudorandom_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segments_respect_bounds() {
+        let chunker = FastCdc::default();
+        let data = pseudorandom_bytes(1_000_000, 7);
+        let ranges = chunker.split(&data);
+
+        assert_eq!(ranges.first().map(|range| range.start), Some(0));
+        assert_eq!(ranges.last().map(|range| range.end), Some(data.len()));
+
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+
+        for range in &ranges[..ranges.len() - 1] {
+            assert!((1024..=4096).contains(&range.len()), "{range:?}");
+        }
+
+        let average = data.len() / ranges.len();
+
+        assert!((1500..=3500).contains(&average), "average {average}");
+    }
+
+    #[test]
+    fn cut_points_resynchronize() {
+        let chunker = FastCdc::default();
+        let data = pseudorandom_bytes(200_000, 11);
+        let mut edited = data.clone();
+
+        edited.insert(100, 0xFF);
+
+        let ends = |data: &[u8], shift: usize| -> Vec<usize> {
+            chunker
+                .split(data)
+                .iter()
+                .map(|range| range.end - shift)
+                .filter(|&end| end > 10_000)
+                .collect()
+        };
+
+        assert_eq!(ends(&data, 0), ends(&edited, 1));
+    }
+
+    #[test]
+    fn rejects_invalid_bounds() {
+        for (min, avg, max) in [(0, 2048, 4096), (3000, 2048, 4096), (1024, 2048, 8192)] {
+            assert!(matches!(
+                FastCdc::new(min, avg, max),
+                Err(HkeyError::ChunkingBounds { .. })
+            ));
+        }
+
+        assert_eq!(
+            FastCdc::new(1024, 2048, 4096).expect("Valid bounds"),
+            FastCdc::default()
+        );
+    }
+}
@@ -2,7 +2,6 @@ pub const LHKEY_SEGMENT_MAX_LENGTH_LOG2: u32 = 12;
 pub const LHKEY_SEGMENT_MAX_LENGTH: usize = 1 << LHKEY_SEGMENT_MAX_LENGTH_LOG2;
 
 pub const LHKEY_PART_COUNT_LOG2: u32 = 4;
-#[allow(dead_code)] // not used as of its definition
 pub const LHKEY_PART_COUNT: usize = 1 << LHKEY_PART_COUNT_LOG2;
 
 pub const LHKEY_LEVEL_MAX_LENGTH_LOG2: u32 = LHKEY_SEGMENT_MAX_LENGTH_LOG2 + LHKEY_PART_COUNT_LOG2;
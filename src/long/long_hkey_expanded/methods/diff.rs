@@ -0,0 +1,391 @@
+use std::sync::Arc;
+
+use ps_datachunk::{DataChunk, DataChunkError};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{long::LongHkeyExpanded, Hkey, HkeyError, Range, Store};
+
+use super::resize::expand_part;
+
+/// The byte ranges in which two versions of a long key differ, as returned by
+/// [`LongHkeyExpanded::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LongHkeyDiff {
+    ranges: Vec<Range>,
+    old_size: usize,
+    new_size: usize,
+}
+
+impl LongHkeyDiff {
+    /// Returns the sorted, disjoint and non-adjacent ranges which may differ.
+    ///
+    /// Bytes past the end of the shorter version are always included.
+    #[must_use]
+    pub fn ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+
+    #[must_use]
+    pub const fn old_size(&self) -> usize {
+        self.old_size
+    }
+
+    #[must_use]
+    pub const fn new_size(&self) -> usize {
+        self.new_size
+    }
+
+    /// Returns how many bytes the new version is longer than the old one.
+    ///
+    /// The result is an `i128`, which holds the difference of any two sizes.
+    #[must_use]
+    pub const fn size_change(&self) -> i128 {
+        self.new_size as i128 - self.old_size as i128
+    }
+
+    /// Returns whether no difference was found.
+    ///
+    /// As the ranges are conservative, this only means that no structural difference was found.
+    /// The same data stored in differently shaped trees is still reported as differing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub(super) fn new(mut ranges: Vec<Range>, old_size: usize, new_size: usize) -> Self {
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range> = Vec::with_capacity(ranges.len());
+
+        for range in ranges.into_iter().filter(|range| !range.is_empty()) {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        Self {
+            ranges: merged,
+            old_size,
+            new_size,
+        }
+    }
+}
+
+impl LongHkeyExpanded {
+    /// Returns the byte ranges in which `other` differs from `self`.
+    ///
+    /// Both trees are walked together, skipping parts whose keys are equal and descending only
+    /// into differing long parts, so index nodes are fetched but no data segment is. The chunk of
+    /// a differing [`Hkey::ListRef`] part is fetched too, to tell a stored node from a list. Differing
+    /// segments are reported whole. Where the part boundaries of both trees do not line up, the
+    /// long parts are expanded until they do, or until only segments remain.
+    pub fn diff<'a, C, E, S>(&self, store: &'a S, other: &Self) -> Result<LongHkeyDiff, E>
+    where
+        C: DataChunk,
+        E: From<HkeyError> + From<DataChunkError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        let ranges = diff_nodes(store, self, other, 0)?;
+
+        Ok(LongHkeyDiff::new(ranges, self.size, other.size))
+    }
+}
+
+/// A run of consecutive parts of both nodes, starting at a shared boundary and ending at the
+/// next one, or at the end of either node.
+pub(super) struct Group<'a> {
+    pub start: usize,
+    pub left: &'a [(Range, Hkey)],
+    pub right: &'a [(Range, Hkey)],
+}
+
+impl Group<'_> {
+    /// Returns the end of the group, which is the end of its longer side.
+    pub fn end(&self) -> usize {
+        let end = |parts: &[(Range, Hkey)]| parts.last().map_or(self.start, |(r, _)| r.end);
+
+        end(self.left).max(end(self.right))
+    }
+
+    /// Returns both parts of a group made of one part on each side, covering the same range.
+    pub fn pair(&self) -> Option<(&Hkey, &Hkey)> {
+        match (self.left, self.right) {
+            ([(left_range, left)], [(right_range, right)]) if left_range == right_range => {
+                Some((left, right))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits the parts of both nodes into groups, leaving out pairs of equal parts.
+pub(super) fn groups<'a>(
+    left: &'a LongHkeyExpanded,
+    right: &'a LongHkeyExpanded,
+) -> Vec<Group<'a>> {
+    let (left, right) = (left.parts(), right.parts());
+    let (mut i, mut j) = (0, 0);
+    let (mut group_i, mut group_j, mut start) = (0, 0, 0);
+    let mut groups = Vec::new();
+
+    while i < left.len() && j < right.len() {
+        let (left_end, right_end) = (left[i].0.end, right[j].0.end);
+
+        if left_end <= right_end {
+            i += 1;
+        }
+
+        if right_end <= left_end {
+            j += 1;
+        }
+
+        if left_end == right_end {
+            let group = Group {
+                start,
+                left: &left[group_i..i],
+                right: &right[group_j..j],
+            };
+
+            if !matches!(group.pair(), Some((l, r)) if l == r) {
+                groups.push(group);
+            }
+
+            (group_i, group_j, start) = (i, j, left_end);
+        }
+    }
+
+    if group_i < left.len() || group_j < right.len() {
+        groups.push(Group {
+            start,
+            left: &left[group_i..],
+            right: &right[group_j..],
+        });
+    }
+
+    groups
+}
+
+/// Builds a node of `parts` relative to `start`, with the children of long parts, given in
+/// `children`, in place of them. Also returns whether any part was replaced.
+pub(super) fn flatten(
+    parts: &[(Range, Hkey)],
+    children: Vec<Option<LongHkeyExpanded>>,
+    start: usize,
+) -> (LongHkeyExpanded, bool) {
+    let mut expanded = false;
+    let mut flat = Vec::with_capacity(parts.len());
+
+    for ((range, hkey), child) in parts.iter().zip(children) {
+        let offset = range.start - start;
+
+        match child {
+            Some(child) => {
+                expanded = true;
+                flat.extend(child.parts().iter().map(|(child_range, child_hkey)| {
+                    (
+                        child_range.start + offset..child_range.end + offset,
+                        child_hkey.clone(),
+                    )
+                }));
+            }
+            None => flat.push((offset..range.end - start, hkey.clone())),
+        }
+    }
+
+    let size = flat.last().map_or(0, |(range, _)| range.end);
+
+    (LongHkeyExpanded::new(0, size, Arc::from(flat)), expanded)
+}
+
+/// Returns the ranges in which `right` differs from `left`, both starting at `offset`.
+fn diff_nodes<'a, C, E, S>(
+    store: &'a S,
+    left: &LongHkeyExpanded,
+    right: &LongHkeyExpanded,
+    offset: usize,
+) -> Result<Vec<Range>, E>
+where
+    C: DataChunk,
+    E: From<HkeyError> + From<DataChunkError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+{
+    let ranges: Result<Vec<Vec<Range>>, E> = groups(left, right)
+        .into_par_iter()
+        .map(|group| diff_group(store, &group, offset))
+        .collect();
+
+    Ok(ranges?.into_iter().flatten().collect())
+}
+
+fn diff_group<'a, C, E, S>(store: &'a S, group: &Group, offset: usize) -> Result<Vec<Range>, E>
+where
+    C: DataChunk,
+    E: From<HkeyError> + From<DataChunkError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+{
+    let start = offset + group.start;
+
+    let expand = |parts: &[(Range, Hkey)]| -> Result<Vec<_>, E> {
+        parts
+            .iter()
+            .map(|(_, hkey)| expand_part(store, hkey))
+            .collect()
+    };
+
+    let left = expand(group.left)?;
+    let right = expand(group.right)?;
+
+    if let (Some(_), [Some(left)], [Some(right)]) = (group.pair(), &left[..], &right[..]) {
+        return diff_nodes(store, left, right, start);
+    }
+
+    let (left, left_expanded) = flatten(group.left, left, group.start);
+    let (right, right_expanded) = flatten(group.right, right, group.start);
+
+    if left_expanded || right_expanded {
+        diff_nodes(store, &left, &right, start)
+    } else {
+        Ok(std::iter::once(start..offset + group.end()).collect())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::executor::block_on;
+    use ps_datachunk::DataChunk;
+    use ps_hash::Hash;
+
+    use crate::{InMemoryAsyncStore, InMemoryStore, InMemoryStoreError, LongHkeyExpanded, Store};
+
+    use super::LongHkeyDiff;
+
+    #[derive(Default)]
+    struct CountingStore {
+        inner: InMemoryStore,
+        gets: AtomicUsize,
+    }
+
+    impl Store for CountingStore {
+        type Chunk<'c> = <InMemoryStore as Store>::Chunk<'c>;
+        type Error = InMemoryStoreError;
+
+        fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+            self.gets.fetch_add(1, Ordering::Relaxed);
+            self.inner.get(hash)
+        }
+
+        fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+            self.inner.put_encrypted(chunk)
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Diffs two versions stored with [`LongHkeyExpanded::from_blob`], returning the ranges and
+    /// the number of chunks fetched.
+    fn diff(old: &[u8], new: &[u8]) -> (Vec<std::ops::Range<usize>>, usize) {
+        let store = CountingStore::default();
+        let old_lhkey = LongHkeyExpanded::from_blob(&store, old).expect("Failed to store");
+        let new_lhkey = LongHkeyExpanded::from_blob(&store, new).expect("Failed to store");
+
+        let diff = old_lhkey.diff(&store, &new_lhkey).expect("Failed to diff");
+
+        assert_eq!(diff.old_size(), old.len());
+        assert_eq!(diff.new_size(), new.len());
+
+        for (index, (a, b)) in old.iter().zip(new).enumerate() {
+            if a != b {
+                assert!(diff.ranges().iter().any(|range| range.contains(&index)));
+            }
+        }
+
+        (diff.ranges().to_vec(), store.gets.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn equal_versions_have_no_difference() {
+        let data = sequential_bytes(1 << 20);
+
+        assert_eq!(diff(&data, &data), (vec![], 0));
+    }
+
+    #[test]
+    fn descends_only_into_differing_nodes() {
+        let old = sequential_bytes(1 << 20);
+        let mut new = old.clone();
+
+        new[300_000] ^= 0xFF;
+
+        // the two nodes of depth 0 holding the changed segment, but no segment
+        assert_eq!(diff(&old, &new), (vec![299_008..303_104], 2));
+    }
+
+    #[test]
+    fn reports_appended_bytes() {
+        let old = sequential_bytes(100_000);
+        let new = sequential_bytes(150_000);
+
+        let (ranges, _) = diff(&old, &new);
+
+        // the last segment of the old version was partial
+        assert_eq!(ranges, [98_304..150_000]);
+    }
+
+    #[test]
+    fn compares_trees_of_different_depths() {
+        let old = sequential_bytes(50_000);
+        let new = sequential_bytes(150_000);
+
+        assert_eq!(diff(&old, &new).0, [49_152..150_000]);
+        assert_eq!(diff(&new, &old).0, [49_152..150_000]);
+    }
+
+    #[test]
+    fn async_matches_sync() {
+        let old = sequential_bytes(500_000);
+        let mut new = sequential_bytes(700_000);
+
+        new[10] = 0;
+        new[400_000] = 0;
+
+        let store = InMemoryStore::default();
+        let sync = LongHkeyExpanded::from_blob(&store, &old)
+            .expect("Failed to store")
+            .diff(
+                &store,
+                &LongHkeyExpanded::from_blob(&store, &new).expect("Failed to store"),
+            )
+            .expect("Failed to diff");
+
+        assert_eq!(sync.size_change(), 200_000);
+        assert_eq!(
+            LongHkeyDiff::new(vec![0..usize::MAX], usize::MAX, 0).size_change(),
+            -(usize::MAX as i128)
+        );
+        assert_eq!(sync.ranges(), [0..4096, 397_312..401_408, 499_712..700_000]);
+
+        let store = InMemoryAsyncStore::default();
+
+        block_on(async {
+            let old = LongHkeyExpanded::from_blob_async(store.clone(), &old)
+                .await
+                .expect("Failed to store");
+            let new = LongHkeyExpanded::from_blob_async(store.clone(), &new)
+                .await
+                .expect("Failed to store");
+
+            assert_eq!(
+                old.diff_async(store, &new).await.expect("Failed to diff"),
+                sync
+            );
+        });
+    }
+}
@@ -0,0 +1,98 @@
+use std::{future::Future, pin::Pin};
+
+use futures::future::try_join_all;
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+
+use crate::{long::LongHkeyExpanded, AsyncStore, Hkey, HkeyError, Range};
+
+use super::{
+    diff::{flatten, groups, Group, LongHkeyDiff},
+    resize_async::expand_part_async,
+};
+
+impl LongHkeyExpanded {
+    /// Asynchronous counterpart of [`LongHkeyExpanded::diff`].
+    pub async fn diff_async<C, E, S>(&self, store: S, other: &Self) -> Result<LongHkeyDiff, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let ranges = diff_nodes_async(store, self.clone(), other.clone(), 0).await?;
+
+        Ok(LongHkeyDiff::new(ranges, self.size(), other.size()))
+    }
+}
+
+/// Returns the ranges in which `right` differs from `left`, both starting at `offset`.
+fn diff_nodes_async<C, E, S>(
+    store: S,
+    left: LongHkeyExpanded,
+    right: LongHkeyExpanded,
+    offset: usize,
+) -> Pin<Box<dyn Future<Output = Result<Vec<Range>, E>> + Send>>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    Box::pin(async move {
+        let futures = groups(&left, &right)
+            .into_iter()
+            .map(|group| diff_group_async(store.clone(), group, offset));
+
+        let ranges = try_join_all(futures).await?;
+
+        Ok(ranges.into_iter().flatten().collect())
+    })
+}
+
+async fn diff_group_async<C, E, S>(
+    store: S,
+    group: Group<'_>,
+    offset: usize,
+) -> Result<Vec<Range>, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    let start = offset + group.start;
+
+    let (left, right) = futures::try_join!(
+        expand_parts_async(store.clone(), group.left),
+        expand_parts_async(store.clone(), group.right)
+    )?;
+
+    if let (Some(_), [Some(left)], [Some(right)]) = (group.pair(), &left[..], &right[..]) {
+        return diff_nodes_async(store, left.clone(), right.clone(), start).await;
+    }
+
+    let (left, left_expanded) = flatten(group.left, left, group.start);
+    let (right, right_expanded) = flatten(group.right, right, group.start);
+
+    if left_expanded || right_expanded {
+        diff_nodes_async(store, left, right, start).await
+    } else {
+        Ok(std::iter::once(start..offset + group.end()).collect())
+    }
+}
+
+/// Expands those of `parts` which are long keys.
+async fn expand_parts_async<C, E, S>(
+    store: S,
+    parts: &[(Range, Hkey)],
+) -> Result<Vec<Option<LongHkeyExpanded>>, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    try_join_all(
+        parts
+            .iter()
+            .map(|(_, hkey)| expand_part_async(store.clone(), hkey)),
+    )
+    .await
+}
@@ -6,16 +6,7 @@ use rayon::{
     slice::ParallelSlice,
 };
 
-use crate::{
-    long::{
-        long_hkey_expanded::{
-            constants::{LHKEY_LEVEL_MAX_LENGTH, LHKEY_SEGMENT_MAX_LENGTH},
-            methods::update::helpers::{calculate_depth, calculate_segment_length},
-        },
-        LongHkeyExpanded,
-    },
-    Hkey, HkeyError, Range, Store,
-};
+use crate::{long::LongHkeyExpanded, Hkey, HkeyError, PutPolicy, Range, Store};
 
 impl LongHkeyExpanded {
     pub fn from_blob<'a, C, E, S>(store: &S, data: &[u8]) -> Result<Self, E>
@@ -24,10 +15,21 @@ impl LongHkeyExpanded {
         E: From<HkeyError> + Send,
         S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
     {
-        let depth = calculate_depth(0, data.len());
+        Self::from_blob_with(store, data, store.policy())
+    }
+
+    /// Splits `data` into segments of the policy's segment size, storing each with
+    /// [`Store::put_with`].
+    pub fn from_blob_with<'a, C, E, S>(store: &S, data: &[u8], policy: PutPolicy) -> Result<Self, E>
+    where
+        C: DataChunk,
+        E: From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        let depth = policy.depth(0, data.len());
 
-        let parts: Result<Vec<(Range, Hkey)>, E> = if data.len() > LHKEY_LEVEL_MAX_LENGTH {
-            let segment_length = calculate_segment_length(depth);
+        let parts: Result<Vec<(Range, Hkey)>, E> = if data.len() > policy.level_length() {
+            let segment_length = policy.segment_length(depth);
 
             let chunks = data.par_chunks(segment_length);
 
@@ -36,20 +38,21 @@ impl LongHkeyExpanded {
                 .map(|(index, chunk)| {
                     let start = index * segment_length;
                     let end = start + chunk.len();
-                    let hkey = Self::from_blob(store, chunk)?.shrink(store)?;
+                    let hkey = Self::from_blob_with(store, chunk, policy)?.shrink(store)?;
 
                     Ok((start..end, hkey))
                 })
                 .collect()
         } else {
-            let chunks = data.par_chunks(LHKEY_SEGMENT_MAX_LENGTH);
+            let segment_size = policy.segment_size();
+            let chunks = data.par_chunks(segment_size);
 
             chunks
                 .enumerate()
                 .map(|(index, chunk)| {
-                    let start = index * LHKEY_SEGMENT_MAX_LENGTH;
+                    let start = index * segment_size;
                     let end = start + chunk.len();
-                    let hkey = store.put(chunk)?;
+                    let hkey = store.put_with(chunk, policy)?;
 
                     Ok((start..end, hkey))
                 })
@@ -3,16 +3,7 @@ use std::{future::Future, pin::Pin, sync::Arc};
 use ps_datachunk::{Bytes, DataChunk};
 use ps_promise::PromiseRejection;
 
-use crate::{
-    long::{
-        long_hkey_expanded::{
-            constants::{LHKEY_LEVEL_MAX_LENGTH, LHKEY_SEGMENT_MAX_LENGTH},
-            methods::update::helpers::{calculate_depth, calculate_segment_length},
-        },
-        LongHkeyExpanded,
-    },
-    AsyncStore, Hkey, HkeyError, Range,
-};
+use crate::{long::LongHkeyExpanded, AsyncStore, Hkey, HkeyError, PutPolicy, Range};
 
 impl LongHkeyExpanded {
     pub fn from_blob_async_box<'a, C, E, S>(
@@ -27,23 +18,52 @@ impl LongHkeyExpanded {
         Box::pin(async move { Self::from_blob_async(store, data).await })
     }
 
+    pub fn from_blob_with_async_box<'a, C, E, S>(
+        store: S,
+        data: &'a [u8],
+        policy: PutPolicy,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { Self::from_blob_with_async(store, data, policy).await })
+    }
+
     pub async fn from_blob_async<C, E, S>(store: S, data: &[u8]) -> Result<Self, E>
     where
         C: DataChunk + Send,
         E: From<HkeyError> + PromiseRejection + Send,
         S: AsyncStore<Chunk = C, Error = E>,
     {
-        let depth = calculate_depth(0, data.len());
+        let policy = store.policy();
 
-        let parts: Result<Vec<(Range, Hkey)>, E> = if data.len() > LHKEY_LEVEL_MAX_LENGTH {
-            let segment_length = calculate_segment_length(depth);
+        Self::from_blob_with_async(store, data, policy).await
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::from_blob_with`].
+    pub async fn from_blob_with_async<C, E, S>(
+        store: S,
+        data: &[u8],
+        policy: PutPolicy,
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let depth = policy.depth(0, data.len());
+
+        let parts: Result<Vec<(Range, Hkey)>, E> = if data.len() > policy.level_length() {
+            let segment_length = policy.segment_length(depth);
 
             let mut chunks = Vec::new();
 
             for (index, chunk) in data.chunks(segment_length).enumerate() {
                 let start = index * segment_length;
                 let end = start + chunk.len();
-                let hkey = Self::from_blob_async_box(store.clone(), chunk)
+                let hkey = Self::from_blob_with_async_box(store.clone(), chunk, policy)
                     .await?
                     .shrink_async(store.clone())
                     .await?;
@@ -53,12 +73,15 @@ impl LongHkeyExpanded {
 
             Ok(chunks)
         } else {
+            let segment_size = policy.segment_size();
             let mut chunks = Vec::new();
 
-            for (index, chunk) in data.chunks(LHKEY_SEGMENT_MAX_LENGTH).enumerate() {
-                let start = index * LHKEY_SEGMENT_MAX_LENGTH;
+            for (index, chunk) in data.chunks(segment_size).enumerate() {
+                let start = index * segment_size;
                 let end = start + chunk.len();
-                let hkey = store.put(Bytes::copy_from_slice(chunk)).await?;
+                let hkey = store
+                    .put_with(Bytes::copy_from_slice(chunk), policy)
+                    .await?;
 
                 chunks.push((start..end, hkey));
             }
@@ -1,8 +1,16 @@
+pub mod diff;
+pub mod diff_async;
 pub mod from_blob;
 pub mod from_blob_async;
 pub mod normalize_segment;
+pub mod normalize_segment_async;
+pub mod resize;
+pub mod resize_async;
 pub mod shrink;
 pub mod shrink_async;
+pub mod splice;
+pub mod splice_async;
 pub mod store;
 pub mod store_async;
 pub mod update;
+pub mod update_async;
@@ -4,12 +4,7 @@ use ps_datachunk::{DataChunk, DataChunkError};
 use ps_util::ToResult;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::{
-    long::{long_hkey_expanded::constants::LHKEY_SEGMENT_MAX_LENGTH, LongHkeyExpanded},
-    Hkey, HkeyError, Range, Store,
-};
-
-use super::update::helpers::{calculate_depth, calculate_segment_length};
+use crate::{long::LongHkeyExpanded, Hkey, HkeyError, Range, Store};
 
 impl LongHkeyExpanded {
     /// Normalizes a segment of this `LongHkeyExpanded` within the given range, producing a new
@@ -57,10 +52,12 @@ impl LongHkeyExpanded {
             return result;
         }
 
+        let policy = store.policy();
+        let segment_size = policy.segment_size();
         let length = range.end - range.start;
-        let depth = calculate_depth(depth, length);
+        let depth = policy.depth(depth, length);
 
-        if depth == 0 && length <= LHKEY_SEGMENT_MAX_LENGTH {
+        if depth == 0 && length <= segment_size {
             let data = self.resolve_slice(store, range)?;
             let segment_hkey = store.put(&data)?;
             let segment_parts = Arc::from([(0..length, segment_hkey)]);
@@ -70,20 +67,18 @@ impl LongHkeyExpanded {
         }
 
         if depth == 0 {
-            let count = length.div_ceil(LHKEY_SEGMENT_MAX_LENGTH);
+            let count = length.div_ceil(segment_size);
             let iterator = (0..count).into_par_iter();
 
             let parts: Result<Vec<_>, E> = iterator
                 .map(|index| {
-                    let begin = range.start + index * LHKEY_SEGMENT_MAX_LENGTH;
-                    let end = range
-                        .end
-                        .min(range.start + (index + 1) * LHKEY_SEGMENT_MAX_LENGTH);
+                    let begin = range.start + index * segment_size;
+                    let end = range.end.min(range.start + (index + 1) * segment_size);
                     let data = self.resolve_slice(store, begin..end)?;
                     let hkey = store.put(&data)?;
 
                     Ok::<_, E>((
-                        index * LHKEY_SEGMENT_MAX_LENGTH..(index + 1) * LHKEY_SEGMENT_MAX_LENGTH,
+                        index * segment_size..length.min((index + 1) * segment_size),
                         hkey,
                     ))
                 })
@@ -91,14 +86,14 @@ impl LongHkeyExpanded {
 
             let parts = Arc::from(parts?.into_boxed_slice());
 
-            let lhkey = Self::new(1, length, parts);
+            let lhkey = Self::new(0, length, parts);
 
             return Ok(lhkey);
         }
 
         // if depth >= 1, resolve recursively
 
-        let segment_length = calculate_segment_length(depth);
+        let segment_length = policy.segment_length(depth);
 
         let iterator = (0..length.div_ceil(segment_length)).into_par_iter();
 
@@ -109,7 +104,10 @@ impl LongHkeyExpanded {
                 let lhkey = self.normalize_segment(store, depth - 1, begin..end)?;
                 let hkey = Hkey::LongHkey(lhkey.store(store)?);
 
-                Ok::<_, E>((index * segment_length..(index + 1) * segment_length, hkey))
+                Ok::<_, E>((
+                    index * segment_length..length.min((index + 1) * segment_length),
+                    hkey,
+                ))
             })
             .collect();
 
@@ -0,0 +1,128 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use futures::future::try_join_all;
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+use ps_util::ToResult;
+
+use crate::{long::LongHkeyExpanded, AsyncStore, Hkey, HkeyError, Range};
+
+impl LongHkeyExpanded {
+    pub fn normalize_segment_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+        depth: u32,
+        range: Range,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.normalize_segment_async(store, depth, range).await })
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::normalize_segment`].
+    ///
+    /// Produces the same segment the synchronous method would, fetching and storing its parts
+    /// concurrently.
+    pub async fn normalize_segment_async<C, E, S>(
+        &self,
+        store: S,
+        depth: u32,
+        range: Range,
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if range.start > range.end {
+            HkeyError::InvalidRange(range.clone()).err()?;
+        }
+
+        if range.end == range.start {
+            return Ok(Self::default());
+        }
+
+        // Check for existing segment
+        for segment in self.parts.iter() {
+            if segment.0 == range {
+                match &segment.1 {
+                    Hkey::LongHkeyExpanded(lhkey) => return Ok(lhkey.clone()),
+                    Hkey::LongHkey(lhkey) => return lhkey.expand_async(store).await,
+                    _ => {}
+                }
+            }
+        }
+
+        let policy = store.policy();
+        let segment_size = policy.segment_size();
+        let length = range.end - range.start;
+        let depth = policy.depth(depth, length);
+
+        if depth == 0 && length <= segment_size {
+            let data = self.resolve_slice_async(store.clone(), range).await?;
+            let size = data.len();
+            let segment_hkey = store.put(data).await?;
+            let segment_parts = Arc::from([(0..length, segment_hkey)]);
+            let lhkey = Self::new(0, size, segment_parts);
+
+            return Ok(lhkey);
+        }
+
+        if depth == 0 {
+            let count = length.div_ceil(segment_size);
+
+            let futures = (0..count).map(|index| {
+                let store = store.clone();
+                let begin = range.start + index * segment_size;
+                let end = range.end.min(range.start + (index + 1) * segment_size);
+
+                async move {
+                    let data = self.resolve_slice_async(store.clone(), begin..end).await?;
+                    let hkey = store.put(data).await?;
+
+                    Ok::<_, E>((
+                        index * segment_size..length.min((index + 1) * segment_size),
+                        hkey,
+                    ))
+                }
+            });
+
+            let parts = Arc::from(try_join_all(futures).await?.into_boxed_slice());
+
+            let lhkey = Self::new(0, length, parts);
+
+            return Ok(lhkey);
+        }
+
+        // if depth >= 1, resolve recursively
+
+        let segment_length = policy.segment_length(depth);
+
+        let futures = (0..length.div_ceil(segment_length)).map(|index| {
+            let store = store.clone();
+            let begin = range.start + index * segment_length;
+            let end = range.end.min(range.start + (index + 1) * segment_length);
+
+            async move {
+                let lhkey = self
+                    .normalize_segment_async_box(store.clone(), depth - 1, begin..end)
+                    .await?;
+                let hkey = Hkey::LongHkey(lhkey.store_async(store).await?);
+
+                Ok::<_, E>((
+                    index * segment_length..length.min((index + 1) * segment_length),
+                    hkey,
+                ))
+            }
+        });
+
+        let parts = Arc::from(try_join_all(futures).await?.into_boxed_slice());
+
+        let lhkey = Self::new(depth, length, parts);
+
+        Ok(lhkey)
+    }
+}
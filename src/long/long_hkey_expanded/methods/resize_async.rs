@@ -0,0 +1,326 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use futures::future::try_join_all;
+use ps_datachunk::{Bytes, DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+
+use crate::{long::LongHkeyExpanded, AsyncStore, Hkey, HkeyError, Range};
+
+impl LongHkeyExpanded {
+    pub fn resize_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+        new_len: usize,
+        fill_byte: u8,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.resize_async(store, new_len, fill_byte).await })
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::truncate`].
+    pub async fn truncate_async<C, E, S>(&self, store: S, new_len: usize) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        self.resize_async(store, new_len.min(self.size), 0).await
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::resize`].
+    pub async fn resize_async<C, E, S>(
+        &self,
+        store: S,
+        new_len: usize,
+        fill_byte: u8,
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if new_len == 0 {
+            return Ok(Self::default());
+        }
+
+        let policy = store.policy();
+        let depth = policy.depth(0, new_len);
+
+        if self.depth > depth {
+            // The new buffer fits within the first part, which is therefore the new root.
+            if let Some((range, hkey)) = self.parts.first() {
+                if range.start == 0 && range.end >= new_len.min(self.size) {
+                    if let Some(child) = expand_part_async(store.clone(), hkey).await? {
+                        return child.resize_async_box(store, new_len, fill_byte).await;
+                    }
+                }
+            }
+
+            let data = self
+                .resolve_padded_async(store.clone(), 0..new_len, fill_byte)
+                .await?;
+
+            return Self::from_blob_async(store, &data).await;
+        }
+
+        let segment_length = policy.segment_length(depth);
+
+        let futures = (0..new_len.div_ceil(segment_length)).map(|index| {
+            let store = store.clone();
+            let start = index * segment_length;
+            let end = new_len.min(start + segment_length);
+
+            async move {
+                let hkey = self
+                    .resize_segment_async(store, depth, start..end, fill_byte)
+                    .await?;
+
+                Ok::<_, E>((start..end, hkey))
+            }
+        });
+
+        let parts: Vec<(Range, Hkey)> = try_join_all(futures).await?;
+        let parts = Arc::from(parts.into_boxed_slice());
+
+        Ok(Self::new(depth, new_len, parts))
+    }
+
+    /// Builds the part covering `range` of the resized buffer, at `depth`.
+    async fn resize_segment_async<C, E, S>(
+        &self,
+        store: S,
+        depth: u32,
+        range: Range,
+        fill_byte: u8,
+    ) -> Result<Hkey, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let length = range.end - range.start;
+
+        if range.start >= self.size {
+            return fill_segment_async(store, depth, length, fill_byte).await;
+        }
+
+        if self.depth < depth && range.start == 0 {
+            // The buffer grew a level, so the whole receiver becomes a prefix of the first part.
+            return self
+                .resize_async_box(store.clone(), length, fill_byte)
+                .await?
+                .shrink_async(store)
+                .await;
+        }
+
+        let part = (self.depth == depth)
+            .then(|| {
+                self.parts
+                    .iter()
+                    .find(|(part, _)| part.start == range.start)
+            })
+            .flatten();
+
+        if let Some((part, hkey)) = part {
+            if *part == range {
+                return Ok(hkey.clone());
+            }
+
+            // Parts need not end on segment boundaries, so a part is only cut down to the
+            // segment when it covers all of it.
+            if depth > 0 && part.end >= range.end {
+                if let Some(child) = expand_part_async(store.clone(), hkey).await? {
+                    return child
+                        .resize_async_box(store.clone(), length, fill_byte)
+                        .await?
+                        .shrink_async(store)
+                        .await;
+                }
+            }
+        }
+
+        let data = self
+            .resolve_padded_async(store.clone(), range, fill_byte)
+            .await?;
+
+        if depth == 0 {
+            store.put(Bytes::from_owner(data)).await
+        } else {
+            Self::from_blob_async(store.clone(), &data)
+                .await?
+                .shrink_async(store)
+                .await
+        }
+    }
+
+    /// Resolves `range`, filling whatever lies past the end of the buffer with `fill_byte`.
+    async fn resolve_padded_async<C, E, S>(
+        &self,
+        store: S,
+        range: Range,
+        fill_byte: u8,
+    ) -> Result<Vec<u8>, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let length = range.end - range.start;
+        let available = range.start.min(self.size)..range.end.min(self.size);
+        let mut data = self.resolve_slice_async(store, available).await?.to_vec();
+
+        data.resize(length, fill_byte);
+
+        Ok(data)
+    }
+}
+
+/// Expands a part if it is itself a long key.
+///
+/// Stored long keys share their textual form with [`Hkey::ListRef`], so parts parsed from a
+/// stored node are resolved to tell the two apart.
+pub(super) async fn expand_part_async<C, E, S>(
+    store: S,
+    hkey: &Hkey,
+) -> Result<Option<LongHkeyExpanded>, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    match hkey {
+        Hkey::LongHkey(lhkey) => Ok(Some(lhkey.expand_async(store).await?)),
+        Hkey::LongHkeyExpanded(lhkey) => Ok(Some(lhkey.clone())),
+        Hkey::ListRef(hash, key) => {
+            let node = Hkey::resolve_encrypted_async(hash, key, store).await?;
+
+            match Hkey::parse(node.data_ref()).map_err(HkeyError::Construction)? {
+                Hkey::LongHkeyExpanded(lhkey) => Ok(Some(lhkey)),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Stores a part of `length` bytes of `fill_byte`, as a child of a node at `depth`.
+async fn fill_segment_async<C, E, S>(
+    store: S,
+    depth: u32,
+    length: usize,
+    fill_byte: u8,
+) -> Result<Hkey, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    if depth == 0 {
+        store.put(Bytes::from_owner(vec![fill_byte; length])).await
+    } else {
+        fill_node_async(store.clone(), length, fill_byte)
+            .await?
+            .shrink_async(store)
+            .await
+    }
+}
+
+/// Builds the node of `length` bytes of `fill_byte`.
+///
+/// Full segments are identical, so each level stores at most two of them, and the fill never
+/// needs to be held in memory.
+fn fill_node_async<C, E, S>(
+    store: S,
+    length: usize,
+    fill_byte: u8,
+) -> Pin<Box<dyn Future<Output = Result<LongHkeyExpanded, E>> + Send>>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    Box::pin(async move {
+        let policy = store.policy();
+        let depth = policy.depth(0, length);
+        let segment_length = policy.segment_length(depth);
+        let full_count = length / segment_length;
+        let remainder = length % segment_length;
+
+        let mut parts = Vec::with_capacity(full_count + 1);
+
+        if full_count > 0 {
+            let hkey = fill_segment_async(store.clone(), depth, segment_length, fill_byte).await?;
+
+            for index in 0..full_count {
+                let start = index * segment_length;
+
+                parts.push((start..start + segment_length, hkey.clone()));
+            }
+        }
+
+        if remainder > 0 {
+            let start = full_count * segment_length;
+            let hkey = fill_segment_async(store, depth, remainder, fill_byte).await?;
+
+            parts.push((start..length, hkey));
+        }
+
+        Ok(LongHkeyExpanded::new(depth, length, Arc::from(parts)))
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+
+    use crate::{InMemoryAsyncStore, LongHkeyExpanded};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn resize_async_matches_from_blob_async() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(1_100_000);
+
+        block_on(async {
+            let lhkey = LongHkeyExpanded::from_blob_async(store.clone(), &data)
+                .await
+                .expect("Failed to store");
+
+            for (new_len, fill_byte) in [(70_000, 0), (100, 0), (1_200_000, 0xEE), (0, 0)] {
+                let mut expected = data.clone();
+
+                expected.resize(new_len, fill_byte);
+
+                let resized = if fill_byte == 0 {
+                    lhkey.truncate_async(store.clone(), new_len).await
+                } else {
+                    lhkey.resize_async(store.clone(), new_len, fill_byte).await
+                }
+                .expect("Failed to resize");
+
+                let fresh = LongHkeyExpanded::from_blob_async(store.clone(), &expected)
+                    .await
+                    .expect("Failed to store");
+
+                assert_eq!(resized.to_string(), fresh.to_string());
+                assert_eq!(
+                    resized
+                        .resolve_async(store.clone())
+                        .await
+                        .expect("Failed to resolve")
+                        .as_ref(),
+                    expected.as_slice()
+                );
+            }
+        });
+    }
+}
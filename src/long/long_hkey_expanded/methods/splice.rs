@@ -0,0 +1,384 @@
+use std::sync::Arc;
+
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_util::ToResult;
+
+use crate::{
+    long::{long_hkey_expanded::constants::LHKEY_PART_COUNT, LongHkeyExpanded},
+    methods::{group_parts, ConcatPart},
+    Hkey, HkeyError, Range, Store,
+};
+
+use super::resize::expand_part;
+
+impl LongHkeyExpanded {
+    /// Replaces the bytes in `range` with `replacement`, shifting everything after it.
+    ///
+    /// Only the parts overlapping `range` are rebuilt, recursing into long parts, so the number
+    /// of chunks touched grows with the depth of the tree rather than with its size. Parts after
+    /// the edit are kept as-is under shifted ranges, which leaves the part ranges non-uniform.
+    /// Where the rebuilt parts would push a node past sixteen parts, they are grouped into a
+    /// stored node of their own, so no node outgrows its capacity. Leaves are cut into segments
+    /// of the segment size of the store's policy.
+    pub fn splice<'a, C, E, S>(
+        &self,
+        store: &'a S,
+        range: Range,
+        replacement: &[u8],
+    ) -> Result<Self, E>
+    where
+        C: DataChunk,
+        E: From<HkeyError> + From<DataChunkError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        if range.start > range.end || range.end > self.size {
+            HkeyError::InvalidRange(range.clone()).err()?;
+        }
+
+        if range.is_empty() && replacement.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let Some((first, last)) = self.affected_parts(&range) else {
+            return Self::from_blob(store, replacement);
+        };
+
+        let (first_range, first_hkey) = &self.parts[first];
+        let (last_range, last_hkey) = &self.parts[last];
+
+        let pieces = if first == last {
+            let local = range.start - first_range.start..range.end - first_range.start;
+
+            splice_part(store, first_hkey, local, replacement)?
+        } else {
+            let head = range.start - first_range.start..first_range.end - first_range.start;
+            let tail = 0..range.end - last_range.start;
+
+            let mut pieces = splice_part(store, first_hkey, head, replacement)?;
+
+            pieces.extend(splice_part(store, last_hkey, tail, &[])?);
+
+            pieces
+        };
+
+        let (pieces, depth) = if self.overflows(first, last, pieces.len()) {
+            let node = group_parts(store, self.concat_parts(pieces))?;
+
+            (
+                vec![(node.size, node.store(store)?.into())],
+                self.depth.max(node.depth + 1),
+            )
+        } else {
+            (pieces, self.depth)
+        };
+
+        Ok(self.with_spliced_parts(first, last, pieces, range, replacement.len(), depth))
+    }
+
+    /// Returns the indices of the first and last parts overlapping `range`.
+    ///
+    /// An empty `range` is an insertion point, which belongs to the part it falls into, or to
+    /// the last part when appending.
+    pub(super) fn affected_parts(&self, range: &Range) -> Option<(usize, usize)> {
+        let last_index = self.parts.len().checked_sub(1)?;
+
+        let first = self
+            .parts
+            .iter()
+            .position(|(part, _)| part.end > range.start)
+            .unwrap_or(last_index);
+
+        let last = if range.is_empty() {
+            first
+        } else {
+            self.parts
+                .iter()
+                .rposition(|(part, _)| part.start < range.end)
+                .unwrap_or(first)
+        };
+
+        Some((first, last))
+    }
+
+    /// Replaces parts `first..=last` with `pieces`, shifting the ranges of later parts, in a
+    /// node of `depth`.
+    pub(super) fn with_spliced_parts(
+        &self,
+        first: usize,
+        last: usize,
+        pieces: Vec<(usize, Hkey)>,
+        range: Range,
+        replacement_length: usize,
+        depth: u32,
+    ) -> Self {
+        let mut parts: Vec<(Range, Hkey)> = Vec::with_capacity(self.parts.len() + pieces.len());
+
+        parts.extend_from_slice(&self.parts[..first]);
+
+        let mut position = self.parts[first].0.start;
+
+        for (length, hkey) in pieces {
+            parts.push((position..position + length, hkey));
+            position += length;
+        }
+
+        for (part, hkey) in &self.parts[last + 1..] {
+            let length = part.end - part.start;
+
+            parts.push((position..position + length, hkey.clone()));
+            position += length;
+        }
+
+        let size = self.size - (range.end - range.start) + replacement_length;
+
+        Self::new(depth, size, Arc::from(parts.into_boxed_slice()))
+    }
+
+    /// Returns whether replacing parts `first..=last` with `count` pieces would leave this node
+    /// with more than [`LHKEY_PART_COUNT`] parts.
+    pub(super) fn overflows(&self, first: usize, last: usize, count: usize) -> bool {
+        self.parts.len() - (last + 1 - first) + count > LHKEY_PART_COUNT
+    }
+
+    /// Returns `pieces` as parts of this node, to be grouped into a node of their own with
+    /// [`group_parts`].
+    pub(super) fn concat_parts(&self, pieces: Vec<(usize, Hkey)>) -> Vec<ConcatPart> {
+        pieces
+            .into_iter()
+            .map(|(length, hkey)| (length, hkey, self.depth))
+            .collect()
+    }
+}
+
+/// Splices a single part, returning the lengths and keys of the parts replacing it.
+fn splice_part<'a, C, E, S>(
+    store: &'a S,
+    hkey: &Hkey,
+    range: Range,
+    replacement: &[u8],
+) -> Result<Vec<(usize, Hkey)>, E>
+where
+    C: DataChunk,
+    E: From<HkeyError> + From<DataChunkError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+{
+    if let Some(child) = expand_part(store, hkey)? {
+        let child = child.splice(store, range, replacement)?;
+
+        if child.size == 0 {
+            return Ok(Vec::new());
+        }
+
+        return Ok(vec![(child.size, child.store(store)?.into())]);
+    }
+
+    let original = hkey.resolve(store)?;
+
+    if range.end > original.len() {
+        HkeyError::InvalidRange(range.clone()).err()?;
+    }
+
+    let mut data =
+        Vec::with_capacity(original.len() - (range.end - range.start) + replacement.len());
+
+    data.extend_from_slice(&original[..range.start]);
+    data.extend_from_slice(replacement);
+    data.extend_from_slice(&original[range.end..]);
+
+    leaf_pieces(store, &data)
+}
+
+/// Stores spliced leaf data as segments, or as a single long part once it outgrows a level.
+pub(super) fn leaf_pieces<'a, C, E, S>(store: &'a S, data: &[u8]) -> Result<Vec<(usize, Hkey)>, E>
+where
+    C: DataChunk,
+    E: From<HkeyError> + From<DataChunkError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+{
+    let policy = store.policy();
+
+    if data.len() > policy.level_length() {
+        let lhkey = LongHkeyExpanded::from_blob(store, data)?.store(store)?;
+
+        return Ok(vec![(data.len(), lhkey.into())]);
+    }
+
+    data.chunks(policy.segment_size())
+        .map(|chunk| Ok((chunk.len(), store.put(chunk)?)))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::expand_part;
+    use crate::{
+        long::LHKEY_PART_COUNT, HkeyError, InMemoryStore, InMemoryStoreError, LongHkeyExpanded,
+        StoreIter,
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn spliced(data: &[u8], range: std::ops::Range<usize>, replacement: &[u8]) -> Vec<u8> {
+        let mut expected = data.to_vec();
+
+        expected.splice(range, replacement.iter().copied());
+
+        expected
+    }
+
+    #[test]
+    fn splice_matches_vec_splice() {
+        let store = InMemoryStore::default();
+
+        for len in [0, 100, 10_000, 1_200_000] {
+            let data = sequential_bytes(len);
+            let lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+            for (range, replacement) in [
+                (0..0, &b"x"[..]),
+                (len / 2..len / 2, b"inserted"),
+                (len..len, b"appended"),
+                (0..len / 3, b""),
+                (len / 4..len * 3 / 4, b"replaced"),
+                (len / 3..len / 3 + len.min(5), &[0xAB; 70_000][..]),
+            ] {
+                let result = lhkey
+                    .splice(&store, range.clone(), replacement)
+                    .expect("Failed to splice");
+                let expected = spliced(&data, range.clone(), replacement);
+
+                assert_eq!(result.size(), expected.len());
+                assert_eq!(
+                    result.resolve(&store).expect("Failed to resolve").as_ref(),
+                    expected.as_slice(),
+                    "len={len} range={range:?}"
+                );
+
+                let shrunk = result.shrink(&store).expect("Failed to shrink");
+
+                assert_eq!(
+                    shrunk.resolve(&store).expect("Failed to resolve").as_ref(),
+                    expected.as_slice()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_splices_compose() {
+        let store = InMemoryStore::default();
+        let mut data = sequential_bytes(300_000);
+        let mut lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+        for step in 0..20 {
+            let start = (step * 13_337) % data.len();
+            let end = (start + step * 101).min(data.len());
+            let replacement = vec![u8::try_from(step).expect("step fits"); step * 37];
+
+            lhkey = lhkey
+                .splice(&store, start..end, &replacement)
+                .expect("Failed to splice");
+            data = spliced(&data, start..end, &replacement);
+        }
+
+        assert_eq!(
+            lhkey.resolve(&store).expect("Failed to resolve").as_ref(),
+            data.as_slice()
+        );
+    }
+
+    #[test]
+    fn insert_touches_depth_chunks() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(1_200_000);
+        let lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+        assert_eq!(lhkey.depth(), 2);
+
+        let before = store.hashes().expect("Failed to list").len();
+        let result = lhkey
+            .splice(&store, 500_000..500_000, b"!")
+            .expect("Failed to splice");
+        let added = store.hashes().expect("Failed to list").len() - before;
+
+        // One leaf, one node per level below the root, and the node grouping the split leaf,
+        // since the full node holding it has no room for another part.
+        assert!(added <= 4, "a one-byte insert stored {added} chunks");
+
+        let unchanged = result
+            .parts()
+            .iter()
+            .zip(lhkey.parts())
+            .filter(|(new, old)| new.1 == old.1)
+            .count();
+
+        assert_eq!(unchanged, lhkey.parts().len() - 1);
+        assert_eq!(result.parts()[1].0, 1_048_577..1_200_001);
+    }
+
+    /// Returns the largest number of parts of any node in the tree.
+    fn max_part_count(store: &InMemoryStore, lhkey: &LongHkeyExpanded) -> usize {
+        lhkey
+            .parts()
+            .iter()
+            .filter_map(|(_, hkey)| expand_part(store, hkey).expect("Failed to expand"))
+            .map(|child| max_part_count(store, &child))
+            .fold(lhkey.parts().len(), usize::max)
+    }
+
+    #[test]
+    fn repeated_inserts_keep_nodes_bounded() {
+        let store = InMemoryStore::default();
+        let mut data = sequential_bytes(60_000);
+        let mut lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+        for step in 0..40 {
+            let position = (step * 7919) % data.len();
+            let insertion = vec![u8::try_from(step).expect("step fits"); 5000];
+
+            lhkey = lhkey
+                .splice(&store, position..position, &insertion)
+                .expect("Failed to splice");
+            data.splice(position..position, insertion);
+
+            assert!(max_part_count(&store, &lhkey) <= LHKEY_PART_COUNT);
+        }
+
+        assert_eq!(
+            lhkey.resolve(&store).expect("Failed to resolve").as_ref(),
+            data.as_slice()
+        );
+
+        for new_len in [data.len() + 10_000, 200_000, 65_536, 5000] {
+            let resized = lhkey
+                .resize(&store, new_len, 0xEE)
+                .expect("Failed to resize");
+            let mut expected = data.clone();
+
+            expected.resize(new_len, 0xEE);
+
+            assert_eq!(
+                resized.resolve(&store).expect("Failed to resolve").as_ref(),
+                expected.as_slice(),
+                "resized to {new_len}"
+            );
+        }
+    }
+
+    #[test]
+    fn splice_out_of_bounds_errors() {
+        let store = InMemoryStore::default();
+        let lhkey = LongHkeyExpanded::from_blob(&store, &[1; 100]).expect("Failed to store");
+
+        let result = lhkey.splice(&store, 50..101, b"");
+
+        assert!(matches!(
+            result,
+            Err(InMemoryStoreError::Hkey(HkeyError::InvalidRange(_)))
+        ));
+    }
+}
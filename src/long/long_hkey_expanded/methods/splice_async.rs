@@ -0,0 +1,213 @@
+use std::{future::Future, pin::Pin};
+
+use ps_datachunk::{Bytes, DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+use ps_util::ToResult;
+
+use crate::{
+    long::LongHkeyExpanded, methods::group_parts_async, AsyncStore, Hkey, HkeyError, Range,
+};
+
+use super::resize_async::expand_part_async;
+
+impl LongHkeyExpanded {
+    pub fn splice_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+        range: Range,
+        replacement: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.splice_async(store, range, replacement).await })
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::splice`].
+    pub async fn splice_async<C, E, S>(
+        &self,
+        store: S,
+        range: Range,
+        replacement: &[u8],
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if range.start > range.end || range.end > self.size {
+            HkeyError::InvalidRange(range.clone()).err()?;
+        }
+
+        if range.is_empty() && replacement.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let Some((first, last)) = self.affected_parts(&range) else {
+            return Self::from_blob_async(store, replacement).await;
+        };
+
+        let (first_range, first_hkey) = &self.parts[first];
+        let (last_range, last_hkey) = &self.parts[last];
+
+        let pieces = if first == last {
+            let local = range.start - first_range.start..range.end - first_range.start;
+
+            splice_part_async(store.clone(), first_hkey, local, replacement).await?
+        } else {
+            let head = range.start - first_range.start..first_range.end - first_range.start;
+            let tail = 0..range.end - last_range.start;
+
+            let mut pieces =
+                splice_part_async(store.clone(), first_hkey, head, replacement).await?;
+
+            pieces.extend(splice_part_async(store.clone(), last_hkey, tail, &[]).await?);
+
+            pieces
+        };
+
+        let (pieces, depth) = if self.overflows(first, last, pieces.len()) {
+            let node = group_parts_async(store.clone(), self.concat_parts(pieces)).await?;
+            let stored = node.store_async(store).await?;
+
+            (
+                vec![(node.size(), stored.into())],
+                self.depth.max(node.depth() + 1),
+            )
+        } else {
+            (pieces, self.depth)
+        };
+
+        Ok(self.with_spliced_parts(first, last, pieces, range, replacement.len(), depth))
+    }
+}
+
+/// Splices a single part, returning the lengths and keys of the parts replacing it.
+async fn splice_part_async<C, E, S>(
+    store: S,
+    hkey: &Hkey,
+    range: Range,
+    replacement: &[u8],
+) -> Result<Vec<(usize, Hkey)>, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    if let Some(child) = expand_part_async(store.clone(), hkey).await? {
+        let child = child
+            .splice_async_box(store.clone(), range, replacement)
+            .await?;
+
+        if child.size() == 0 {
+            return Ok(Vec::new());
+        }
+
+        return Ok(vec![(child.size(), child.store_async(store).await?.into())]);
+    }
+
+    let original = hkey.resolve_async(store.clone()).await?;
+
+    if range.end > original.len() {
+        HkeyError::InvalidRange(range.clone()).err()?;
+    }
+
+    let mut data =
+        Vec::with_capacity(original.len() - (range.end - range.start) + replacement.len());
+
+    data.extend_from_slice(&original[..range.start]);
+    data.extend_from_slice(replacement);
+    data.extend_from_slice(&original[range.end..]);
+
+    leaf_pieces_async(store, &data).await
+}
+
+/// Stores spliced leaf data as segments, or as a single long part once it outgrows a level.
+async fn leaf_pieces_async<C, E, S>(store: S, data: &[u8]) -> Result<Vec<(usize, Hkey)>, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    let policy = store.policy();
+
+    if data.len() > policy.level_length() {
+        let lhkey = LongHkeyExpanded::from_blob_async(store.clone(), data)
+            .await?
+            .store_async(store)
+            .await?;
+
+        return Ok(vec![(data.len(), lhkey.into())]);
+    }
+
+    let mut pieces = Vec::new();
+
+    for chunk in data.chunks(policy.segment_size()) {
+        let hkey = store.put(Bytes::copy_from_slice(chunk)).await?;
+
+        pieces.push((chunk.len(), hkey));
+    }
+
+    Ok(pieces)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+
+    use crate::{InMemoryAsyncStore, InMemoryStore, LongHkeyExpanded};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn splice_async_matches_vec_splice() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(1_200_000);
+        let sync_store = InMemoryStore::default();
+        let sync = LongHkeyExpanded::from_blob(&sync_store, &data).expect("Failed to store");
+
+        block_on(async {
+            let lhkey = LongHkeyExpanded::from_blob_async(store.clone(), &data)
+                .await
+                .expect("Failed to store");
+
+            for (range, replacement) in [
+                (0..0, &b"x"[..]),
+                (600_000..600_000, b"inserted"),
+                (1_000_000..1_100_000, b"replaced"),
+                (10..1_199_990, b""),
+            ] {
+                let result = lhkey
+                    .splice_async(store.clone(), range.clone(), replacement)
+                    .await
+                    .expect("Failed to splice");
+
+                let mut expected = data.clone();
+
+                assert_eq!(
+                    result.to_string(),
+                    sync.splice(&sync_store, range.clone(), replacement)
+                        .expect("Failed to splice")
+                        .to_string()
+                );
+
+                expected.splice(range, replacement.iter().copied());
+
+                assert_eq!(
+                    result
+                        .resolve_async(store.clone())
+                        .await
+                        .expect("Failed to resolve")
+                        .as_ref(),
+                    expected.as_slice()
+                );
+            }
+        });
+    }
+}
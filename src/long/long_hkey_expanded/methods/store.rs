@@ -1,19 +1,47 @@
 use ps_datachunk::DataChunk;
-use ps_util::ToResult;
 
-use crate::{Hkey, HkeyError, LongHkey, LongHkeyExpanded, Store};
+use crate::{store::keyed::encrypt, HkeyError, LongHkey, LongHkeyExpanded, Store};
 
 impl LongHkeyExpanded {
+    /// Encrypts and stores the textual form of this node.
+    ///
+    /// The node is always encrypted, as [`Store::put`] would inline a short one.
     pub fn store<'a, C, E, S>(&self, store: &S) -> Result<LongHkey, E>
     where
         C: DataChunk,
         E: From<HkeyError> + Send,
         S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
     {
-        match store.put(self.to_string().as_bytes())? {
-            Hkey::Encrypted(hash, key) => LongHkey::from_hash_and_key(hash, key),
-            _ => Err(HkeyError::Storage)?,
-        }
-        .ok()
+        let string = self.to_string();
+        let encrypted = encrypt(string.as_bytes(), store.secret().as_ref())?;
+        let lhkey = LongHkey::from_hash_and_key(encrypted.hash(), encrypted.key());
+
+        store.put_encrypted(encrypted)?;
+
+        Ok(lhkey)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use crate::{InMemoryStore, LongHkeyExpanded, Store};
+
+    #[test]
+    fn short_nodes_are_encrypted() {
+        let store = InMemoryStore::default();
+        let data = [7u8; 65_537];
+
+        // The final segment holds a single byte, so its node is shorter than an inline key.
+        let hkey = store.put(&data).expect("Failed to put");
+
+        assert_eq!(
+            hkey.resolve(&store).expect("Failed to resolve").as_ref(),
+            data.as_slice()
+        );
+
+        let lhkey = LongHkeyExpanded::from_blob(&store, &data[..1]).expect("Failed to store");
+
+        lhkey.store(&store).expect("Failed to store the node");
     }
 }
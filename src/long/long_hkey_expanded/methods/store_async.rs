@@ -1,20 +1,24 @@
-use ps_datachunk::{Bytes, DataChunk};
+use ps_datachunk::DataChunk;
 use ps_promise::PromiseRejection;
-use ps_util::ToResult;
 
-use crate::{AsyncStore, Hkey, HkeyError, LongHkey, LongHkeyExpanded};
+use crate::{store::keyed::encrypt, AsyncStore, HkeyError, LongHkey, LongHkeyExpanded};
 
 impl LongHkeyExpanded {
+    /// Encrypts and stores the textual form of this node.
+    ///
+    /// The node is always encrypted, as [`AsyncStore::put`] would inline a short one.
     pub async fn store_async<C, E, S>(&self, store: S) -> Result<LongHkey, E>
     where
         C: DataChunk + Send,
         E: From<HkeyError> + PromiseRejection + Send,
         S: AsyncStore<Chunk = C, Error = E>,
     {
-        match store.put(Bytes::from_owner(self.to_string())).await? {
-            Hkey::Encrypted(hash, key) => LongHkey::from_hash_and_key(hash, key),
-            _ => Err(HkeyError::Storage)?,
-        }
-        .ok()
+        let string = self.to_string();
+        let encrypted = encrypt(string.as_bytes(), store.secret().as_ref())?;
+        let lhkey = LongHkey::from_hash_and_key(encrypted.hash(), encrypted.key());
+
+        store.put_encrypted(encrypted).await?;
+
+        Ok(lhkey)
     }
 }
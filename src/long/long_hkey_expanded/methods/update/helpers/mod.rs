@@ -1,5 +0,0 @@
-mod calculate_depth;
-mod calculate_segment_length;
-
-pub use calculate_depth::calculate_depth;
-pub use calculate_segment_length::*;
@@ -1,5 +1,3 @@
-pub mod helpers;
-
 #[cfg(test)]
 mod tests;
 
@@ -9,16 +7,12 @@ use std::{
     sync::Arc,
 };
 
-use helpers::{calculate_depth, calculate_segment_length};
 use ps_buffer::Buffer;
 use ps_datachunk::{Bytes, DataChunk, DataChunkError};
 use ps_util::ToResult;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::{
-    long::{long_hkey_expanded::constants::LHKEY_SEGMENT_MAX_LENGTH, LongHkeyExpanded},
-    Hkey, HkeyBug, HkeyError, Range, Store,
-};
+use crate::{long::LongHkeyExpanded, Hkey, HkeyBug, HkeyError, Range, Store};
 
 impl LongHkeyExpanded {
     /// only to be used with depth=0
@@ -48,12 +42,13 @@ impl LongHkeyExpanded {
         let data = &data[..length];
 
         let new_size = range.end.max(self.size);
+        let segment_size = store.policy().segment_size();
 
-        let parts: Result<Vec<(Range, Hkey)>, E> = (0..new_size.div_ceil(LHKEY_SEGMENT_MAX_LENGTH))
+        let parts: Result<Vec<(Range, Hkey)>, E> = (0..new_size.div_ceil(segment_size))
             .into_par_iter()
             .map(|index| {
-                let part_start = index.mul(LHKEY_SEGMENT_MAX_LENGTH);
-                let part_end = index.add(1).mul(LHKEY_SEGMENT_MAX_LENGTH).min(new_size);
+                let part_start = index.mul(segment_size);
+                let part_end = index.add(1).mul(segment_size).min(new_size);
 
                 // part is entirely outside of range
                 if range.end <= part_start || range.start >= part_end {
@@ -144,9 +139,10 @@ impl LongHkeyExpanded {
             return Ok(self.clone());
         }
 
+        let policy = store.policy();
         let length = range.end.max(self.size);
-        let depth = calculate_depth(self.depth, range.end);
-        let segment_length = calculate_segment_length(depth);
+        let depth = policy.depth(self.depth, range.end);
+        let segment_length = policy.segment_length(depth);
 
         if depth == 0 {
             return self.update_flat(store, data, &range);
@@ -170,7 +166,8 @@ impl LongHkeyExpanded {
 
                 let offset_start = start.max(range.start);
                 let offset_end = end.min(range.end);
-                let offset_range = offset_start..offset_end;
+                // the segment is addressed relative to its own start
+                let offset_range = offset_start - start..offset_end - start;
                 let data_slice_start = offset_start.sub(range.start);
                 let data_slice_end = offset_end.sub(range.start);
                 let data_slice_range = data_slice_start..data_slice_end;
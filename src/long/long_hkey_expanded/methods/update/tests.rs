@@ -1,7 +1,7 @@
 #![allow(clippy::expect_used)]
 
 use super::*;
-use crate::{InMemoryStore, InMemoryStoreError};
+use crate::{long::LHKEY_SEGMENT_MAX_LENGTH, InMemoryStore, InMemoryStoreError};
 
 #[allow(clippy::cast_possible_truncation)]
 fn sequential_bytes(len: usize) -> Vec<u8> {
@@ -252,7 +252,7 @@ fn update_extending_past_the_end_keeps_the_head() {
     assert_eq!(&resolved[50..], &patch[..]);
 }
 
-/// `normalize_segment` preserves content even though its final part range overruns.
+/// `normalize_segment` preserves content, and its final part range ends where the content does.
 #[test]
 fn normalize_segment_preserves_content() {
     let store = InMemoryStore::default();
@@ -269,6 +269,51 @@ fn normalize_segment_preserves_content() {
         .expect("Failed to resolve");
 
     assert_eq!(&resolved[..], &original[..]);
+    assert_eq!(
+        segment.parts.last().map(|part| part.0.end),
+        Some(original.len())
+    );
+}
+
+/// Writes `patch_len` bytes at `offset` into a tree built by `from_blob`, and asserts that the
+/// result resolves to the original patched, zero-filling any gap past its end.
+fn assert_update_from_blob(original_len: usize, offset: usize, patch_len: usize) {
+    let store = InMemoryStore::default();
+
+    let original = sequential_bytes(original_len);
+    let lhkey = LongHkeyExpanded::from_blob(&store, &original).expect("Failed to store");
+
+    let patch = vec![0xCC; patch_len];
+    let range = offset..offset + patch_len;
+
+    let updated = lhkey
+        .update(&store, &patch, range.clone())
+        .expect("Failed to update");
+
+    let mut expected = original;
+
+    expected.resize(expected.len().max(range.end), 0);
+    expected[range].copy_from_slice(&patch);
+
+    let resolved = updated
+        .resolve_slice(&store, 0..expected.len())
+        .expect("Failed to resolve");
+
+    assert_eq!(updated.size(), expected.len(), "Reported size");
+    assert_eq!(&resolved[..], &expected[..]);
+}
+
+/// Segments past the first are addressed relative to their own start.
+#[test]
+fn update_at_depth_one_within_a_later_segment() {
+    assert_update_from_blob(70_000, 65_000, 1_000);
+    assert_update_from_blob(200_000, 150_000, 10);
+}
+
+/// Growing a flat tree past a level boundary reuses none of the parts covering the gap.
+#[test]
+fn update_at_depth_one_past_the_end() {
+    assert_update_from_blob(50_000, 60_000, 20_000);
 }
 
 /// A `LongHkeyExpanded` must survive a `Display` and `parse` round trip.
@@ -0,0 +1,376 @@
+use std::{
+    cmp::Ordering::{Equal, Greater, Less},
+    future::Future,
+    ops::{Add, Mul, Sub},
+    pin::Pin,
+    sync::Arc,
+};
+
+use futures::future::try_join_all;
+use ps_buffer::Buffer;
+use ps_datachunk::{Bytes, DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+use ps_util::ToResult;
+
+use crate::{long::LongHkeyExpanded, AsyncStore, HkeyBug, HkeyError, Range};
+
+impl LongHkeyExpanded {
+    /// Asynchronous counterpart of [`LongHkeyExpanded::update_flat`].
+    ///
+    /// only to be used with depth=0
+    pub async fn update_flat_async<C, E, S>(
+        &self,
+        store: S,
+        data: &[u8],
+        range: &Range,
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if range.start > range.end {
+            HkeyError::InvalidRange(range.clone()).err()?;
+        }
+
+        let length = data.len().min(range.end - range.start);
+
+        // Writing no bytes is the identity
+        if length == 0 {
+            return Ok(self.clone());
+        }
+
+        let range = range.start..range.start + length;
+        let data = &data[..length];
+        let range = &range;
+
+        let new_size = range.end.max(self.size);
+        let segment_size = store.policy().segment_size();
+
+        let futures = (0..new_size.div_ceil(segment_size)).map(|index| {
+            let store = store.clone();
+
+            async move {
+                let part_start = index.mul(segment_size);
+                let part_end = index.add(1).mul(segment_size).min(new_size);
+
+                // part is entirely outside of range
+                if range.end <= part_start || range.start >= part_end {
+                    if let Some(segment) = self.parts.get(index) {
+                        if segment.0.start == part_start && segment.0.end == part_end {
+                            return Ok::<_, E>(segment.clone());
+                        }
+                    }
+
+                    let slice = self
+                        .resolve_slice_exact_async(store.clone(), part_start..part_end)
+                        .await?;
+
+                    return (part_start..part_end, store.put(slice).await?).ok();
+                }
+
+                // part is intirely within range
+                if part_start >= range.start && part_end <= range.end {
+                    let slice = &data[part_start - range.start..part_end - range.start];
+
+                    return (
+                        part_start..part_end,
+                        store.put(Bytes::copy_from_slice(slice)).await?,
+                    )
+                        .ok();
+                }
+
+                // range is entirely within part
+                if range.start >= part_start && range.end <= part_end {
+                    let mut buffer = Vec::with_capacity(part_end - part_start);
+
+                    let original = self
+                        .resolve_slice_exact_async(store.clone(), part_start..part_end)
+                        .await?;
+
+                    let data_start = range.start - part_start;
+                    let data_end = data_start + data.len();
+
+                    buffer.extend_from_slice(&original[..data_start]);
+                    buffer.extend_from_slice(data);
+                    buffer.extend_from_slice(&original[data_end..]);
+
+                    return (part_start..part_end, store.put(buffer.into()).await?).ok();
+                }
+
+                // part begins with original data
+                if range.start > part_start {
+                    let mut buffer = Vec::with_capacity(part_end - part_start);
+
+                    buffer.extend_from_slice(
+                        &self
+                            .resolve_slice_exact_async(store.clone(), part_start..range.start)
+                            .await?,
+                    );
+                    buffer.extend_from_slice(&data[..part_end - range.start]);
+
+                    return (part_start..part_end, store.put(buffer.into()).await?).ok();
+                }
+
+                // part begins with new data
+                if part_start >= range.start {
+                    let mut buffer = Vec::with_capacity(part_end - part_start);
+
+                    let data_start = part_start - range.start;
+
+                    buffer.extend_from_slice(&data[data_start..]);
+                    buffer.extend_from_slice(
+                        &self
+                            .resolve_slice_async(store.clone(), range.end..part_end)
+                            .await?,
+                    );
+
+                    return (part_start..part_end, store.put(buffer.into()).await?).ok();
+                }
+
+                // all variants have been exhausted
+                Err(HkeyError::Bug(HkeyBug::UpdateFlatAllVariantsExhausted))?
+            }
+        });
+
+        let parts = try_join_all(futures).await?;
+
+        let lhkey = Self::new(0, new_size, Arc::from(parts.into_boxed_slice()));
+
+        Ok(lhkey)
+    }
+
+    pub fn update_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+        data: &'a [u8],
+        range: Range,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.update_async(store, data, range).await })
+    }
+
+    /// Asynchronous counterpart of [`LongHkeyExpanded::update`].
+    ///
+    /// Only the segments overlapping `range` are fetched and stored again, concurrently; the
+    /// resulting tree is identical to the one [`LongHkeyExpanded::update`] produces.
+    pub async fn update_async<C, E, S>(
+        &self,
+        store: S,
+        data: &[u8],
+        range: Range,
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if range.start > range.end {
+            HkeyError::InvalidRange(range.clone()).err()?;
+        }
+
+        let range = range.start..range.end.min(range.start + data.len());
+
+        // Writing no bytes is the identity; see `update` for why this also ends the recursion.
+        if range.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let policy = store.policy();
+        let length = range.end.max(self.size);
+        let depth = policy.depth(self.depth, range.end);
+        let segment_length = policy.segment_length(depth);
+
+        if depth == 0 {
+            return self.update_flat_async(store, data, &range).await;
+        }
+
+        let range = &range;
+
+        let futures = (0..length.div_ceil(segment_length)).map(|index| {
+            let store = store.clone();
+
+            async move {
+                let start = index * segment_length;
+                let end = (index + 1).mul(segment_length).min(length);
+                let segment_range = start.min(self.size)..end.min(self.size);
+                let segment = self
+                    .normalize_segment_async(store.clone(), depth - 1, segment_range)
+                    .await?;
+
+                if start >= range.end || end <= range.start {
+                    // outside of modified range
+                    return Ok((start..end, segment.store_async(store).await?.into()));
+                }
+
+                let offset_start = start.max(range.start);
+                let offset_end = end.min(range.end);
+                // the segment is addressed relative to its own start
+                let offset_range = offset_start - start..offset_end - start;
+                let data_slice_start = offset_start.sub(range.start);
+                let data_slice_end = offset_end.sub(range.start);
+                let data_slice_range = data_slice_start..data_slice_end;
+                let data_slice = &data[data_slice_range];
+
+                let segment = segment
+                    .update_async_box(store.clone(), data_slice, offset_range)
+                    .await?;
+
+                Ok::<_, E>((start..end, segment.store_async(store).await?.into()))
+            }
+        });
+
+        let parts = Arc::from(try_join_all(futures).await?.into_boxed_slice());
+
+        let lhkey = Self::new(depth, length, parts);
+
+        Ok(lhkey)
+    }
+
+    /// Asynchronous counterpart of `resolve_slice_exact`, zero-filling whatever lies past the end
+    /// of the receiver.
+    async fn resolve_slice_exact_async<C, E, S>(&self, store: S, range: Range) -> Result<Bytes, E>
+    where
+        C: DataChunk + Send,
+        E: From<HkeyError> + From<DataChunkError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let resolved = self.resolve_slice_async(store, range.clone()).await?;
+        let length = range.end - range.start;
+
+        match resolved.len().cmp(&length) {
+            Greater => Err(HkeyError::Bug(HkeyBug::ResolvedSliceTooLong))?,
+            Equal => return Ok(resolved),
+            Less => {}
+        }
+
+        let mut buffer = Buffer::with_capacity(length).map_err(HkeyError::from)?;
+
+        buffer
+            .extend_from_slice(&resolved)
+            .map_err(HkeyError::from)?;
+
+        buffer.resize(length, 0).map_err(HkeyError::from)?;
+
+        Ok(buffer.into())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+
+    use crate::{long::LongHkeyExpanded, InMemoryAsyncStore, InMemoryStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Applies the same write through `update` and `update_async`, starting from the same
+    /// original, and asserts that both produce the same tree.
+    fn assert_update_matches(original_len: usize, offset: usize, patch_len: usize) {
+        let store = InMemoryStore::default();
+        let async_store = InMemoryAsyncStore::default();
+
+        let original = sequential_bytes(original_len);
+        let patch = vec![0xA5; patch_len];
+        let range = offset..offset + patch_len;
+
+        let lhkey = LongHkeyExpanded::from_blob(&store, &original).expect("Failed to store");
+        let async_lhkey = block_on(LongHkeyExpanded::from_blob_async(
+            async_store.clone(),
+            &original,
+        ))
+        .expect("Failed to store");
+
+        assert_eq!(lhkey, async_lhkey, "The originals differ");
+
+        let updated = lhkey
+            .update(&store, &patch, range.clone())
+            .expect("Failed to update");
+        let async_updated = block_on(async_lhkey.update_async(async_store.clone(), &patch, range))
+            .expect("Failed to update");
+
+        assert_eq!(updated, async_updated);
+
+        let mut expected = original;
+
+        expected.resize(expected.len().max(offset + patch_len), 0);
+        expected[offset..offset + patch_len].copy_from_slice(&patch);
+
+        let resolved =
+            block_on(async_updated.resolve_async(async_store)).expect("Failed to resolve");
+
+        assert_eq!(resolved.as_ref(), expected.as_slice());
+    }
+
+    #[test]
+    fn update_async_matches_update_at_depth_zero() {
+        assert_update_matches(10_000, 100, 50);
+        assert_update_matches(10_000, 4000, 200);
+        assert_update_matches(10_000, 9_990, 5_000);
+    }
+
+    #[test]
+    fn update_async_matches_update_at_depth_one() {
+        assert_update_matches(70_000, 5, 10);
+        assert_update_matches(70_000, 65_000, 1_000);
+        assert_update_matches(50_000, 60_000, 20_000);
+    }
+
+    #[test]
+    fn update_flat_async_matches_update_flat() {
+        let store = InMemoryStore::default();
+        let async_store = InMemoryAsyncStore::default();
+
+        let original = sequential_bytes(9_000);
+        let lhkey = LongHkeyExpanded::from_blob(&store, &original).expect("Failed to store");
+        let range = 4_090..4_110;
+
+        block_on(LongHkeyExpanded::from_blob_async(
+            async_store.clone(),
+            &original,
+        ))
+        .expect("Failed to store");
+
+        let updated = lhkey
+            .update_flat(&store, &[7; 20], &range)
+            .expect("Failed to update");
+        let async_updated = block_on(lhkey.update_flat_async(async_store, &[7; 20], &range))
+            .expect("Failed to update");
+
+        assert_eq!(updated, async_updated);
+    }
+
+    #[test]
+    fn normalize_segment_async_matches_normalize_segment() {
+        let store = InMemoryStore::default();
+        let async_store = InMemoryAsyncStore::default();
+
+        let original = sequential_bytes(100_000);
+        let lhkey = LongHkeyExpanded::from_blob(&store, &original).expect("Failed to store");
+
+        block_on(LongHkeyExpanded::from_blob_async(
+            async_store.clone(),
+            &original,
+        ))
+        .expect("Failed to store");
+
+        for range in [0..100_000, 1_000..9_000, 65_536..100_000, 3..70_000] {
+            let segment = lhkey
+                .normalize_segment(&store, 0, range.clone())
+                .expect("Failed to normalize");
+            let async_segment =
+                block_on(lhkey.normalize_segment_async(async_store.clone(), 0, range))
+                    .expect("Failed to normalize");
+
+            assert_eq!(segment, async_segment);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod builder;
+pub mod chunking;
 pub mod constants;
 pub mod implementations;
 pub mod methods;
@@ -37,6 +39,18 @@ impl LongHkeyExpanded {
         self.size
     }
 
+    /// Returns the depth this node was built at.
+    #[must_use]
+    pub const fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the parts of this node, each with the range it covers.
+    #[must_use]
+    pub fn parts(&self) -> &[(Range, Hkey)] {
+        &self.parts
+    }
+
     pub fn resolve<'a, C, E, S>(&self, store: &'a S) -> Result<Bytes, E>
     where
         C: DataChunk,
@@ -300,4 +314,34 @@ mod tests {
             Err(InMemoryStoreError::Hkey(HkeyError::InvalidRange(_)))
         ));
     }
+
+    #[test]
+    fn update_async_inverted_range_errors() {
+        let store = InMemoryAsyncStore::default();
+
+        let result = futures::executor::block_on(LongHkeyExpanded::default().update_async(
+            store,
+            b"data",
+            3..1,
+        ));
+
+        assert!(matches!(
+            result,
+            Err(InMemoryAsyncStoreError::Hkey(HkeyError::InvalidRange(_)))
+        ));
+    }
+
+    #[test]
+    fn normalize_segment_async_inverted_range_errors() {
+        let store = InMemoryAsyncStore::default();
+
+        let result = futures::executor::block_on(
+            LongHkeyExpanded::default().normalize_segment_async(store, 0, 3..1),
+        );
+
+        assert!(matches!(
+            result,
+            Err(InMemoryAsyncStoreError::Hkey(HkeyError::InvalidRange(_)))
+        ));
+    }
 }
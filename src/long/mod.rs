@@ -2,4 +2,9 @@ mod long_hkey;
 mod long_hkey_expanded;
 
 pub use long_hkey::LongHkey;
+pub use long_hkey_expanded::builder::LongHkeyBuilder;
+pub use long_hkey_expanded::chunking::{Chunking, FastCdc};
+pub use long_hkey_expanded::methods::diff::LongHkeyDiff;
 pub use long_hkey_expanded::LongHkeyExpanded;
+
+pub(crate) use long_hkey_expanded::constants::*;
@@ -0,0 +1,417 @@
+//! A self-describing binary encoding of every [`Hkey`] variant.
+//!
+//! The encoding is the version byte [`BINARY_FORMAT_VERSION`] followed by one node. A node is
+//! a tag byte followed by its fields, where hashes are in their compact form and integers are
+//! unsigned LEB128:
+//!
+//! | tag | variant              | fields                                               |
+//! |-----|----------------------|------------------------------------------------------|
+//! | 0   | `Empty`              |                                                      |
+//! | 1   | `Raw`                | length, bytes                                        |
+//! | 2   | `Base64`             | length, bytes                                        |
+//! | 3   | `Direct`             | hash                                                 |
+//! | 4   | `Encrypted`          | hash, key                                            |
+//! | 5   | `ListRef`            | hash, key                                            |
+//! | 6   | `List`               | count, nodes                                         |
+//! | 7   | `LongHkey`           | hash, key                                            |
+//! | 8   | `LongHkeyExpanded`   | depth, size, count, (start, length, node) per part   |
+
+use ps_datachunk::Bytes;
+use ps_hash::Hash;
+
+use crate::{
+    Hkey, HkeyBinaryError, LongHkey, LongHkeyExpanded, BINARY_FORMAT_VERSION, HASH_SIZE_COMPACT,
+    MAX_BINARY_DEPTH,
+};
+
+const TAG_EMPTY: u8 = 0;
+const TAG_RAW: u8 = 1;
+const TAG_BASE64: u8 = 2;
+const TAG_DIRECT: u8 = 3;
+const TAG_ENCRYPTED: u8 = 4;
+const TAG_LIST_REF: u8 = 5;
+const TAG_LIST: u8 = 6;
+const TAG_LONG_HKEY: u8 = 7;
+const TAG_LONG_HKEY_EXPANDED: u8 = 8;
+
+impl Hkey {
+    /// Encodes this [`Hkey`] without shrinking it, so that [`Hkey::decode_binary`] returns
+    /// an identical [`Hkey`] without accessing a store.
+    #[must_use]
+    pub fn encode_binary(&self) -> Bytes {
+        let mut buffer = vec![BINARY_FORMAT_VERSION];
+
+        encode_node(self, &mut buffer);
+
+        buffer.into()
+    }
+
+    /// Decodes the output of [`Hkey::encode_binary`].
+    pub fn decode_binary(bytes: &[u8]) -> Result<Self, HkeyBinaryError> {
+        let mut decoder = Decoder { bytes, position: 0 };
+
+        match decoder.byte()? {
+            BINARY_FORMAT_VERSION => {}
+            version => return Err(HkeyBinaryError::UnsupportedVersion(version)),
+        }
+
+        let hkey = decoder.node(0)?;
+
+        if decoder.position < bytes.len() {
+            return Err(HkeyBinaryError::TrailingBytes(decoder.position));
+        }
+
+        Ok(hkey)
+    }
+}
+
+fn encode_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        #[allow(clippy::cast_possible_truncation)]
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    buffer.push(value as u8);
+}
+
+fn encode_len(value: usize, buffer: &mut Vec<u8>) {
+    encode_varint(value as u64, buffer);
+}
+
+fn encode_hashes(tag: u8, hash: &Hash, key: &Hash, buffer: &mut Vec<u8>) {
+    buffer.push(tag);
+    buffer.extend_from_slice(hash.compact());
+    buffer.extend_from_slice(key.compact());
+}
+
+fn encode_node(hkey: &Hkey, buffer: &mut Vec<u8>) {
+    match hkey {
+        Hkey::Empty => buffer.push(TAG_EMPTY),
+        Hkey::Raw(raw) => {
+            buffer.push(TAG_RAW);
+            encode_len(raw.len(), buffer);
+            buffer.extend_from_slice(raw);
+        }
+        Hkey::Base64(base64) => {
+            buffer.push(TAG_BASE64);
+            encode_len(base64.len(), buffer);
+            buffer.extend_from_slice(base64.as_bytes());
+        }
+        Hkey::Direct(hash) => {
+            buffer.push(TAG_DIRECT);
+            buffer.extend_from_slice(hash.compact());
+        }
+        Hkey::Encrypted(hash, key) => encode_hashes(TAG_ENCRYPTED, hash, key, buffer),
+        Hkey::ListRef(hash, key) => encode_hashes(TAG_LIST_REF, hash, key, buffer),
+        Hkey::List(list) => {
+            buffer.push(TAG_LIST);
+            encode_len(list.len(), buffer);
+
+            for item in list.iter() {
+                encode_node(item, buffer);
+            }
+        }
+        Hkey::LongHkey(lhkey) => {
+            encode_hashes(TAG_LONG_HKEY, lhkey.hash_ref(), lhkey.key_ref(), buffer);
+        }
+        Hkey::LongHkeyExpanded(lhkey) => {
+            buffer.push(TAG_LONG_HKEY_EXPANDED);
+            encode_varint(lhkey.depth().into(), buffer);
+            encode_len(lhkey.size(), buffer);
+            encode_len(lhkey.parts().len(), buffer);
+
+            for (range, part) in lhkey.parts() {
+                encode_len(range.start, buffer);
+                encode_len(range.end.saturating_sub(range.start), buffer);
+                encode_node(part, buffer);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Result<u8, HkeyBinaryError> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or(HkeyBinaryError::UnexpectedEnd(self.position))?;
+
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], HkeyBinaryError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(HkeyBinaryError::UnexpectedEnd(self.bytes.len()))?;
+
+        let slice = &self.bytes[self.position..end];
+
+        self.position = end;
+
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, HkeyBinaryError> {
+        let start = self.position;
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7F);
+
+            if bits << shift >> shift != bits {
+                return Err(HkeyBinaryError::InvalidVarint(start));
+            }
+
+            value |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(HkeyBinaryError::InvalidVarint(start))
+    }
+
+    fn len(&mut self) -> Result<usize, HkeyBinaryError> {
+        let start = self.position;
+
+        usize::try_from(self.varint()?).map_err(|_| HkeyBinaryError::InvalidVarint(start))
+    }
+
+    fn hash(&mut self) -> Result<Hash, HkeyBinaryError> {
+        Ok(Hash::validate(self.take(HASH_SIZE_COMPACT)?)?)
+    }
+
+    fn node(&mut self, depth: usize) -> Result<Hkey, HkeyBinaryError> {
+        if depth > MAX_BINARY_DEPTH {
+            return Err(HkeyBinaryError::TooDeep(MAX_BINARY_DEPTH));
+        }
+
+        let tag_position = self.position;
+
+        let hkey = match self.byte()? {
+            TAG_EMPTY => Hkey::Empty,
+            TAG_RAW => {
+                let len = self.len()?;
+
+                Hkey::from_raw(self.take(len)?)?
+            }
+            TAG_BASE64 => {
+                let len = self.len()?;
+                let start = self.position;
+                let base64 = std::str::from_utf8(self.take(len)?)
+                    .map_err(|_| HkeyBinaryError::InvalidBase64(start))?;
+
+                Hkey::from_base64_slice(base64)?
+            }
+            TAG_DIRECT => Hkey::Direct(self.hash()?),
+            TAG_ENCRYPTED => Hkey::Encrypted(self.hash()?, self.hash()?),
+            TAG_LIST_REF => Hkey::ListRef(self.hash()?, self.hash()?),
+            TAG_LIST => {
+                let count = self.len()?;
+                // Every item takes at least one byte, which bounds the allocation.
+                let mut items = Vec::with_capacity(count.min(self.bytes.len() - self.position));
+
+                for _ in 0..count {
+                    items.push(self.node(depth + 1)?);
+                }
+
+                Hkey::List(items.into())
+            }
+            TAG_LONG_HKEY => {
+                Hkey::LongHkey(LongHkey::from_hash_and_key(self.hash()?, self.hash()?))
+            }
+            TAG_LONG_HKEY_EXPANDED => {
+                let depth_position = self.position;
+                let lhkey_depth = u32::try_from(self.varint()?)
+                    .map_err(|_| HkeyBinaryError::InvalidVarint(depth_position))?;
+                let size = self.len()?;
+                let count = self.len()?;
+                let mut parts = Vec::with_capacity(count.min(self.bytes.len() - self.position));
+
+                for _ in 0..count {
+                    let range_position = self.position;
+                    let start = self.len()?;
+                    let end = start
+                        .checked_add(self.len()?)
+                        .ok_or(HkeyBinaryError::InvalidRange(range_position))?;
+
+                    parts.push((start..end, self.node(depth + 1)?));
+                }
+
+                Hkey::LongHkeyExpanded(LongHkeyExpanded::new(lhkey_depth, size, parts.into()))
+            }
+            tag => {
+                return Err(HkeyBinaryError::UnknownTag {
+                    position: tag_position,
+                    tag,
+                })
+            }
+        };
+
+        Ok(hkey)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_hash::hash;
+
+    use crate::{
+        Hkey, HkeyBinaryError, LongHkey, LongHkeyExpanded, BINARY_FORMAT_VERSION, MAX_BINARY_DEPTH,
+    };
+
+    fn mk_hash(data: &[u8]) -> ps_hash::Hash {
+        hash(data).expect("Failed to hash")
+    }
+
+    fn every_variant() -> Vec<Hkey> {
+        let leaves = vec![
+            Hkey::Empty,
+            Hkey::from_raw(&[0, 1, 2, 255]).expect("Failed to allocate"),
+            Hkey::from_base64_slice("SGVsbG8").expect("Failed to allocate"),
+            Hkey::Direct(mk_hash(b"direct")),
+            Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key")),
+            Hkey::ListRef(mk_hash(b"list-hash"), mk_hash(b"list-key")),
+            Hkey::LongHkey(LongHkey::from_hash_and_key(
+                mk_hash(b"long-hash"),
+                mk_hash(b"long-key"),
+            )),
+        ];
+
+        let expanded = LongHkeyExpanded::new(
+            2,
+            1 << 40,
+            vec![
+                (0..1 << 39, Hkey::List(leaves.clone().into())),
+                (1 << 39..1 << 40, Hkey::Direct(mk_hash(b"second half"))),
+            ]
+            .into(),
+        );
+
+        let mut hkeys = leaves.clone();
+
+        hkeys.push(Hkey::List(Vec::new().into()));
+        hkeys.push(Hkey::List(vec![Hkey::Empty].into()));
+        hkeys.push(Hkey::List(leaves.into()));
+        hkeys.push(Hkey::LongHkeyExpanded(LongHkeyExpanded::default()));
+        hkeys.push(Hkey::LongHkeyExpanded(expanded));
+
+        hkeys
+    }
+
+    #[test]
+    fn roundtrip_every_variant() {
+        for hkey in every_variant() {
+            let encoded = hkey.encode_binary();
+
+            assert_eq!(encoded[0], BINARY_FORMAT_VERSION);
+
+            let decoded = Hkey::decode_binary(&encoded).expect("Failed to decode");
+
+            assert_eq!(decoded, hkey);
+            assert_eq!(decoded.encode_binary(), encoded);
+        }
+    }
+
+    #[test]
+    fn hashes_are_compact() {
+        let encrypted = Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key"));
+
+        assert_eq!(
+            encrypted.encode_binary().len(),
+            2 + 2 * crate::HASH_SIZE_COMPACT
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let direct = Hkey::Direct(mk_hash(b"direct")).encode_binary();
+
+        assert!(matches!(
+            Hkey::decode_binary(&[]),
+            Err(HkeyBinaryError::UnexpectedEnd(0))
+        ));
+
+        assert!(matches!(
+            Hkey::decode_binary(&[BINARY_FORMAT_VERSION + 1, 0]),
+            Err(HkeyBinaryError::UnsupportedVersion(_))
+        ));
+
+        assert!(matches!(
+            Hkey::decode_binary(&direct[..direct.len() - 1]),
+            Err(HkeyBinaryError::UnexpectedEnd(_))
+        ));
+
+        assert!(matches!(
+            Hkey::decode_binary(&[direct.as_ref(), &[0]].concat()),
+            Err(HkeyBinaryError::TrailingBytes(pos)) if pos == direct.len()
+        ));
+
+        assert!(matches!(
+            Hkey::decode_binary(&[BINARY_FORMAT_VERSION, 42]),
+            Err(HkeyBinaryError::UnknownTag {
+                position: 1,
+                tag: 42
+            })
+        ));
+
+        // A list claiming more items than there are bytes.
+        assert!(matches!(
+            Hkey::decode_binary(&[BINARY_FORMAT_VERSION, 6, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+            Err(HkeyBinaryError::UnexpectedEnd(_))
+        ));
+
+        // A varint longer than 64 bits.
+        assert!(matches!(
+            Hkey::decode_binary(&[&[BINARY_FORMAT_VERSION, 1][..], &[0xFF; 11]].concat()),
+            Err(HkeyBinaryError::InvalidVarint(2))
+        ));
+
+        // A Raw value exceeding its capacity.
+        assert!(matches!(
+            Hkey::decode_binary(&[&[BINARY_FORMAT_VERSION, 1, 100][..], &[0; 100]].concat()),
+            Err(HkeyBinaryError::Construction(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_excessive_nesting() {
+        let mut nested = vec![BINARY_FORMAT_VERSION];
+
+        for _ in 0..=MAX_BINARY_DEPTH {
+            nested.extend_from_slice(&[6, 1]);
+        }
+
+        nested.push(0);
+
+        assert!(matches!(
+            Hkey::decode_binary(&nested),
+            Err(HkeyBinaryError::TooDeep(MAX_BINARY_DEPTH))
+        ));
+
+        let mut hkey = Hkey::Empty;
+
+        for _ in 0..MAX_BINARY_DEPTH {
+            hkey = Hkey::List(vec![hkey].into());
+        }
+
+        assert_eq!(
+            Hkey::decode_binary(&hkey.encode_binary()).expect("Failed to decode"),
+            hkey
+        );
+    }
+}
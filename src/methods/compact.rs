@@ -2,7 +2,7 @@ use ps_base64::base64;
 use ps_datachunk::Bytes;
 use ps_hash::{Hash, HASH_SIZE_COMPACT};
 
-use crate::{Hkey, Store};
+use crate::{Hkey, LongHkey, Store, LONG_HKEY_COMPACT_PREFIX, LONG_HKEY_SIZE_COMPACT};
 
 impl Hkey {
     pub fn compact<S: Store>(&self, store: &S) -> Result<Bytes, S::Error> {
@@ -12,7 +12,8 @@ impl Hkey {
             Self::Direct(hash) => Ok(Bytes::copy_from_slice(hash.compact())),
             Self::Encrypted(hash, key) => Ok(compact_dhash(&hash, &key, 0)),
             Self::ListRef(hash, key) => Ok(compact_dhash(&hash, &key, 1)),
-            Self::LongHkey(lhkey) => Ok(compact_dhash(lhkey.hash_ref(), lhkey.key_ref(), 1)),
+            Self::LongHkey(lhkey) => Ok(compact_long_hkey(&lhkey)),
+            Self::Empty => Ok(Bytes::new()),
             hkey => hkey.shrink(store)?.compact(store),
         }
     }
@@ -30,6 +31,18 @@ pub fn compact_dhash(a: &Hash, b: &Hash, flag: u8) -> Bytes {
     double.into()
 }
 
+/// Prefixes the hash and key of a [`LongHkey`], so that its compact form is distinct from that
+/// of a [`Hkey::ListRef`].
+pub fn compact_long_hkey(lhkey: &LongHkey) -> Bytes {
+    let mut compact = Vec::with_capacity(LONG_HKEY_SIZE_COMPACT);
+
+    compact.push(LONG_HKEY_COMPACT_PREFIX);
+    compact.extend_from_slice(lhkey.hash_ref().compact());
+    compact.extend_from_slice(lhkey.key_ref().compact());
+
+    compact.into()
+}
+
 #[cfg(test)]
 #[allow(clippy::expect_used)]
 mod tests {
@@ -41,9 +54,13 @@ mod tests {
 
     use std::sync::Arc;
 
+    use ps_datachunk::Bytes;
     use ps_hash::Hash;
 
-    use crate::{Hkey, InMemoryStore, LongHkeyExpanded, Store};
+    use crate::{
+        AsyncStore, Hkey, HkeyFromCompactError, InMemoryAsyncStore, InMemoryStore, LongHkey,
+        LongHkeyExpanded, Store, LONG_HKEY_SIZE_COMPACT,
+    };
 
     #[test]
     fn test_raw_variant_roundtrip() {
@@ -164,10 +181,88 @@ mod tests {
             .shrink(&store)
             .expect("Failed to shrink LongHkeyExpanded");
 
+        assert!(matches!(hkey, Hkey::LongHkey(_)));
+
         let compact = hkey.compact(&store).expect("Failed to compact Hkey");
         let restored = Hkey::from_compact(&compact).expect("Failed to restore Hkey");
 
-        assert_eq!(hkey.to_string(), restored.to_string());
+        assert_eq!(compact.len(), LONG_HKEY_SIZE_COMPACT);
+        assert_eq!(hkey, restored);
+        assert_eq!(
+            restored.resolve(&store).expect("Failed to resolve"),
+            b"Hello, world".repeat(200)
+        );
+    }
+
+    #[test]
+    fn test_longhkey_compact_differs_from_listref() {
+        let hash = Hash::hash(b"hash").expect("Failed to hash data");
+        let key = Hash::hash(b"key").expect("Failed to hash data");
+        let store = InMemoryStore::default();
+
+        let list_ref = Hkey::ListRef(hash, key)
+            .compact(&store)
+            .expect("Failed to compact Hkey");
+        let long = Hkey::LongHkey(LongHkey::from_hash_and_key(hash, key))
+            .compact(&store)
+            .expect("Failed to compact Hkey");
+
+        assert_ne!(list_ref, long);
+        assert_eq!(
+            Hkey::from_compact(&list_ref).expect("Failed to restore Hkey"),
+            Hkey::ListRef(hash, key)
+        );
+        assert_eq!(
+            Hkey::from_compact(&long).expect("Failed to restore Hkey"),
+            Hkey::LongHkey(LongHkey::from_hash_and_key(hash, key))
+        );
+
+        let mut invalid = long.to_vec();
+
+        invalid[0] = 0;
+
+        assert!(matches!(
+            Hkey::from_compact(&invalid),
+            Err(HkeyFromCompactError::LongHkeyPrefix(0))
+        ));
+    }
+
+    #[test]
+    fn test_longhkey_compact_async() {
+        let store = InMemoryAsyncStore::default();
+        let data = b"Hello, world".repeat(10_000);
+
+        futures::executor::block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put data");
+
+            let expanded = match &hkey {
+                Hkey::LongHkey(lhkey) => lhkey
+                    .expand_async(store.clone())
+                    .await
+                    .expect("Failed to expand"),
+                hkey => panic!("Expected Hkey::LongHkey, got {hkey}"),
+            };
+
+            for hkey in [hkey, Hkey::LongHkeyExpanded(expanded)] {
+                let compact = hkey
+                    .compact_async(store.clone())
+                    .await
+                    .expect("Failed to compact Hkey");
+                let restored = Hkey::from_compact(&compact).expect("Failed to restore Hkey");
+
+                assert!(matches!(restored, Hkey::LongHkey(_)));
+                assert_eq!(
+                    restored
+                        .resolve_async(store.clone())
+                        .await
+                        .expect("Failed to resolve"),
+                    data
+                );
+            }
+        });
     }
 
     #[test]
@@ -1,7 +1,10 @@
 use ps_base64::base64;
 use ps_datachunk::Bytes;
 
-use crate::{methods::compact::compact_dhash, AsyncStore, Hkey};
+use crate::{
+    methods::compact::{compact_dhash, compact_long_hkey},
+    AsyncStore, Hkey,
+};
 
 impl Hkey {
     pub async fn compact_async<S: AsyncStore>(&self, store: S) -> Result<Bytes, S::Error> {
@@ -11,13 +14,9 @@ impl Hkey {
             Self::Direct(hash) => Ok(Bytes::copy_from_slice(hash.compact())),
             Self::Encrypted(hash, key) => Ok(compact_dhash(&hash, &key, 0)),
             Self::ListRef(hash, key) => Ok(compact_dhash(&hash, &key, 1)),
-            Self::LongHkey(lhkey) => Ok(compact_dhash(lhkey.hash_ref(), lhkey.key_ref(), 1)),
-            hkey => {
-                hkey.shrink_async(store.clone())
-                    .await?
-                    .compact_async(store)
-                    .await
-            }
+            Self::LongHkey(lhkey) => Ok(compact_long_hkey(&lhkey)),
+            Self::Empty => Ok(Bytes::new()),
+            hkey => Box::pin(hkey.shrink_async(store.clone()).await?.compact_async(store)).await,
         }
     }
 }
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use ps_datachunk::{DataChunk, DataChunkError};
+
+use crate::{long::LHKEY_PART_COUNT, Hkey, HkeyError, LongHkeyExpanded, Store};
+
+/// A part of a concatenation: its length, its key, and the depth of a node holding it.
+pub type ConcatPart = (usize, Hkey, u32);
+
+impl Hkey {
+    /// Concatenates `hkeys` into a [`LongHkeyExpanded`] referencing them.
+    ///
+    /// Lists are flattened into their items and long keys are referenced as they are, so their
+    /// lengths are read from their index nodes, and no leaf data is uploaded again. A standalone
+    /// [`Direct`](Self::Direct) or [`Encrypted`](Self::Encrypted) leaf, however, is fetched and
+    /// decrypted to measure it unless [`Hkey::len_hint`] tells its length, which it does for
+    /// leaves of fewer than 256 bytes. Parts beyond what a single node holds are grouped into
+    /// stored intermediate nodes, so that slicing the result only walks the nodes along the
+    /// slice. Part ranges follow the lengths of the keys, so they need not be uniform.
+    pub fn concat<'a, C, E, S>(store: &'a S, hkeys: &[Self]) -> Result<LongHkeyExpanded, E>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        let mut parts = Vec::with_capacity(hkeys.len());
+
+        for hkey in hkeys {
+            hkey.concat_parts(store, &mut parts)?;
+        }
+
+        group_parts(store, parts)
+    }
+
+    /// Appends the parts `self` contributes to a concatenation.
+    fn concat_parts<'a, C, E, S>(&self, store: &'a S, parts: &mut Vec<ConcatPart>) -> Result<(), E>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        match self {
+            Self::List(list) => {
+                for item in list.iter() {
+                    item.concat_parts(store, parts)?;
+                }
+            }
+            Self::ListRef(hash, key) => {
+                let node = Self::resolve_encrypted(hash, key, store)?;
+
+                // Stored long keys share their textual form with list references.
+                match Self::parse(node.data_ref()).map_err(HkeyError::Construction)? {
+                    Self::LongHkeyExpanded(lhkey) => {
+                        push_part(parts, lhkey.size(), self.clone(), lhkey.depth() + 1);
+                    }
+                    list => list.concat_parts(store, parts)?,
+                }
+            }
+            Self::LongHkey(lhkey) => {
+                let expanded = lhkey.expand(store)?;
+
+                push_part(parts, expanded.size(), self.clone(), expanded.depth() + 1);
+            }
+            Self::LongHkeyExpanded(lhkey) => {
+                if lhkey.size() > 0 {
+                    let stored = lhkey.store(store)?;
+
+                    push_part(parts, lhkey.size(), stored.into(), lhkey.depth() + 1);
+                }
+            }
+            leaf => push_part(parts, leaf.len(store)?, leaf.clone(), 0),
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends a part, skipping empty ones.
+pub fn push_part(parts: &mut Vec<ConcatPart>, length: usize, hkey: Hkey, depth: u32) {
+    if length > 0 {
+        parts.push((length, hkey, depth));
+    }
+}
+
+/// Builds the node holding `parts` back to back, grouping them into stored intermediate nodes
+/// of up to [`LHKEY_PART_COUNT`] parts, level by level, until the root holds at most that many.
+pub fn group_parts<'a, C, E, S>(
+    store: &S,
+    mut parts: Vec<ConcatPart>,
+) -> Result<LongHkeyExpanded, E>
+where
+    C: DataChunk,
+    E: From<HkeyError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+{
+    while parts.len() > LHKEY_PART_COUNT {
+        parts = parts
+            .chunks(LHKEY_PART_COUNT)
+            .map(|group| {
+                let node = concat_node(group);
+
+                Ok((node.size(), node.store(store)?.into(), node.depth() + 1))
+            })
+            .collect::<Result<_, E>>()?;
+    }
+
+    Ok(concat_node(&parts))
+}
+
+/// Builds the node holding `parts` back to back.
+pub fn concat_node(parts: &[ConcatPart]) -> LongHkeyExpanded {
+    let mut position = 0;
+    let mut depth = 0;
+
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|(length, hkey, part_depth)| {
+            let range = position..position + length;
+
+            position += length;
+            depth = depth.max(*part_depth);
+
+            (range, hkey.clone())
+        })
+        .collect();
+
+    LongHkeyExpanded::new(depth, position, Arc::from(parts))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use crate::{Hkey, InMemoryStore, LongHkeyExpanded, Store, StoreIter};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn concat_matches_concatenated_data() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(300_000);
+        let lens = [0, 10, 100, 3000, 10_000, 300_000];
+
+        let mut hkeys: Vec<Hkey> = lens
+            .iter()
+            .map(|&len| store.put(&data[..len]).expect("Failed to put"))
+            .collect();
+        let mut expected: Vec<u8> = lens.iter().flat_map(|&len| &data[..len]).copied().collect();
+
+        let list = Hkey::List(hkeys.clone().into());
+        let list_ref = list.shrink(&store).expect("Failed to shrink");
+        let expanded = LongHkeyExpanded::from_blob(&store, &data[..70_000]).expect("Failed");
+
+        hkeys.extend([list, list_ref, Hkey::Empty, expanded.into()]);
+
+        let doubled = expected.clone();
+
+        expected.extend_from_slice(&doubled);
+        expected.extend_from_slice(&doubled);
+        expected.extend_from_slice(&data[..70_000]);
+
+        let before = store.hashes().expect("Failed to list").len();
+        let concatenated = Hkey::concat(&store, &hkeys).expect("Failed to concatenate");
+        let added = store.hashes().expect("Failed to list").len() - before;
+
+        // Only the stored expanded key and the intermediate nodes are new.
+        assert!(added <= 3, "concatenation stored {added} chunks");
+        assert_eq!(concatenated.size(), expected.len());
+        assert_eq!(
+            concatenated
+                .resolve(&store)
+                .expect("Failed to resolve")
+                .as_ref(),
+            expected.as_slice()
+        );
+
+        for range in [0..1, 5000..400_000, 600_000..expected.len()] {
+            assert_eq!(
+                concatenated
+                    .resolve_slice(&store, range.clone())
+                    .expect("Failed to slice")
+                    .as_ref(),
+                &expected[range]
+            );
+        }
+
+        let shrunk = concatenated.shrink(&store).expect("Failed to shrink");
+
+        assert_eq!(
+            shrunk.resolve(&store).expect("Failed to resolve").as_ref(),
+            expected.as_slice()
+        );
+    }
+
+    #[test]
+    fn concat_result_resizes() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(1_300_000);
+        let bounds = [0, 1_048_566, 1_048_600, 1_050_000, 1_300_000];
+
+        let hkeys: Vec<Hkey> = bounds
+            .windows(2)
+            .map(|pair| store.put(&data[pair[0]..pair[1]]).expect("Failed to put"))
+            .collect();
+        let concatenated = Hkey::concat(&store, &hkeys).expect("Failed to concatenate");
+
+        for new_len in [1_400_000, 1_100_000, 1_048_580, 1_048_566, 300_000, 10] {
+            let resized = concatenated
+                .resize(&store, new_len, 0)
+                .expect("Failed to resize");
+            let mut expected = data.clone();
+
+            expected.resize(new_len, 0);
+
+            assert_eq!(
+                resized.resolve(&store).expect("Failed to resolve").as_ref(),
+                expected.as_slice(),
+                "resized to {new_len}"
+            );
+        }
+    }
+
+    #[test]
+    fn concat_groups_many_parts() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(1000);
+
+        let hkeys: Vec<Hkey> = data
+            .chunks(3)
+            .map(|chunk| store.put(chunk).expect("Failed to put"))
+            .collect();
+
+        let concatenated = Hkey::concat(&store, &hkeys).expect("Failed to concatenate");
+
+        assert_eq!(concatenated.depth(), 2);
+        assert!(concatenated.parts().len() <= 16);
+        assert_eq!(
+            concatenated
+                .resolve(&store)
+                .expect("Failed to resolve")
+                .as_ref(),
+            data.as_slice()
+        );
+        assert_eq!(
+            Hkey::concat(&store, &[])
+                .expect("Failed to concatenate")
+                .size(),
+            0
+        );
+    }
+}
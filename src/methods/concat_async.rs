@@ -0,0 +1,167 @@
+use std::{future::Future, pin::Pin};
+
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+
+use crate::{long::LHKEY_PART_COUNT, AsyncStore, Hkey, HkeyError, LongHkeyExpanded};
+
+use super::concat::{concat_node, push_part, ConcatPart};
+
+impl Hkey {
+    /// Asynchronous counterpart of [`Hkey::concat`].
+    pub async fn concat_async<C, E, S>(store: S, hkeys: &[Self]) -> Result<LongHkeyExpanded, E>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let mut parts = Vec::with_capacity(hkeys.len());
+
+        for hkey in hkeys {
+            parts.extend(hkey.concat_parts_async(store.clone()).await?);
+        }
+
+        group_parts_async(store, parts).await
+    }
+
+    /// Returns the parts `self` contributes to a concatenation.
+    fn concat_parts_async<'a, C, E, S>(
+        &'a self,
+        store: S,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ConcatPart>, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move {
+            let mut parts = Vec::new();
+
+            match self {
+                Self::List(list) => {
+                    for item in list.iter() {
+                        parts.extend(item.concat_parts_async(store.clone()).await?);
+                    }
+                }
+                Self::ListRef(hash, key) => {
+                    let node = Self::resolve_encrypted_async(hash, key, store.clone()).await?;
+
+                    // Stored long keys share their textual form with list references.
+                    match Self::parse(node.data_ref()).map_err(HkeyError::Construction)? {
+                        Self::LongHkeyExpanded(lhkey) => {
+                            push_part(&mut parts, lhkey.size(), self.clone(), lhkey.depth() + 1);
+                        }
+                        list => parts.extend(list.concat_parts_async(store).await?),
+                    }
+                }
+                Self::LongHkey(lhkey) => {
+                    let expanded = lhkey.expand_async(store).await?;
+
+                    push_part(
+                        &mut parts,
+                        expanded.size(),
+                        self.clone(),
+                        expanded.depth() + 1,
+                    );
+                }
+                Self::LongHkeyExpanded(lhkey) => {
+                    if lhkey.size() > 0 {
+                        let stored = lhkey.store_async(store).await?;
+
+                        push_part(&mut parts, lhkey.size(), stored.into(), lhkey.depth() + 1);
+                    }
+                }
+                leaf => {
+                    let length = leaf.len_async(store).await?;
+
+                    push_part(&mut parts, length, leaf.clone(), 0);
+                }
+            }
+
+            Ok(parts)
+        })
+    }
+}
+
+/// Asynchronous counterpart of [`group_parts`](super::concat::group_parts).
+pub async fn group_parts_async<C, E, S>(
+    store: S,
+    mut parts: Vec<ConcatPart>,
+) -> Result<LongHkeyExpanded, E>
+where
+    C: DataChunk + Send,
+    E: From<HkeyError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+{
+    while parts.len() > LHKEY_PART_COUNT {
+        let mut grouped = Vec::with_capacity(parts.len().div_ceil(LHKEY_PART_COUNT));
+
+        for group in parts.chunks(LHKEY_PART_COUNT) {
+            let node = concat_node(group);
+            let stored = node.store_async(store.clone()).await?;
+
+            grouped.push((node.size(), stored.into(), node.depth() + 1));
+        }
+
+        parts = grouped;
+    }
+
+    Ok(concat_node(&parts))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncStore, Hkey, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn concat_async_matches_concatenated_data() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(300_000);
+
+        block_on(async {
+            let mut hkeys = Vec::new();
+            let mut expected = Vec::new();
+
+            for len in [10, 3000, 300_000, 0, 100].into_iter().cycle().take(40) {
+                let hkey = store
+                    .put(Bytes::copy_from_slice(&data[..len]))
+                    .await
+                    .expect("Failed to put");
+
+                hkeys.push(hkey);
+                expected.extend_from_slice(&data[..len]);
+            }
+
+            let list_ref = Hkey::List(hkeys[..5].to_vec().into())
+                .shrink_async(store.clone())
+                .await
+                .expect("Failed to shrink");
+
+            hkeys.push(list_ref);
+            expected.extend_from_within(..303_110);
+
+            let concatenated = Hkey::concat_async(store.clone(), &hkeys)
+                .await
+                .expect("Failed to concatenate");
+
+            assert_eq!(concatenated.depth(), 3);
+            assert_eq!(
+                concatenated
+                    .resolve_async(store.clone())
+                    .await
+                    .expect("Failed to resolve")
+                    .as_ref(),
+                expected.as_slice()
+            );
+        });
+    }
+}
@@ -1,6 +1,9 @@
 use ps_hash::Hash;
 
-use crate::{Hkey, HkeyFromCompactError, DOUBLE_HASH_SIZE_COMPACT, HASH_SIZE_COMPACT};
+use crate::{
+    Hkey, HkeyFromCompactError, LongHkey, DOUBLE_HASH_SIZE_COMPACT, HASH_SIZE_COMPACT,
+    LONG_HKEY_COMPACT_PREFIX, LONG_HKEY_SIZE_COMPACT,
+};
 
 impl Hkey {
     pub fn from_compact(bytes: &[u8]) -> Result<Self, HkeyFromCompactError> {
@@ -18,6 +21,17 @@ impl Hkey {
                 }
             }
 
+            LONG_HKEY_SIZE_COMPACT => {
+                if bytes[0] != LONG_HKEY_COMPACT_PREFIX {
+                    return Err(HkeyFromCompactError::LongHkeyPrefix(bytes[0]));
+                }
+
+                let hash = Hash::validate(&bytes[1..=HASH_SIZE_COMPACT])?;
+                let key = Hash::validate(&bytes[HASH_SIZE_COMPACT + 1..])?;
+
+                Ok(Self::LongHkey(LongHkey::from_hash_and_key(hash, key)))
+            }
+
             _ => Self::from_raw(bytes).map_err(Into::into),
         }
     }
@@ -0,0 +1,156 @@
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{Hkey, HkeyError, Store, HASH_SIZE};
+
+/// [`Hash::data_max_len`] rounds lengths of 256 bytes or more up to a representable value.
+const EXACT_LEN_LIMIT: usize = 256;
+
+/// Returns the length of the data `hash` was computed from, if the hash pins it exactly.
+fn exact_len(hash: &Hash) -> Option<usize> {
+    let len = hash.data_max_len().to_usize();
+
+    (len < EXACT_LEN_LIMIT).then_some(len)
+}
+
+impl Hkey {
+    /// Returns the number of bytes `self` references, without accessing a store.
+    ///
+    /// Returns `None` if the length cannot be determined exactly from `self` alone:
+    /// [`ListRef`](Self::ListRef) and [`LongHkey`](Self::LongHkey) do not carry it, and the
+    /// hashes of [`Direct`](Self::Direct) and [`Encrypted`](Self::Encrypted) only pin lengths
+    /// of fewer than 256 bytes, including the hash prefixed to encrypted chunks.
+    #[must_use]
+    pub fn len_hint(&self) -> Option<usize> {
+        match self {
+            Self::Empty => Some(0),
+            Self::Raw(raw) => Some(raw.len()),
+            Self::Base64(base64) => Some(ps_base64::decode(base64.as_bytes()).len()),
+            Self::Direct(hash) => exact_len(hash),
+            // The key is the hash of the serialized chunk, which prefixes the data with its hash.
+            Self::Encrypted(_, key) => exact_len(key)?.checked_sub(HASH_SIZE),
+            Self::ListRef(_, _) | Self::LongHkey(_) => None,
+            Self::List(list) => list.iter().map(Self::len_hint).sum(),
+            Self::LongHkeyExpanded(lhkey) => Some(lhkey.size()),
+        }
+    }
+
+    /// Returns the number of bytes `self` references.
+    ///
+    /// Only the index nodes of [`ListRef`](Self::ListRef) and [`LongHkey`](Self::LongHkey) are
+    /// fetched. A [`Direct`](Self::Direct) or [`Encrypted`](Self::Encrypted) leaf outside of a
+    /// [`LongHkey`](Self::LongHkey) is fetched only if [`Hkey::len_hint`] cannot tell its length.
+    pub fn len<'a, C, E, S>(&self, store: &'a S) -> Result<usize, E>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        if let Some(len) = self.len_hint() {
+            return Ok(len);
+        }
+
+        let len = match self {
+            Self::ListRef(hash, key) => {
+                let list_bytes = Self::resolve_encrypted(hash, key, store)?;
+
+                Self::parse(list_bytes.data_ref())
+                    .map_err(HkeyError::Construction)?
+                    .len(store)?
+            }
+            Self::LongHkey(lhkey) => lhkey.expand(store)?.size(),
+            Self::List(list) => {
+                let lengths: Result<Vec<usize>, E> =
+                    list.into_par_iter().map(|hkey| hkey.len(store)).collect();
+
+                lengths?.into_iter().sum()
+            }
+            leaf => leaf.resolve(store)?.len(),
+        };
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Hkey, InMemoryStore, Store};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn hint_is_exact_for_local_variants() {
+        let store = InMemoryStore::default();
+
+        assert_eq!(Hkey::Empty.len_hint(), Some(0));
+        assert_eq!(
+            Hkey::from_raw(b"data")
+                .expect("Failed to allocate")
+                .len_hint(),
+            Some(4)
+        );
+        assert_eq!(
+            Hkey::from_base64_slice("SGVsbG8gd29ybGQh")
+                .expect("Failed to allocate")
+                .len_hint(),
+            Some(12)
+        );
+
+        let short = store.put(&sequential_bytes(100)).expect("Failed to put");
+
+        assert!(matches!(short, Hkey::Encrypted(_, _)));
+        assert_eq!(short.len_hint(), Some(100));
+
+        let long = store.put(&sequential_bytes(1000)).expect("Failed to put");
+
+        assert_eq!(long.len_hint(), None);
+    }
+
+    #[test]
+    fn hint_sums_lists() {
+        let store = InMemoryStore::default();
+        let known = store.put(&sequential_bytes(100)).expect("Failed to put");
+        let unknown = store.put(&sequential_bytes(1000)).expect("Failed to put");
+
+        let list = Hkey::List(Arc::from([known.clone(), Hkey::Empty, known.clone()]));
+
+        assert_eq!(list.len_hint(), Some(200));
+
+        let list = Hkey::List(Arc::from([known, unknown]));
+
+        assert_eq!(list.len_hint(), None);
+    }
+
+    #[test]
+    fn len_matches_resolve() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(300_000);
+
+        let items: Arc<[Hkey]> = Arc::from(vec![
+            store.put(&data[..10]).expect("Failed to put"),
+            store.put(&data[..1000]).expect("Failed to put"),
+            store.put(&data).expect("Failed to put"),
+        ]);
+        let list = Hkey::List(items);
+        let list_ref = list.shrink(&store).expect("Failed to shrink");
+
+        for len in [0, 10, 255, 256, 4000, 70_000, 300_000] {
+            let hkey = store.put(&data[..len]).expect("Failed to put");
+
+            assert_eq!(hkey.len(&store).expect("Failed to measure"), len);
+        }
+
+        for hkey in [list, list_ref] {
+            let expected = hkey.resolve(&store).expect("Failed to resolve").len();
+
+            assert_eq!(hkey.len(&store).expect("Failed to measure"), expected);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{future::Future, pin::Pin};
+
+use futures::future::try_join_all;
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+
+use crate::{AsyncStore, Hkey, HkeyError};
+
+impl Hkey {
+    pub fn len_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+    ) -> Pin<Box<dyn Future<Output = Result<usize, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.len_async(store).await })
+    }
+
+    /// Asynchronous counterpart of [`Hkey::len`].
+    pub async fn len_async<C, E, S>(&self, store: S) -> Result<usize, E>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        if let Some(len) = self.len_hint() {
+            return Ok(len);
+        }
+
+        let len = match self {
+            Self::ListRef(hash, key) => {
+                let list_bytes = Self::resolve_encrypted_async(hash, key, store.clone()).await?;
+
+                Self::parse(list_bytes.data_ref())
+                    .map_err(HkeyError::Construction)?
+                    .len_async_box(store)
+                    .await?
+            }
+            Self::LongHkey(lhkey) => lhkey.expand_async(store).await?.size(),
+            Self::List(list) => {
+                let futures = list.iter().map(|hkey| hkey.len_async_box(store.clone()));
+
+                try_join_all(futures).await?.into_iter().sum()
+            }
+            leaf => leaf.resolve_async(store).await?.len(),
+        };
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncStore, Hkey, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn len_async_matches_resolve_async() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(300_000);
+
+        block_on(async {
+            let mut items = Vec::new();
+
+            for len in [0, 10, 255, 256, 4000, 70_000, 300_000] {
+                let hkey = store
+                    .put(Bytes::copy_from_slice(&data[..len]))
+                    .await
+                    .expect("Failed to put");
+
+                assert_eq!(
+                    hkey.len_async(store.clone())
+                        .await
+                        .expect("Failed to measure"),
+                    len
+                );
+
+                items.push(hkey);
+            }
+
+            let list = Hkey::List(Arc::from(items));
+            let list_ref = list
+                .shrink_async(store.clone())
+                .await
+                .expect("Failed to shrink");
+
+            for hkey in [list, list_ref] {
+                let expected = hkey
+                    .resolve_async(store.clone())
+                    .await
+                    .expect("Failed to resolve")
+                    .len();
+
+                assert_eq!(
+                    hkey.len_async(store.clone())
+                        .await
+                        .expect("Failed to measure"),
+                    expected
+                );
+            }
+        });
+    }
+}
@@ -1,6 +1,24 @@
+mod binary;
 mod compact;
 mod compact_async;
+mod concat;
+mod concat_async;
 mod from_compact;
 mod is_empty;
+mod len;
+mod len_async;
 mod parse;
+mod parse_strict;
+mod split_top_level;
+mod transfer;
+mod transfer_async;
 mod try_parse;
+mod visit_chunks;
+mod visit_chunks_async;
+mod write_at;
+mod write_at_async;
+
+pub(crate) use concat::{group_parts, ConcatPart};
+pub(crate) use concat_async::group_parts_async;
+pub use split_top_level::split_top_level;
+pub use transfer::{TransferError, TransferReport};
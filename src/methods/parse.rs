@@ -21,7 +21,7 @@ mod tests {
     use ps_base64::base64;
     use ps_hash::{hash, Hash};
 
-    use crate::MAX_SIZE_RAW;
+    use crate::{LongHkeyExpanded, MAX_SIZE_RAW};
 
     use super::*;
 
@@ -513,4 +513,135 @@ mod tests {
             }
         }
     }
+
+    // ------------------------
+    // Nesting: lists and expanded long hkeys within each other
+    // ------------------------
+
+    #[test]
+    fn nested_lists_roundtrip() {
+        let inner = Hkey::List(
+            vec![
+                Hkey::Direct(mk_hash(b"inner-a")),
+                Hkey::Base64(arrstr("YWJj")),
+            ]
+            .into(),
+        );
+        let expanded = LongHkeyExpanded::new(
+            1,
+            7,
+            vec![(0..3, Hkey::Base64(arrstr("YWJj"))), (3..7, inner.clone())].into(),
+        );
+        let hkey = Hkey::List(
+            vec![
+                inner,
+                Hkey::List(Vec::new().into()),
+                Hkey::LongHkeyExpanded(expanded),
+                Hkey::Encrypted(mk_hash(b"h"), mk_hash(b"k")),
+            ]
+            .into(),
+        );
+
+        assert_eq!(
+            Hkey::parse(hkey.to_string()).expect("Failed to parse Hkey"),
+            hkey
+        );
+    }
+
+    #[test]
+    fn unterminated_nested_lists_do_not_panic() {
+        assert!(Hkey::try_parse("[").is_err());
+
+        for input in [
+            "[",
+            "[[]",
+            "[[],[]",
+            "{0;4;0-3:[}",
+            "{0;4;0-3:[[}",
+            "[{0;4;0-3:[}]",
+        ] {
+            // Items are parsed leniently, so these may still parse as data.
+            let _ = Hkey::parse(input);
+            let _ = Hkey::try_parse(input);
+
+            assert!(Hkey::parse_strict(input).is_err(), "{input} must not parse");
+        }
+    }
+
+    /// A xorshift generator, so that failures are reproducible from the seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    /// Generates a random tree of canonical hkeys.
+    ///
+    /// Leaves are never [`Hkey::Empty`], since a list of one empty item is formatted as the
+    /// empty list, nor [`Hkey::Raw`] or [`Hkey::LongHkey`], which are formatted as their
+    /// [`Hkey::Base64`] and [`Hkey::ListRef`] aliases.
+    #[allow(clippy::cast_possible_truncation)]
+    fn random_hkey(rng: &mut Rng, depth: usize) -> Hkey {
+        let kinds = if depth == 0 { 4 } else { 6 };
+        let seed = rng.next().to_le_bytes();
+
+        match rng.below(kinds) {
+            0 => {
+                let len = 1 + rng.below(MAX_SIZE_RAW);
+                let data: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+
+                Hkey::Base64(arrstr(&ps_base64::encode(&data)))
+            }
+            1 => Hkey::Direct(mk_hash(seed)),
+            2 => Hkey::Encrypted(mk_hash(seed), mk_hash(b"key")),
+            3 => Hkey::ListRef(mk_hash(seed), mk_hash(b"list-key")),
+            4 => {
+                let len = rng.below(4);
+                let items: Vec<Hkey> = (0..len).map(|_| random_hkey(rng, depth - 1)).collect();
+
+                Hkey::List(items.into())
+            }
+            _ => {
+                let mut offset = 0;
+                let parts: Vec<_> = (0..=rng.below(3))
+                    .map(|_| {
+                        let len = 1 + rng.below(1000);
+                        let range = offset..offset + len;
+
+                        offset += len;
+
+                        (range, random_hkey(rng, depth - 1))
+                    })
+                    .collect();
+
+                Hkey::LongHkeyExpanded(LongHkeyExpanded::new(depth as u32, offset, parts.into()))
+            }
+        }
+    }
+
+    #[test]
+    fn random_trees_roundtrip() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..500 {
+            let hkey = random_hkey(&mut rng, 4);
+            let string = hkey.to_string();
+
+            assert_eq!(
+                Hkey::parse(&string).expect("Failed to parse Hkey"),
+                hkey,
+                "{string} must parse back to the same Hkey"
+            );
+        }
+    }
 }
@@ -0,0 +1,543 @@
+use ps_hash::Hash;
+
+use crate::{
+    Hkey, HkeyParseError, LongHkeyExpanded, BUF_SIZE_BASE64, DOUBLE_HASH_SIZE_PREFIXED, HASH_SIZE,
+    HASH_SIZE_PREFIXED,
+};
+
+type ParseResult<T> = Result<T, HkeyParseError>;
+
+impl Hkey {
+    /// Parses the canonical string form of an [`Hkey`], as produced by its `Display`
+    /// implementation.
+    ///
+    /// Unlike [`Hkey::parse`], this never falls back to [`Hkey::Base64`] or [`Hkey::Raw`]:
+    /// any input which is not exactly the canonical form of some [`Hkey`] is rejected.
+    /// [`Hkey::Raw`] is never returned, since it is formatted as [`Hkey::Base64`]. Nor is a list
+    /// holding a single [`Hkey::Empty`], since it is formatted like the empty list.
+    ///
+    /// # Errors
+    /// - [`HkeyParseError`] describing the first offending byte.
+    pub fn parse_strict(value: impl AsRef<[u8]>) -> ParseResult<Self> {
+        let input = value.as_ref();
+
+        if input.is_empty() {
+            return Ok(Self::Empty);
+        }
+
+        let mut parser = StrictParser { input, position: 0 };
+        let hkey = parser.hkey()?;
+
+        if parser.position < input.len() {
+            return Err(HkeyParseError::TrailingInput {
+                position: parser.position,
+            });
+        }
+
+        Ok(hkey)
+    }
+}
+
+struct StrictParser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+const fn is_base64_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+}
+
+const fn is_delimiter(byte: u8) -> bool {
+    matches!(byte, b',' | b']' | b'}')
+}
+
+impl StrictParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn hkey(&mut self) -> ParseResult<Hkey> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b'{') => self.long(),
+            _ => self.leaf(),
+        }
+    }
+
+    fn list(&mut self) -> ParseResult<Hkey> {
+        self.position += 1;
+
+        let mut items = Vec::new();
+
+        if self.peek() == Some(b']') {
+            self.position += 1;
+
+            return Ok(Hkey::List(items.into()));
+        }
+
+        loop {
+            match self.peek() {
+                None | Some(b'}') => return Err(self.list_separator()),
+                // An empty item is the string form of `Hkey::Empty`.
+                Some(b',' | b']') => items.push(Hkey::Empty),
+                Some(_) => items.push(self.hkey()?),
+            }
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => break,
+                _ => return Err(self.list_separator()),
+            }
+        }
+
+        self.position += 1;
+
+        Ok(Hkey::List(items.into()))
+    }
+
+    fn list_separator(&self) -> HkeyParseError {
+        HkeyParseError::ListSeparator {
+            position: self.position,
+            found: self.peek().map(char::from),
+        }
+    }
+
+    fn long(&mut self) -> ParseResult<Hkey> {
+        self.position += 1;
+
+        let depth = self.number("invalid depth")?;
+
+        self.expect(b';', "expected ';' after the depth")?;
+
+        let size = self.number("invalid size")?;
+
+        self.expect(b';', "expected ';' after the size")?;
+
+        let mut parts = Vec::new();
+
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+
+            return Ok(LongHkeyExpanded::new(depth, size, parts.into()).into());
+        }
+
+        loop {
+            let start_position = self.position;
+            let start: usize = self.number("invalid range start")?;
+
+            self.expect(b'-', "expected '-' after the range start")?;
+
+            let end: usize = self.number("invalid range end")?;
+
+            self.expect(b':', "expected ':' after the range end")?;
+
+            if end < start {
+                return Err(self.malformed_at(start_position, "range start exceeds its end"));
+            }
+
+            let end = end
+                .checked_add(1)
+                .ok_or_else(|| self.malformed_at(start_position, "range end is too large"))?;
+
+            match self.peek() {
+                Some(byte) if !is_delimiter(byte) => parts.push((start..end, self.hkey()?)),
+                _ => return Err(self.malformed("expected an hkey after ':'")),
+            }
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => break,
+                _ => return Err(self.malformed("expected ',' or '}' after a part")),
+            }
+        }
+
+        self.position += 1;
+
+        Ok(LongHkeyExpanded::new(depth, size, parts.into()).into())
+    }
+
+    fn malformed(&self, reason: &'static str) -> HkeyParseError {
+        self.malformed_at(self.position, reason)
+    }
+
+    const fn malformed_at(&self, position: usize, reason: &'static str) -> HkeyParseError {
+        HkeyParseError::MalformedLongHkey { position, reason }
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> ParseResult<()> {
+        if self.peek() != Some(byte) {
+            return Err(self.malformed(reason));
+        }
+
+        self.position += 1;
+
+        Ok(())
+    }
+
+    /// Reads a decimal number without leading zeros.
+    fn number<T: std::str::FromStr>(&mut self, reason: &'static str) -> ParseResult<T> {
+        let start = self.position;
+        let digits = self.input[start..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+
+        if digits == 0 || (digits > 1 && self.input[start] == b'0') {
+            return Err(self.malformed(reason));
+        }
+
+        self.position += digits;
+
+        std::str::from_utf8(&self.input[start..self.position])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| self.malformed_at(start, reason))
+    }
+
+    fn leaf(&mut self) -> ParseResult<Hkey> {
+        let start = self.position;
+        let length = self.input[start..]
+            .iter()
+            .take_while(|byte| !is_delimiter(**byte))
+            .count();
+
+        self.position += length;
+
+        let leaf = &self.input[start..self.position];
+
+        // Only reachable with a delimiter at the start of the input.
+        let Some(&first) = leaf.first() else {
+            return Err(HkeyParseError::InvalidBase64Character {
+                position: start,
+                byte: self.peek().map_or(char::REPLACEMENT_CHARACTER, char::from),
+            });
+        };
+
+        match (first, length) {
+            (_, HASH_SIZE) => Ok(Hkey::Direct(parse_hash(start, leaf)?)),
+            (b'E' | b'L', DOUBLE_HASH_SIZE_PREFIXED) => {
+                let hash = parse_hash(start + 1, &leaf[1..=HASH_SIZE])?;
+                let key = parse_hash(start + HASH_SIZE_PREFIXED, &leaf[HASH_SIZE_PREFIXED..])?;
+
+                if first == b'E' {
+                    Ok(Hkey::Encrypted(hash, key))
+                } else {
+                    Ok(Hkey::ListRef(hash, key))
+                }
+            }
+            (byte, HASH_SIZE_PREFIXED | DOUBLE_HASH_SIZE_PREFIXED) => {
+                Err(HkeyParseError::UnexpectedPrefix {
+                    position: start,
+                    byte: char::from(byte),
+                })
+            }
+            (_, ..=BUF_SIZE_BASE64) => parse_base64(start, leaf),
+            (b'E' | b'L', _) => Err(HkeyParseError::HashLength {
+                position: start + 1,
+                expected: 2 * HASH_SIZE,
+                found: length - 1,
+            }),
+            (_, _) => Err(HkeyParseError::HashLength {
+                position: start,
+                expected: HASH_SIZE,
+                found: length,
+            }),
+        }
+    }
+}
+
+fn parse_hash(position: usize, bytes: &[u8]) -> ParseResult<Hash> {
+    if let Some(offset) = bytes.iter().position(|byte| !is_base64_byte(*byte)) {
+        return Err(HkeyParseError::InvalidHashCharacter {
+            position: position + offset,
+            byte: char::from(bytes[offset]),
+        });
+    }
+
+    let hash = Hash::validate(bytes).map_err(|_| HkeyParseError::InvalidHash { position })?;
+    let canonical = hash.to_string();
+
+    // Validation corrects a few wrong characters, which a strict parser must not accept.
+    if let Some(offset) = canonical
+        .bytes()
+        .zip(bytes)
+        .position(|(expected, actual)| expected != *actual)
+    {
+        return Err(HkeyParseError::NonCanonicalHash {
+            position: position + offset,
+        });
+    }
+
+    Ok(hash)
+}
+
+fn parse_base64(position: usize, bytes: &[u8]) -> ParseResult<Hkey> {
+    if let Some(offset) = bytes.iter().position(|byte| !is_base64_byte(*byte)) {
+        return Err(HkeyParseError::InvalidBase64Character {
+            position: position + offset,
+            byte: char::from(bytes[offset]),
+        });
+    }
+
+    // A single character in the last group, or unused bits which are set, cannot have been
+    // produced by the encoder.
+    if ps_base64::encode(&ps_base64::decode(bytes)).as_bytes() != bytes {
+        return Err(HkeyParseError::NonCanonicalBase64 {
+            position: position + bytes.len() - 1,
+        });
+    }
+
+    let base64 = std::str::from_utf8(bytes).map_err(|_| HkeyParseError::NonCanonicalBase64 {
+        position: position + bytes.len() - 1,
+    })?;
+
+    Hkey::from_base64_slice(base64).map_err(|_| HkeyParseError::HashLength {
+        position,
+        expected: HASH_SIZE,
+        found: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_hash::hash;
+
+    use crate::{Hkey, HkeyParseError, LongHkeyExpanded, HASH_SIZE};
+
+    fn mk_hash(data: &[u8]) -> ps_hash::Hash {
+        hash(data).expect("Failed to hash")
+    }
+
+    fn nested() -> Hkey {
+        let expanded = LongHkeyExpanded::new(
+            1,
+            12,
+            vec![
+                (
+                    0..4,
+                    Hkey::from_base64_slice("YWJj").expect("Failed to allocate"),
+                ),
+                (
+                    4..12,
+                    Hkey::List(
+                        vec![
+                            Hkey::Direct(mk_hash(b"a")),
+                            Hkey::from_base64_slice("ZA").expect("Failed to allocate"),
+                        ]
+                        .into(),
+                    ),
+                ),
+            ]
+            .into(),
+        );
+
+        Hkey::List(
+            vec![
+                Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key")),
+                Hkey::LongHkeyExpanded(expanded),
+                Hkey::List(Vec::new().into()),
+                Hkey::ListRef(mk_hash(b"list-hash"), mk_hash(b"list-key")),
+            ]
+            .into(),
+        )
+    }
+
+    #[test]
+    fn accepts_canonical_forms() {
+        let abc = Hkey::from_base64_slice("YWJj").expect("Failed to allocate");
+        let list = |items: Vec<Hkey>| Hkey::List(items.into());
+        let hkeys = [
+            Hkey::Empty,
+            Hkey::from_base64_slice("SGVsbG8").expect("Failed to allocate"),
+            Hkey::Direct(mk_hash(b"direct")),
+            Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key")),
+            Hkey::ListRef(mk_hash(b"list-hash"), mk_hash(b"list-key")),
+            Hkey::LongHkeyExpanded(LongHkeyExpanded::default()),
+            nested(),
+            list(vec![abc.clone(), Hkey::Empty]),
+            list(vec![Hkey::Empty, abc.clone()]),
+            list(vec![Hkey::Empty, Hkey::Empty]),
+            list(vec![
+                Hkey::ListRef(mk_hash(b"list-hash"), mk_hash(b"list-key")),
+                abc,
+                Hkey::Empty,
+            ]),
+        ];
+
+        for hkey in hkeys {
+            let parsed = Hkey::parse_strict(hkey.to_string()).expect("Failed to parse");
+
+            assert_eq!(parsed, hkey);
+        }
+
+        let raw = Hkey::from_raw(&[0, 1, 2, 255]).expect("Failed to allocate");
+
+        assert!(matches!(
+            Hkey::parse_strict(raw.to_string()),
+            Ok(Hkey::Base64(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_mistyped_hashes() {
+        let direct = Hkey::Direct(mk_hash(b"direct")).to_string();
+        let mut typo = direct.into_bytes();
+
+        typo[10] = if typo[10] == b'A' { b'B' } else { b'A' };
+
+        // The lenient parser corrects the typo.
+        assert!(matches!(Hkey::parse(&typo), Ok(Hkey::Direct(_))));
+
+        assert_eq!(
+            Hkey::parse_strict(&typo),
+            Err(HkeyParseError::NonCanonicalHash { position: 10 })
+        );
+
+        typo[20] = b'!';
+
+        assert_eq!(
+            Hkey::parse_strict(&typo),
+            Err(HkeyParseError::InvalidHashCharacter {
+                position: 20,
+                byte: '!'
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict(&typo[..60]),
+            Err(HkeyParseError::HashLength {
+                position: 0,
+                expected: HASH_SIZE,
+                found: 60
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_prefixes() {
+        let hash = mk_hash(b"hash");
+
+        assert_eq!(
+            Hkey::parse_strict(format!("D{hash}")),
+            Err(HkeyParseError::UnexpectedPrefix {
+                position: 0,
+                byte: 'D'
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict(format!("X{hash}{hash}")),
+            Err(HkeyParseError::UnexpectedPrefix {
+                position: 0,
+                byte: 'X'
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict(format!("E{hash}{}", &hash.to_string()[1..])),
+            Err(HkeyParseError::HashLength {
+                position: 1,
+                expected: 2 * HASH_SIZE,
+                found: 2 * HASH_SIZE - 1
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_base64() {
+        assert_eq!(
+            Hkey::parse_strict("SGVs bG8"),
+            Err(HkeyParseError::InvalidBase64Character {
+                position: 4,
+                byte: ' '
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict("SGVsbG8="),
+            Err(HkeyParseError::InvalidBase64Character {
+                position: 7,
+                byte: '='
+            })
+        );
+
+        // "SGVsbG9" sets bits which "SGVsbG8" leaves unused.
+        assert_eq!(
+            Hkey::parse_strict("SGVsbG9"),
+            Err(HkeyParseError::NonCanonicalBase64 { position: 6 })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict("SGVsb"),
+            Err(HkeyParseError::NonCanonicalBase64 { position: 4 })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lists() {
+        let direct = Hkey::Direct(mk_hash(b"direct")).to_string();
+
+        assert_eq!(
+            Hkey::parse_strict(format!("[{direct},}}")),
+            Err(HkeyParseError::ListSeparator {
+                position: 66,
+                found: Some('}')
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict(format!("[{direct}")),
+            Err(HkeyParseError::ListSeparator {
+                position: 65,
+                found: None
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict("[[]x]"),
+            Err(HkeyParseError::ListSeparator {
+                position: 3,
+                found: Some('x')
+            })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict("[]]"),
+            Err(HkeyParseError::TrailingInput { position: 2 })
+        );
+
+        assert_eq!(
+            Hkey::parse_strict(","),
+            Err(HkeyParseError::InvalidBase64Character {
+                position: 0,
+                byte: ','
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_long_hkeys() {
+        let cases = [
+            ("{0;4}", 4),
+            ("{01;4;}", 1),
+            ("{0;4;0-3}", 8),
+            ("{0;4;3-0:YWJj}", 5),
+            ("{0;4;0-3:}", 9),
+            ("{0;4;0-3:YWJj]", 13),
+            ("{0;4;0-3:YWJj", 13),
+            ("{99999999999;4;}", 1),
+        ];
+
+        for (input, expected) in cases {
+            assert!(
+                matches!(
+                    Hkey::parse_strict(input),
+                    Err(HkeyParseError::MalformedLongHkey { position, .. }) if position == expected
+                ),
+                "{input} should be rejected at byte {expected}, got {:?}",
+                Hkey::parse_strict(input)
+            );
+        }
+    }
+}
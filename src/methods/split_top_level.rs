@@ -0,0 +1,47 @@
+/// Splits `bytes` on every `separator` which is not nested within `[]` or `{}`.
+///
+/// Hashes and base64 never contain brackets or braces, so this respects the nesting of lists
+/// and expanded long hkeys within the textual format.
+pub fn split_top_level(bytes: &[u8], separator: u8) -> Vec<&[u8]> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (index, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ if byte == separator && depth == 0 => {
+                parts.push(&bytes[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&bytes[start..]);
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_top_level;
+
+    #[test]
+    fn respects_nesting() {
+        let parts = split_top_level(b"a,[b,c],{0;2;0-0:d,1-1:[e,f]},g", b',');
+
+        assert_eq!(
+            parts,
+            [
+                &b"a"[..],
+                &b"[b,c]"[..],
+                &b"{0;2;0-0:d,1-1:[e,f]}"[..],
+                &b"g"[..]
+            ]
+        );
+
+        assert_eq!(split_top_level(b"", b','), [&b""[..]]);
+    }
+}
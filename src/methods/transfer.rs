@@ -0,0 +1,304 @@
+use std::collections::HashSet;
+
+use parking_lot::Mutex;
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{Hkey, HkeyError, Store, StoreContains};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransferError<S, D> {
+    #[error("Source store: {0}")]
+    Source(S),
+    #[error("Destination store: {0}")]
+    Destination(D),
+}
+
+/// What [`Hkey::transfer`] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferReport {
+    chunks_copied: usize,
+    bytes_copied: usize,
+    chunks_skipped: usize,
+}
+
+impl TransferReport {
+    /// Returns the number of chunks copied into the destination.
+    #[must_use]
+    pub const fn chunks_copied(&self) -> usize {
+        self.chunks_copied
+    }
+
+    /// Returns the total length of the chunks copied, as stored.
+    #[must_use]
+    pub const fn bytes_copied(&self) -> usize {
+        self.bytes_copied
+    }
+
+    /// Returns the number of chunks the destination already held.
+    #[must_use]
+    pub const fn chunks_skipped(&self) -> usize {
+        self.chunks_skipped
+    }
+
+    pub(super) const fn copied(bytes: usize) -> Self {
+        Self {
+            chunks_copied: 1,
+            bytes_copied: bytes,
+            chunks_skipped: 0,
+        }
+    }
+
+    pub(super) const fn skipped() -> Self {
+        Self {
+            chunks_copied: 0,
+            bytes_copied: 0,
+            chunks_skipped: 1,
+        }
+    }
+
+    pub(super) const fn merge(self, other: Self) -> Self {
+        Self {
+            chunks_copied: self.chunks_copied + other.chunks_copied,
+            bytes_copied: self.bytes_copied + other.bytes_copied,
+            chunks_skipped: self.chunks_skipped + other.chunks_skipped,
+        }
+    }
+}
+
+impl Hkey {
+    /// Copies every chunk `self` depends on from `src` into `dst`, as stored.
+    ///
+    /// Index chunks are decrypted to find their children, but data chunks are copied without
+    /// being decrypted. Chunks `dst` already holds are skipped, and since children are copied
+    /// before their parents, so is the subtree below an index chunk `dst` already holds, which
+    /// keeps transfers of a new version of a long key proportional to what changed. Siblings
+    /// are copied in parallel.
+    pub fn transfer<'a, 'b, C, E, S, F, D>(
+        &self,
+        src: &'a S,
+        dst: &'b D,
+    ) -> Result<TransferReport, TransferError<E, F>>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+        F: Send,
+        D: StoreContains<Error = F> + Sync + 'b,
+    {
+        self.transfer_with(src, dst, &Mutex::new(HashSet::new()))
+    }
+
+    /// Transfers `self`, skipping the chunks in `visited`, and adding the rest to it.
+    fn transfer_with<'a, 'b, C, E, S, F, D>(
+        &self,
+        src: &'a S,
+        dst: &'b D,
+        visited: &Mutex<HashSet<Hash>>,
+    ) -> Result<TransferReport, TransferError<E, F>>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+        F: Send,
+        D: StoreContains<Error = F> + Sync + 'b,
+    {
+        match self {
+            Self::Empty | Self::Raw(_) | Self::Base64(_) => Ok(TransferReport::default()),
+            Self::Direct(hash) | Self::Encrypted(hash, _) => {
+                transfer_chunk(src, dst, visited, hash, None)
+            }
+            Self::ListRef(hash, key) => transfer_chunk(src, dst, visited, hash, Some(key)),
+            Self::LongHkey(lhkey) => {
+                transfer_chunk(src, dst, visited, lhkey.hash_ref(), Some(lhkey.key_ref()))
+            }
+            Self::List(list) => list
+                .par_iter()
+                .map(|hkey| hkey.transfer_with(src, dst, visited))
+                .try_reduce(TransferReport::default, |a, b| Ok(a.merge(b))),
+            Self::LongHkeyExpanded(lhkey) => lhkey
+                .parts()
+                .par_iter()
+                .map(|(_, hkey)| hkey.transfer_with(src, dst, visited))
+                .try_reduce(TransferReport::default, |a, b| Ok(a.merge(b))),
+        }
+    }
+}
+
+/// Decrypts and parses an index chunk.
+pub(super) fn parse_node<C: DataChunk, E: From<DataChunkError> + From<HkeyError>>(
+    chunk: &C,
+    key: &Hash,
+) -> Result<Hkey, E> {
+    let node = chunk.decrypt(key)?;
+
+    Ok(Hkey::parse(node.data_ref()).map_err(HkeyError::Construction)?)
+}
+
+/// Copies the chunk `hash` unless `dst` holds it. If it is an index chunk, decrypting with
+/// `key`, what it references is transferred first.
+fn transfer_chunk<'a, 'b, C, E, S, F, D>(
+    src: &'a S,
+    dst: &'b D,
+    visited: &Mutex<HashSet<Hash>>,
+    hash: &Hash,
+    key: Option<&Hash>,
+) -> Result<TransferReport, TransferError<E, F>>
+where
+    C: DataChunk,
+    E: From<DataChunkError> + From<HkeyError> + Send,
+    S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    F: Send,
+    D: StoreContains<Error = F> + Sync + 'b,
+{
+    if !visited.lock().insert(*hash) {
+        return Ok(TransferReport::default());
+    }
+
+    if dst.contains(hash).map_err(TransferError::Destination)? {
+        return Ok(TransferReport::skipped());
+    }
+
+    let chunk = src.get(hash).map_err(TransferError::Source)?;
+
+    let report = match key {
+        Some(key) => parse_node::<C, E>(&chunk, key)
+            .map_err(TransferError::Source)?
+            .transfer_with(src, dst, visited)?,
+        None => TransferReport::default(),
+    };
+
+    let bytes = chunk.data_ref().len();
+
+    dst.put_encrypted(chunk)
+        .map_err(TransferError::Destination)?;
+
+    Ok(report.merge(TransferReport::copied(bytes)))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{
+        AsyncStore, AsyncStoreIter, InMemoryAsyncStore, InMemoryStore, Store, StoreIter,
+        TransferReport,
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Returns the total length of the chunks `store` holds.
+    fn stored_bytes(store: &InMemoryStore) -> usize {
+        store
+            .hashes()
+            .expect("Failed to list")
+            .iter()
+            .map(|hash| store.get(hash).expect("Failed to get").data_ref().len())
+            .sum()
+    }
+
+    #[test]
+    fn copies_everything_once() {
+        let data = sequential_bytes(300_000);
+        let src = InMemoryStore::default();
+        let dst = InMemoryStore::default();
+        let hkey = src.put(&data).expect("Failed to put");
+
+        let report = hkey.transfer(&src, &dst).expect("Failed to transfer");
+
+        assert_eq!(hkey.resolve(&dst).expect("Failed to resolve"), data);
+        assert_eq!(
+            report.chunks_copied(),
+            dst.hashes().expect("Failed to list").len()
+        );
+        assert_eq!(report.bytes_copied(), stored_bytes(&dst));
+        assert_eq!(report.chunks_skipped(), 0);
+
+        let again = hkey.transfer(&src, &dst).expect("Failed to transfer");
+
+        assert_eq!(again.chunks_copied(), 0);
+        assert_eq!(again.chunks_skipped(), 1);
+    }
+
+    #[test]
+    fn copies_only_what_changed() {
+        let old = sequential_bytes(1 << 20);
+        let mut new = old.clone();
+
+        new[300_000] ^= 0xFF;
+
+        let src = InMemoryStore::default();
+        let dst = InMemoryStore::default();
+        let old_hkey = src.put(&old).expect("Failed to put");
+        let new_hkey = src.put(&new).expect("Failed to put");
+
+        old_hkey.transfer(&src, &dst).expect("Failed to transfer");
+
+        let count = dst.hashes().expect("Failed to list").len();
+        let report = new_hkey.transfer(&src, &dst).expect("Failed to transfer");
+
+        assert_eq!(new_hkey.resolve(&dst).expect("Failed to resolve"), new);
+        assert_eq!(
+            dst.hashes().expect("Failed to list").len(),
+            count + report.chunks_copied()
+        );
+        assert!(report.chunks_copied() < count / 4);
+        assert!(report.chunks_skipped() > 0);
+    }
+
+    #[test]
+    fn skips_inline_keys() {
+        let src = InMemoryStore::default();
+        let dst = InMemoryStore::default();
+        let hkey = src.put(b"short").expect("Failed to put");
+
+        assert_eq!(
+            hkey.transfer(&src, &dst).expect("Failed to transfer"),
+            TransferReport::default()
+        );
+        assert!(dst.hashes().expect("Failed to list").is_empty());
+    }
+
+    #[test]
+    fn async_matches_sync() {
+        let data = sequential_bytes(500_000);
+        let src = InMemoryStore::default();
+        let hkey = src.put(&data).expect("Failed to put");
+        let sync = hkey
+            .transfer(&src, &InMemoryStore::default())
+            .expect("Failed to transfer");
+
+        let src = InMemoryAsyncStore::default();
+        let dst = InMemoryAsyncStore::default();
+
+        block_on(async {
+            let hkey = src
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+            let report = hkey
+                .transfer_async(src, dst.clone())
+                .await
+                .expect("Failed to transfer");
+
+            assert_eq!(report, sync);
+            assert_eq!(
+                dst.hashes().await.expect("Failed to list").len(),
+                sync.chunks_copied()
+            );
+            assert_eq!(
+                hkey.resolve_async(dst)
+                    .await
+                    .expect("Failed to resolve")
+                    .as_ref(),
+                data.as_slice()
+            );
+        });
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashSet, future::Future, pin::Pin, sync::Arc};
+
+use futures::future::try_join_all;
+use parking_lot::Mutex;
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+use ps_promise::PromiseRejection;
+
+use crate::{AsyncStore, AsyncStoreContains, Hkey, HkeyError};
+
+use super::transfer::{parse_node, TransferError, TransferReport};
+
+impl Hkey {
+    /// Asynchronous counterpart of [`Hkey::transfer`].
+    pub async fn transfer_async<C, E, S, F, D>(
+        &self,
+        src: S,
+        dst: D,
+    ) -> Result<TransferReport, TransferError<E, F>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+        F: PromiseRejection + Send,
+        D: AsyncStoreContains<Error = F>,
+    {
+        self.transfer_async_box(src, dst, Arc::default()).await
+    }
+
+    /// Transfers `self`, skipping the chunks in `visited`, and adding the rest to it.
+    fn transfer_async_box<'a, C, E, S, F, D>(
+        &'a self,
+        src: S,
+        dst: D,
+        visited: Arc<Mutex<HashSet<Hash>>>,
+    ) -> Pin<Box<dyn Future<Output = Result<TransferReport, TransferError<E, F>>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+        F: PromiseRejection + Send + 'a,
+        D: AsyncStoreContains<Error = F>,
+    {
+        Box::pin(async move {
+            match self {
+                Self::Empty | Self::Raw(_) | Self::Base64(_) => Ok(TransferReport::default()),
+                Self::Direct(hash) | Self::Encrypted(hash, _) => {
+                    transfer_chunk_async(src, dst, visited, hash, None).await
+                }
+                Self::ListRef(hash, key) => {
+                    transfer_chunk_async(src, dst, visited, hash, Some(key)).await
+                }
+                Self::LongHkey(lhkey) => {
+                    let (hash, key) = (lhkey.hash_ref(), lhkey.key_ref());
+
+                    transfer_chunk_async(src, dst, visited, hash, Some(key)).await
+                }
+                Self::List(list) => transfer_all_async(src, dst, visited, list.iter()).await,
+                Self::LongHkeyExpanded(lhkey) => {
+                    let parts = lhkey.parts().iter().map(|(_, hkey)| hkey);
+
+                    transfer_all_async(src, dst, visited, parts).await
+                }
+            }
+        })
+    }
+}
+
+/// Transfers `hkeys` concurrently, summing up their reports.
+async fn transfer_all_async<'a, C, E, S, F, D, I>(
+    src: S,
+    dst: D,
+    visited: Arc<Mutex<HashSet<Hash>>>,
+    hkeys: I,
+) -> Result<TransferReport, TransferError<E, F>>
+where
+    C: DataChunk + Send,
+    E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+    S: AsyncStore<Chunk = C, Error = E>,
+    F: PromiseRejection + Send + 'a,
+    D: AsyncStoreContains<Error = F>,
+    I: Iterator<Item = &'a Hkey>,
+{
+    let futures =
+        hkeys.map(|hkey| hkey.transfer_async_box(src.clone(), dst.clone(), visited.clone()));
+
+    Ok(try_join_all(futures)
+        .await?
+        .into_iter()
+        .fold(TransferReport::default(), TransferReport::merge))
+}
+
+/// Asynchronous counterpart of `transfer_chunk`.
+async fn transfer_chunk_async<C, E, S, F, D>(
+    src: S,
+    dst: D,
+    visited: Arc<Mutex<HashSet<Hash>>>,
+    hash: &Hash,
+    key: Option<&Hash>,
+) -> Result<TransferReport, TransferError<E, F>>
+where
+    C: DataChunk + Send,
+    E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+    S: AsyncStore<Chunk = C, Error = E>,
+    F: PromiseRejection + Send,
+    D: AsyncStoreContains<Error = F>,
+{
+    if !visited.lock().insert(*hash) {
+        return Ok(TransferReport::default());
+    }
+
+    if dst
+        .contains(hash)
+        .await
+        .map_err(TransferError::Destination)?
+    {
+        return Ok(TransferReport::skipped());
+    }
+
+    let chunk = src.get(hash).await.map_err(TransferError::Source)?;
+
+    let report = match key {
+        Some(key) => {
+            parse_node::<C, E>(&chunk, key)
+                .map_err(TransferError::Source)?
+                .transfer_async_box(src, dst.clone(), visited)
+                .await?
+        }
+        None => TransferReport::default(),
+    };
+
+    let bytes = chunk.data_ref().len();
+
+    dst.put_encrypted(chunk)
+        .await
+        .map_err(TransferError::Destination)?;
+
+    Ok(report.merge(TransferReport::copied(bytes)))
+}
@@ -8,14 +8,15 @@ impl Hkey {
             return Ok(Self::Empty);
         }
 
+        // Lists and long hkeys come first, as they may happen to be as long as a hash.
         match (bytes[0], bytes.len()) {
+            (b'[', _) => Self::try_as_list(bytes),
+            (b'{', _) => Self::try_as_long(bytes),
             (_, HASH_SIZE) => Self::try_as_direct(bytes),
             (_, DOUBLE_HASH_SIZE) => Self::try_as_encrypted(bytes),
             (b'D', HASH_SIZE_PREFIXED) => Self::try_as_direct(&bytes[1..]),
             (b'E', DOUBLE_HASH_SIZE_PREFIXED) => Self::try_as_encrypted(&bytes[1..]),
             (b'L', DOUBLE_HASH_SIZE_PREFIXED) => Self::try_as_list_ref(&bytes[1..]),
-            (b'[', _) => Self::try_as_list(bytes),
-            (b'{', _) => Self::try_as_long(bytes),
             _ => Ok(match std::str::from_utf8(bytes) {
                 Ok(str) => Self::from_base64_slice(str)?,
                 Err(_) => Self::from_raw(bytes)?,
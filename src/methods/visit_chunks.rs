@@ -0,0 +1,109 @@
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+
+use crate::{Hkey, HkeyError, Store};
+
+impl Hkey {
+    /// Calls `visitor` with the hash of every chunk `self` depends on, parents before children.
+    ///
+    /// The index chunks of [`ListRef`](Self::ListRef) and [`LongHkey`](Self::LongHkey) are
+    /// fetched in order to find their children, unless `visitor` returns `false` for them,
+    /// which allows skipping subtrees that have already been visited.
+    pub fn visit_chunks<'a, C, E, S, V>(&self, store: &'a S, visitor: &mut V) -> Result<(), E>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+        V: FnMut(&Hash) -> bool,
+    {
+        match self {
+            Self::Empty | Self::Raw(_) | Self::Base64(_) => {}
+            Self::Direct(hash) | Self::Encrypted(hash, _) => {
+                visitor(hash);
+            }
+            Self::ListRef(hash, key) => {
+                if visitor(hash) {
+                    let list_bytes = Self::resolve_encrypted(hash, key, store)?;
+
+                    Self::parse(list_bytes.data_ref())
+                        .map_err(HkeyError::Construction)?
+                        .visit_chunks(store, visitor)?;
+                }
+            }
+            Self::List(list) => {
+                for hkey in list.iter() {
+                    hkey.visit_chunks(store, visitor)?;
+                }
+            }
+            Self::LongHkey(lhkey) => {
+                if visitor(lhkey.hash_ref()) {
+                    Self::LongHkeyExpanded(lhkey.expand(store)?).visit_chunks(store, visitor)?;
+                }
+            }
+            Self::LongHkeyExpanded(lhkey) => {
+                for (_, hkey) in lhkey.parts() {
+                    hkey.visit_chunks(store, visitor)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc};
+
+    use crate::{Hkey, InMemoryStore, Store, StoreIter};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn visits_every_stored_chunk() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(300_000);
+
+        let items: Arc<[Hkey]> = Arc::from(vec![
+            store.put(&data[..10]).expect("Failed to put"),
+            store.put(&data[..1000]).expect("Failed to put"),
+            store.put(&data).expect("Failed to put"),
+        ]);
+        let hkey = Hkey::List(items).shrink(&store).expect("Failed to shrink");
+
+        let mut visited = HashSet::new();
+
+        hkey.visit_chunks(&store, &mut |hash| visited.insert(*hash))
+            .expect("Failed to visit");
+
+        let stored: HashSet<_> = store
+            .hashes()
+            .expect("Failed to enumerate")
+            .into_iter()
+            .collect();
+
+        assert_eq!(visited, stored);
+    }
+
+    #[test]
+    fn skips_subtrees_the_visitor_declines() {
+        let store = InMemoryStore::default();
+        let hkey = store
+            .put(&sequential_bytes(300_000))
+            .expect("Failed to put");
+
+        let mut count = 0;
+
+        hkey.visit_chunks(&store, &mut |_| {
+            count += 1;
+            false
+        })
+        .expect("Failed to visit");
+
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,112 @@
+use std::{future::Future, pin::Pin};
+
+use ps_datachunk::{DataChunk, DataChunkError};
+use ps_hash::Hash;
+use ps_promise::PromiseRejection;
+
+use crate::{AsyncStore, Hkey, HkeyError};
+
+impl Hkey {
+    pub fn visit_chunks_async_box<'a, C, E, S, V>(
+        &'a self,
+        store: S,
+        visitor: &'a mut V,
+    ) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+        V: FnMut(&Hash) -> bool + Send,
+    {
+        Box::pin(async move { self.visit_chunks_async(store, visitor).await })
+    }
+
+    /// Asynchronous counterpart of [`Hkey::visit_chunks`].
+    pub async fn visit_chunks_async<C, E, S, V>(&self, store: S, visitor: &mut V) -> Result<(), E>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+        V: FnMut(&Hash) -> bool + Send,
+    {
+        match self {
+            Self::Empty | Self::Raw(_) | Self::Base64(_) => {}
+            Self::Direct(hash) | Self::Encrypted(hash, _) => {
+                visitor(hash);
+            }
+            Self::ListRef(hash, key) => {
+                if visitor(hash) {
+                    let list_bytes =
+                        Self::resolve_encrypted_async(hash, key, store.clone()).await?;
+
+                    Self::parse(list_bytes.data_ref())
+                        .map_err(HkeyError::Construction)?
+                        .visit_chunks_async_box(store, visitor)
+                        .await?;
+                }
+            }
+            Self::List(list) => {
+                for hkey in list.iter() {
+                    hkey.visit_chunks_async_box(store.clone(), visitor).await?;
+                }
+            }
+            Self::LongHkey(lhkey) => {
+                if visitor(lhkey.hash_ref()) {
+                    Self::LongHkeyExpanded(lhkey.expand_async(store.clone()).await?)
+                        .visit_chunks_async_box(store, visitor)
+                        .await?;
+                }
+            }
+            Self::LongHkeyExpanded(lhkey) => {
+                for (_, hkey) in lhkey.parts() {
+                    hkey.visit_chunks_async_box(store.clone(), visitor).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::collections::HashSet;
+
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{AsyncStore, AsyncStoreIter, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn visits_every_stored_chunk() {
+        let store = InMemoryAsyncStore::default();
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::from_owner(sequential_bytes(300_000)))
+                .await
+                .expect("Failed to put");
+
+            let mut visited = HashSet::new();
+
+            hkey.visit_chunks_async(store.clone(), &mut |hash| visited.insert(*hash))
+                .await
+                .expect("Failed to visit");
+
+            let stored: HashSet<_> = store
+                .hashes()
+                .await
+                .expect("Failed to enumerate")
+                .into_iter()
+                .collect();
+
+            assert_eq!(visited, stored);
+        });
+    }
+}
@@ -0,0 +1,245 @@
+use ps_datachunk::{DataChunk, DataChunkError};
+
+use crate::{Hkey, HkeyError, Store};
+
+impl Hkey {
+    /// Writes `data` at `offset` into the buffer `self` represents, returning the shrunk
+    /// [`Hkey`] of the result.
+    ///
+    /// Writing past the end grows the buffer, zero-filling any gap. Leaves are rewritten whole
+    /// and become a [`Hkey::LongHkey`] once they outgrow [`MAX_DECRYPTED_SIZE`], lists only
+    /// rewrite the items overlapping `data`, and long keys are updated in place via
+    /// [`LongHkeyExpanded::update`](crate::LongHkeyExpanded::update).
+    ///
+    /// # Errors
+    /// - [`HkeyError::ListTooLong`] if a list grows past what its [`Hkey::ListRef`] chunk holds.
+    ///
+    /// [`MAX_DECRYPTED_SIZE`]: crate::MAX_DECRYPTED_SIZE
+    pub fn write_at<'a, C, E, S>(&self, store: &'a S, offset: usize, data: &[u8]) -> Result<Self, E>
+    where
+        C: DataChunk,
+        E: From<DataChunkError> + From<HkeyError> + Send,
+        S: Store<Chunk<'a> = C, Error = E> + Sync + 'a,
+    {
+        let end = write_end(offset, data)?;
+
+        if data.is_empty() {
+            return self.shrink(store);
+        }
+
+        match self {
+            Self::ListRef(hash, key) => {
+                let list_bytes = Self::resolve_encrypted(hash, key, store)?;
+
+                Self::parse(list_bytes.data_ref())
+                    .map_err(HkeyError::Construction)?
+                    .write_at(store, offset, data)
+            }
+            Self::List(list) => {
+                let mut items = Vec::with_capacity(list.len() + 1);
+                let mut position = 0;
+
+                for item in list.iter() {
+                    let item_end = position + item.len(store)?;
+
+                    if item_end <= offset || position >= end {
+                        items.push(item.clone());
+                    } else {
+                        let start = offset.max(position);
+                        let stop = end.min(item_end);
+                        let slice = &data[start - offset..stop - offset];
+
+                        items.push(item.write_at(store, start - position, slice)?);
+                    }
+
+                    position = item_end;
+                }
+
+                if end > position {
+                    items.push(store.put(&append(position, offset, data))?);
+                }
+
+                Self::List(items.into()).shrink(store)
+            }
+            Self::LongHkey(lhkey) => {
+                let updated = lhkey.expand(store)?.update(store, data, offset..end)?;
+
+                Self::LongHkeyExpanded(updated).shrink(store)
+            }
+            Self::LongHkeyExpanded(lhkey) => {
+                let updated = lhkey.update(store, data, offset..end)?;
+
+                Self::LongHkeyExpanded(updated).shrink(store)
+            }
+            leaf => store.put(&patch(&leaf.resolve(store)?, offset, data)),
+        }
+    }
+}
+
+pub fn write_end(offset: usize, data: &[u8]) -> Result<usize, HkeyError> {
+    offset
+        .checked_add(data.len())
+        .ok_or_else(|| HkeyError::InvalidRange(offset..offset.wrapping_add(data.len())))
+}
+
+/// Returns a copy of `original` with `data` written at `offset`.
+pub fn patch(original: &[u8], offset: usize, data: &[u8]) -> Vec<u8> {
+    let end = offset + data.len();
+    let mut buffer = Vec::with_capacity(original.len().max(end));
+
+    buffer.extend_from_slice(original);
+
+    if buffer.len() < end {
+        buffer.resize(end, 0);
+    }
+
+    buffer[offset..end].copy_from_slice(data);
+
+    buffer
+}
+
+/// Returns the part of `data` written at `offset` which lies past `length`, preceded by the
+/// zeroes filling any gap.
+pub fn append(length: usize, offset: usize, data: &[u8]) -> Vec<u8> {
+    let mut tail = vec![0; offset.saturating_sub(length)];
+
+    tail.extend_from_slice(&data[length.saturating_sub(offset)..]);
+
+    tail
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_datachunk::DataChunk;
+
+    use crate::{Hkey, InMemoryStore, Store};
+
+    use super::patch;
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn list_items(hkey: &Hkey, store: &InMemoryStore) -> Vec<Hkey> {
+        let Hkey::ListRef(hash, key) = hkey else {
+            panic!("Expected Hkey::ListRef, got {hkey}");
+        };
+
+        let list_bytes = Hkey::resolve_encrypted(hash, key, store).expect("Failed to decrypt");
+
+        match Hkey::parse(list_bytes.data_ref()).expect("Failed to parse") {
+            Hkey::List(items) => items.to_vec(),
+            hkey => panic!("Expected Hkey::List, got {hkey}"),
+        }
+    }
+
+    #[test]
+    fn patches_leaves() {
+        let store = InMemoryStore::default();
+
+        for len in [0, 10, 100, 3000] {
+            let original = sequential_bytes(len);
+            let hkey = store.put(&original).expect("Failed to put");
+
+            for (offset, data) in [(0, &b"abc"[..]), (len / 2, b"xyz"), (len + 5, b"tail")] {
+                let written = hkey
+                    .write_at(&store, offset, data)
+                    .expect("Failed to write");
+
+                assert_eq!(
+                    written.resolve(&store).expect("Failed to resolve"),
+                    patch(&original, offset, data)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn promotes_growing_leaves() {
+        let store = InMemoryStore::default();
+        let original = sequential_bytes(3000);
+        let hkey = store.put(&original).expect("Failed to put");
+
+        assert!(matches!(hkey, Hkey::Encrypted(..)));
+
+        let data = vec![7; 100_000];
+        let written = hkey.write_at(&store, 2000, &data).expect("Failed to write");
+
+        assert!(matches!(written, Hkey::LongHkey(_)));
+        assert_eq!(
+            written.resolve(&store).expect("Failed to resolve"),
+            patch(&original, 2000, &data)
+        );
+    }
+
+    #[test]
+    fn reuses_untouched_list_items() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(12_000);
+        let items: Vec<Hkey> = data
+            .chunks(3000)
+            .map(|chunk| store.put(chunk).expect("Failed to put"))
+            .collect();
+        let hkey = Hkey::List(items.clone().into());
+
+        let written = hkey
+            .write_at(&store, 4000, b"patched")
+            .expect("Failed to write");
+        let written_items = list_items(&written, &store);
+
+        assert_eq!(written_items.len(), 4);
+        assert_eq!(written_items[0], items[0]);
+        assert_ne!(written_items[1], items[1]);
+        assert_eq!(written_items[2..], items[2..]);
+        assert_eq!(
+            written.resolve(&store).expect("Failed to resolve"),
+            patch(&data, 4000, b"patched")
+        );
+
+        // Writes spanning items and the end of the list.
+        let tail = vec![9; 5000];
+        let appended = written
+            .write_at(&store, 11_000, &tail)
+            .expect("Failed to write");
+
+        assert_eq!(list_items(&appended, &store)[..2], written_items[..2]);
+        assert_eq!(
+            appended.resolve(&store).expect("Failed to resolve"),
+            patch(&patch(&data, 4000, b"patched"), 11_000, &tail)
+        );
+
+        let gap = appended
+            .write_at(&store, 20_000, b"gap")
+            .expect("Failed to write");
+
+        assert_eq!(
+            gap.resolve(&store).expect("Failed to resolve"),
+            patch(
+                &patch(&patch(&data, 4000, b"patched"), 11_000, &tail),
+                20_000,
+                b"gap"
+            )
+        );
+    }
+
+    #[test]
+    fn updates_long_hkeys() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(300_000);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        assert!(matches!(hkey, Hkey::LongHkey(_)));
+
+        let written = hkey
+            .write_at(&store, 150_000, b"patched")
+            .expect("Failed to write");
+
+        assert!(matches!(written, Hkey::LongHkey(_)));
+        assert_eq!(
+            written.resolve(&store).expect("Failed to resolve"),
+            patch(&data, 150_000, b"patched")
+        );
+    }
+}
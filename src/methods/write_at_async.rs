@@ -0,0 +1,171 @@
+use std::{future::Future, pin::Pin};
+
+use ps_datachunk::{Bytes, DataChunk, DataChunkError};
+use ps_promise::PromiseRejection;
+
+use crate::{AsyncStore, Hkey, HkeyError};
+
+use super::write_at::{append, patch, write_end};
+
+impl Hkey {
+    pub fn write_at_async_box<'a, C, E, S>(
+        &'a self,
+        store: S,
+        offset: usize,
+        data: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<Self, E>> + Send + 'a>>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send + 'a,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        Box::pin(async move { self.write_at_async(store, offset, data).await })
+    }
+
+    /// Asynchronous counterpart of [`Hkey::write_at`].
+    pub async fn write_at_async<C, E, S>(
+        &self,
+        store: S,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<Self, E>
+    where
+        C: DataChunk + Send,
+        E: From<DataChunkError> + From<HkeyError> + PromiseRejection + Send,
+        S: AsyncStore<Chunk = C, Error = E>,
+    {
+        let end = write_end(offset, data)?;
+
+        if data.is_empty() {
+            return self.shrink_async(store).await;
+        }
+
+        match self {
+            Self::ListRef(hash, key) => {
+                let list_bytes = Self::resolve_encrypted_async(hash, key, store.clone()).await?;
+
+                Self::parse(list_bytes.data_ref())
+                    .map_err(HkeyError::Construction)?
+                    .write_at_async_box(store, offset, data)
+                    .await
+            }
+            Self::List(list) => {
+                let mut items = Vec::with_capacity(list.len() + 1);
+                let mut position = 0;
+
+                for item in list.iter() {
+                    let item_end = position + item.len_async_box(store.clone()).await?;
+
+                    if item_end <= offset || position >= end {
+                        items.push(item.clone());
+                    } else {
+                        let start = offset.max(position);
+                        let stop = end.min(item_end);
+                        let slice = &data[start - offset..stop - offset];
+
+                        items.push(
+                            item.write_at_async_box(store.clone(), start - position, slice)
+                                .await?,
+                        );
+                    }
+
+                    position = item_end;
+                }
+
+                if end > position {
+                    let tail = Bytes::from_owner(append(position, offset, data));
+
+                    items.push(store.put(tail).await?);
+                }
+
+                Self::List(items.into()).shrink_async(store).await
+            }
+            Self::LongHkey(lhkey) => {
+                let updated = lhkey
+                    .expand_async(store.clone())
+                    .await?
+                    .update_async(store.clone(), data, offset..end)
+                    .await?;
+
+                Self::LongHkeyExpanded(updated).shrink_async(store).await
+            }
+            Self::LongHkeyExpanded(lhkey) => {
+                let updated = lhkey.update_async(store.clone(), data, offset..end).await?;
+
+                Self::LongHkeyExpanded(updated).shrink_async(store).await
+            }
+            leaf => {
+                let original = leaf.resolve_async(store.clone()).await?;
+
+                store
+                    .put(Bytes::from_owner(patch(&original, offset, data)))
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{methods::write_at::patch, AsyncStore, Hkey, InMemoryAsyncStore};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn write_at_async_matches_patch() {
+        let store = InMemoryAsyncStore::default();
+        let data = sequential_bytes(300_000);
+
+        block_on(async {
+            let lens = [0, 10, 3000, 300_000];
+            let mut items = Vec::new();
+
+            for len in lens {
+                let hkey = store
+                    .put(Bytes::copy_from_slice(&data[..len]))
+                    .await
+                    .expect("Failed to put");
+
+                items.push(hkey);
+            }
+
+            let list = Hkey::List(Arc::from(items.clone()));
+            let expected: Vec<u8> = lens.iter().flat_map(|&len| &data[..len]).copied().collect();
+
+            let mut cases: Vec<(Hkey, Vec<u8>)> = lens
+                .into_iter()
+                .zip(items)
+                .map(|(len, hkey)| (hkey, data[..len].to_vec()))
+                .collect();
+
+            cases.push((list, expected));
+
+            for (hkey, original) in cases {
+                for offset in [0, original.len() / 2, original.len() + 100] {
+                    let patch_data = vec![0xAB; 5000];
+                    let written = hkey
+                        .write_at_async(store.clone(), offset, &patch_data)
+                        .await
+                        .expect("Failed to write");
+
+                    assert_eq!(
+                        written
+                            .resolve_async(store.clone())
+                            .await
+                            .expect("Failed to resolve"),
+                        patch(&original, offset, &patch_data)
+                    );
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,570 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use ps_datachunk::{Bytes, DataChunk};
+use ps_hash::Hash;
+
+use crate::{Hkey, HkeyError, LongHkey, LongHkeyExpanded, Store};
+
+/// The leaf currently under the cursor, and the offset at which it begins.
+#[derive(Clone, Debug)]
+pub(crate) struct Segment {
+    pub start: usize,
+    pub data: Bytes,
+}
+
+impl Segment {
+    /// Copies as much as possible from `position` into `buf`, if this segment contains
+    /// `position`.
+    pub fn read_at(&self, position: usize, buf: &mut [u8]) -> Option<usize> {
+        let offset = position.checked_sub(self.start)?;
+        let available = self.data.get(offset..).filter(|data| !data.is_empty())?;
+        let count = available.len().min(buf.len());
+
+        buf[..count].copy_from_slice(&available[..count]);
+
+        Some(count)
+    }
+}
+
+/// Identifies a list within the tree being read: the hash of the nearest stored node above it,
+/// if any, and the indices of the parts and items leading from that node down to the list.
+pub(crate) type ListPath = (Option<Hash>, Vec<usize>);
+
+/// Returns `path` extended by `index`.
+pub(crate) fn child_path(path: &ListPath, index: usize) -> ListPath {
+    let (hash, indices) = path;
+    let mut indices = indices.clone();
+
+    indices.push(index);
+
+    (*hash, indices)
+}
+
+/// Finds the item of a list whose cumulative item ends are `ends` containing `offset`, returning
+/// its index and the offset at which it begins.
+pub(crate) fn find_item(ends: &[usize], offset: usize) -> Option<(usize, usize)> {
+    let index = ends.partition_point(|&end| end <= offset);
+
+    (index < ends.len()).then(|| (index, index.checked_sub(1).map_or(0, |i| ends[i])))
+}
+
+/// Drops the cached index nodes, lists and list offsets off the path through the stored nodes
+/// in `visited`, so that a reader only holds the nodes above its current leaf.
+pub(crate) fn retain_path(
+    visited: &[Hash],
+    nodes: &mut HashMap<Hash, LongHkeyExpanded>,
+    lists: &mut HashMap<Hash, Hkey>,
+    ends: &mut HashMap<ListPath, Arc<[usize]>>,
+) {
+    nodes.retain(|hash, _| visited.contains(hash));
+    lists.retain(|hash, _| visited.contains(hash));
+    ends.retain(|(hash, _), _| hash.is_none_or(|hash| visited.contains(&hash)));
+}
+
+/// Computes the position a [`SeekFrom`] refers to.
+pub(crate) fn seek_position(
+    position: usize,
+    length: Option<usize>,
+    target: SeekFrom,
+) -> io::Result<usize> {
+    let (base, delta) = match target {
+        SeekFrom::Start(offset) => {
+            return usize::try_from(offset).map_err(|_| io::ErrorKind::InvalidInput.into());
+        }
+        SeekFrom::Current(delta) => (position, delta),
+        SeekFrom::End(delta) => (length.unwrap_or_default(), delta),
+    };
+
+    let magnitude = usize::try_from(delta.unsigned_abs()).map_err(io::Error::other)?;
+
+    let position = if delta < 0 {
+        base.checked_sub(magnitude)
+    } else {
+        base.checked_add(magnitude)
+    };
+
+    position.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+/// Reads the data an [`Hkey`] refers to, fetching only the leaf under the cursor.
+///
+/// The index nodes of [`Hkey::LongHkey`] and [`Hkey::ListRef`] on the path to the current leaf
+/// are cached by hash, so sequential reads and nearby seeks fetch each of them once, while
+/// memory stays bounded by the depth of the tree. The offsets of the items of each
+/// [`Hkey::List`] on that path are likewise cached the first time the list is measured.
+pub struct HkeyReader<'s, S: Store> {
+    store: &'s S,
+    root: Hkey,
+    position: usize,
+    length: Option<usize>,
+    segment: Option<Segment>,
+    nodes: HashMap<Hash, LongHkeyExpanded>,
+    lists: HashMap<Hash, Hkey>,
+    ends: HashMap<ListPath, Arc<[usize]>>,
+}
+
+impl<'s, S: Store> HkeyReader<'s, S> {
+    pub fn new(store: &'s S, hkey: Hkey) -> Self {
+        Self {
+            store,
+            root: hkey,
+            position: 0,
+            length: None,
+            segment: None,
+            nodes: HashMap::new(),
+            lists: HashMap::new(),
+            ends: HashMap::new(),
+        }
+    }
+
+    /// Returns the offset of the cursor.
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bytes the [`Hkey`] refers to.
+    pub fn length(&mut self) -> Result<usize, S::Error> {
+        if let Some(length) = self.length {
+            return Ok(length);
+        }
+
+        let length = self.length_of(&(None, Vec::new()), &self.root.clone())?;
+
+        self.length = Some(length);
+
+        Ok(length)
+    }
+
+    /// Reads into `buf` from the cursor, returning the number of bytes read, which is zero at the
+    /// end of the data.
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize, S::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(count) = self.read_cached(buf) {
+            return Ok(count);
+        }
+
+        if self.position >= self.length()? {
+            return Ok(0);
+        }
+
+        self.segment = Some(self.locate(self.position)?);
+
+        Ok(self.read_cached(buf).unwrap_or_default())
+    }
+
+    fn read_cached(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let count = self.segment.as_ref()?.read_at(self.position, buf)?;
+
+        self.position += count;
+
+        Some(count)
+    }
+
+    /// Finds the leaf containing `position`, descending through the index nodes.
+    fn locate(&mut self, position: usize) -> Result<Segment, S::Error> {
+        let mut hkey = self.root.clone();
+        let mut start = 0;
+        let mut offset = position;
+        let mut limit = usize::MAX;
+        let mut path: ListPath = (None, Vec::new());
+        let mut visited = Vec::new();
+
+        loop {
+            hkey = match hkey {
+                Hkey::LongHkey(lhkey) => {
+                    path = (Some(lhkey.hash()), Vec::new());
+                    visited.push(lhkey.hash());
+
+                    Hkey::LongHkeyExpanded(self.expand(&lhkey)?)
+                }
+                Hkey::ListRef(hash, key) => {
+                    path = (Some(hash), Vec::new());
+                    visited.push(hash);
+
+                    self.list(&hash, &key)?
+                }
+                Hkey::LongHkeyExpanded(lhkey) => {
+                    // Part ranges may overrun the end of the node.
+                    let size = lhkey.size().min(limit);
+
+                    let (index, (range, part)) = lhkey
+                        .parts()
+                        .iter()
+                        .enumerate()
+                        .find(|(_, (range, _))| {
+                            range.start <= offset && offset < range.end.min(size)
+                        })
+                        .ok_or(HkeyError::Range(size))?;
+
+                    start += range.start;
+                    offset -= range.start;
+                    limit = range.end.min(size) - range.start;
+                    path.1.push(index);
+
+                    part.clone()
+                }
+                Hkey::List(items) => {
+                    let ends = self.item_ends(&path, &items)?;
+
+                    let (index, begin) = find_item(&ends, offset)
+                        .ok_or_else(|| HkeyError::Range(start + ends.last().unwrap_or(&0)))?;
+
+                    start += begin;
+                    offset -= begin;
+                    limit = (ends[index] - begin).min(limit);
+                    path.1.push(index);
+
+                    items[index].clone()
+                }
+                leaf => {
+                    let data = leaf.resolve(self.store)?;
+                    let data = data.slice(..data.len().min(limit));
+
+                    if offset >= data.len() {
+                        Err(HkeyError::Range(data.len()))?;
+                    }
+
+                    retain_path(&visited, &mut self.nodes, &mut self.lists, &mut self.ends);
+
+                    return Ok(Segment { start, data });
+                }
+            }
+        }
+    }
+
+    /// Measures `hkey`, found at `path`, fetching leaves only if [`Hkey::len_hint`] cannot tell
+    /// their length.
+    fn length_of(&mut self, path: &ListPath, hkey: &Hkey) -> Result<usize, S::Error> {
+        if let Some(length) = hkey.len_hint() {
+            return Ok(length);
+        }
+
+        let length = match hkey {
+            Hkey::LongHkey(lhkey) => self.expand(lhkey)?.size(),
+            Hkey::ListRef(hash, key) => {
+                let list = self.list(hash, key)?;
+
+                self.length_of(&(Some(*hash), Vec::new()), &list)?
+            }
+            Hkey::List(items) => self
+                .item_ends(path, items)?
+                .last()
+                .copied()
+                .unwrap_or_default(),
+            leaf => leaf.len(self.store)?,
+        };
+
+        Ok(length)
+    }
+
+    /// Returns the offsets at which the items of the list at `path` end.
+    fn item_ends(&mut self, path: &ListPath, items: &[Hkey]) -> Result<Arc<[usize]>, S::Error> {
+        if let Some(ends) = self.ends.get(path) {
+            return Ok(ends.clone());
+        }
+
+        let mut ends = Vec::with_capacity(items.len());
+        let mut end = 0;
+
+        for (index, item) in items.iter().enumerate() {
+            end += self.length_of(&child_path(path, index), item)?;
+            ends.push(end);
+        }
+
+        let ends: Arc<[usize]> = ends.into();
+
+        self.ends.insert(path.clone(), ends.clone());
+
+        Ok(ends)
+    }
+
+    fn expand(&mut self, lhkey: &LongHkey) -> Result<LongHkeyExpanded, S::Error> {
+        if let Some(expanded) = self.nodes.get(lhkey.hash_ref()) {
+            return Ok(expanded.clone());
+        }
+
+        let expanded = lhkey.expand(self.store)?;
+
+        self.nodes.insert(lhkey.hash(), expanded.clone());
+
+        Ok(expanded)
+    }
+
+    fn list(&mut self, hash: &Hash, key: &Hash) -> Result<Hkey, S::Error> {
+        if let Some(list) = self.lists.get(hash) {
+            return Ok(list.clone());
+        }
+
+        let chunk = self.store.get(hash)?;
+        let decrypted = chunk.decrypt(key)?;
+        let list = Hkey::parse(decrypted.data_ref()).map_err(HkeyError::Construction)?;
+
+        self.lists.insert(*hash, list.clone());
+
+        Ok(list)
+    }
+}
+
+impl<S> Read for HkeyReader<'_, S>
+where
+    S: Store,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_bytes(buf).map_err(io::Error::other)
+    }
+}
+
+impl<S> Seek for HkeyReader<'_, S>
+where
+    S: Store,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        let length = match target {
+            SeekFrom::End(_) => Some(self.length().map_err(io::Error::other)?),
+            _ => None,
+        };
+
+        self.position = seek_position(self.position, length, target)?;
+
+        Ok(self.position as u64)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{
+        io::{Read, Seek, SeekFrom},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use ps_datachunk::DataChunk;
+    use ps_hash::Hash;
+
+    use crate::{Hkey, HkeyReader, InMemoryStore, InMemoryStoreError, Store};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[derive(Default)]
+    struct CountingStore {
+        inner: InMemoryStore,
+        gets: AtomicUsize,
+    }
+
+    impl Store for CountingStore {
+        type Chunk<'c> = <InMemoryStore as Store>::Chunk<'c>;
+        type Error = InMemoryStoreError;
+
+        fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+            self.gets.fetch_add(1, Ordering::Relaxed);
+            self.inner.get(hash)
+        }
+
+        fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+            self.inner.put_encrypted(chunk)
+        }
+    }
+
+    #[test]
+    fn reads_a_long_hkey_to_the_end() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(200_000);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        let mut reader = HkeyReader::new(&store, hkey);
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer).expect("Failed to read");
+
+        assert_eq!(buffer, data);
+        assert_eq!(reader.length().expect("Failed to measure"), data.len());
+    }
+
+    #[test]
+    fn seeks_within_a_long_hkey() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(300_000);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        let mut reader = HkeyReader::new(&store, hkey);
+
+        for position in [0, 4095, 4096, 65_535, 65_536, 123_456, 299_990] {
+            let mut buffer = [0; 100];
+
+            reader
+                .seek(SeekFrom::Start(position as u64))
+                .expect("Failed to seek");
+
+            let count = reader.read(&mut buffer).expect("Failed to read");
+
+            assert!(count > 0);
+            assert_eq!(&buffer[..count], &data[position..position + count]);
+        }
+
+        let mut tail = Vec::new();
+
+        reader.seek(SeekFrom::End(-10)).expect("Failed to seek");
+        reader.read_to_end(&mut tail).expect("Failed to read");
+
+        assert_eq!(tail, &data[data.len() - 10..]);
+
+        reader.seek(SeekFrom::Current(5)).expect("Failed to seek");
+
+        assert_eq!(reader.read(&mut [0; 8]).expect("Failed to read"), 0);
+        assert!(reader.seek(SeekFrom::Current(-400_000)).is_err());
+    }
+
+    #[test]
+    fn fetches_only_the_path_to_the_cursor() {
+        let store = CountingStore::default();
+        let data = sequential_bytes(300_000);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        let mut reader = HkeyReader::new(&store, hkey);
+        let mut buffer = [0; 10];
+
+        reader
+            .seek(SeekFrom::Start(150_000))
+            .expect("Failed to seek");
+        reader.read_exact(&mut buffer).expect("Failed to read");
+
+        // the root, one node of depth 0 and one leaf
+        assert_eq!(store.gets.load(Ordering::Relaxed), 3);
+        assert_eq!(buffer, data[150_000..150_010]);
+
+        reader
+            .seek(SeekFrom::Start(160_000))
+            .expect("Failed to seek");
+        reader.read_exact(&mut buffer).expect("Failed to read");
+
+        // both nodes are cached, so only the next leaf is fetched
+        assert_eq!(store.gets.load(Ordering::Relaxed), 4);
+        assert_eq!(buffer, data[160_000..160_010]);
+    }
+
+    #[test]
+    fn reads_lists() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(80_000);
+
+        let items: Arc<[Hkey]> = Arc::from(vec![
+            store.put(&data[..10]).expect("Failed to put"),
+            store.put(&data[10..3000]).expect("Failed to put"),
+            Hkey::Empty,
+            store.put(&data[3000..]).expect("Failed to put"),
+        ]);
+
+        let list = Hkey::List(items);
+        let list_ref = list.shrink(&store).expect("Failed to shrink");
+
+        for hkey in [list, list_ref] {
+            let mut reader = HkeyReader::new(&store, hkey);
+            let mut buffer = Vec::new();
+
+            reader.read_to_end(&mut buffer).expect("Failed to read");
+
+            assert_eq!(buffer, data);
+
+            let mut window = [0; 20];
+
+            reader.seek(SeekFrom::Start(2990)).expect("Failed to seek");
+            reader.read_exact(&mut window).expect("Failed to read");
+
+            assert_eq!(window, data[2990..3010]);
+        }
+    }
+
+    #[test]
+    fn measures_each_list_item_once() {
+        let store = CountingStore::default();
+        let data = sequential_bytes(40 * 300);
+
+        // Leaves of 300 bytes are too long for their length to be read from their hash.
+        let mut items: Vec<Hkey> = data[..30 * 300]
+            .chunks(300)
+            .map(|chunk| store.put(chunk).expect("Failed to put"))
+            .collect();
+        let nested: Vec<Hkey> = data[30 * 300..]
+            .chunks(300)
+            .map(|chunk| store.put(chunk).expect("Failed to put"))
+            .collect();
+
+        items.push(Hkey::List(nested.into()));
+
+        let mut reader = HkeyReader::new(&store, Hkey::List(items.into()));
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer).expect("Failed to read");
+
+        assert_eq!(buffer, data);
+
+        // each leaf is fetched once to be measured and once to be read
+        assert_eq!(store.gets.load(Ordering::Relaxed), 2 * 40);
+
+        let mut window = [0; 20];
+
+        reader.seek(SeekFrom::Start(9_290)).expect("Failed to seek");
+        reader.read_exact(&mut window).expect("Failed to read");
+
+        assert_eq!(window, data[9_290..9_310]);
+        assert_eq!(store.gets.load(Ordering::Relaxed), 2 * 40 + 2);
+    }
+
+    #[test]
+    fn caches_only_the_path_to_the_cursor() {
+        let store = CountingStore::default();
+        let data = sequential_bytes(3_000_000);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        let mut reader = HkeyReader::new(&store, hkey);
+        let mut buffer = vec![0; 10_000];
+        let mut read = Vec::new();
+        let mut most_cached = 0;
+
+        loop {
+            let count = reader.read(&mut buffer).expect("Failed to read");
+
+            if count == 0 {
+                break;
+            }
+
+            read.extend_from_slice(&buffer[..count]);
+            most_cached = most_cached.max(reader.nodes.len() + reader.lists.len());
+        }
+
+        assert_eq!(read, data);
+
+        // the root, one node of depth 1 and one of depth 0
+        assert_eq!(most_cached, 3);
+
+        // each node is still fetched only once
+        let nodes_of_depth_0 = data.len().div_ceil(1 << 16);
+        let nodes_of_depth_1 = data.len().div_ceil(1 << 20);
+        let leaves = data.len().div_ceil(1 << 12);
+
+        assert_eq!(
+            store.gets.load(Ordering::Relaxed),
+            1 + nodes_of_depth_1 + nodes_of_depth_0 + leaves
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! [`serde`] support, enabled by the `serde` feature.
+//!
+//! Human-readable formats use the string form of each type, as produced by [`Display`] and
+//! read back by [`Hkey::parse`]. That form does not tell every variant apart, so the round trip
+//! is lossy for two of them, though the restored key resolves to the same data:
+//!
+//! - [`Hkey::Raw`] reads back as [`Hkey::Base64`] of the same bytes.
+//! - [`Hkey::LongHkey`] reads back as [`Hkey::ListRef`] with the same hash and key.
+//!
+//! This applies within lists and the parts of a [`LongHkeyExpanded`] too.
+//!
+//! Binary formats use a tagged form with hashes in their compact form, which keeps every
+//! variant.
+//!
+//! [`Display`]: std::fmt::Display
+
+use std::fmt;
+
+use ps_hash::Hash;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    Hkey, HkeyError, HkeyFromCompactError, LongHkey, LongHkeyExpanded, Range, HASH_SIZE_COMPACT,
+};
+
+/// A [`Hash`] in its compact binary form.
+struct CompactHash(Hash);
+
+impl Serialize for CompactHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0.compact())
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(CompactHashVisitor)
+    }
+}
+
+struct CompactHashVisitor;
+
+impl<'de> Visitor<'de> for CompactHashVisitor {
+    type Value = CompactHash;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a compact hash of {HASH_SIZE_COMPACT} bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        if bytes.len() != HASH_SIZE_COMPACT {
+            return Err(E::invalid_length(bytes.len(), &self));
+        }
+
+        Hash::validate(bytes).map(CompactHash).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(HASH_SIZE_COMPACT);
+
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+
+        self.visit_bytes(&bytes)
+    }
+}
+
+/// Deserializes a value from its string form.
+struct ParseVisitor<T> {
+    expecting: &'static str,
+    parse: fn(&str) -> Result<T, String>,
+}
+
+impl<T> ParseVisitor<T> {
+    fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        expecting: &'static str,
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<T, D::Error> {
+        deserializer.deserialize_str(Self { expecting, parse })
+    }
+}
+
+impl<'de, T> Visitor<'de> for ParseVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        (self.parse)(value).map_err(E::custom)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "Hkey")]
+enum HkeyRef<'a> {
+    Empty,
+    Raw(&'a [u8]),
+    Base64(&'a str),
+    Direct(CompactHash),
+    Encrypted(CompactHash, CompactHash),
+    ListRef(CompactHash, CompactHash),
+    List(&'a [Hkey]),
+    LongHkey(&'a LongHkey),
+    LongHkeyExpanded(&'a LongHkeyExpanded),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Hkey")]
+enum HkeyRepr {
+    Empty,
+    Raw(Vec<u8>),
+    Base64(String),
+    Direct(CompactHash),
+    Encrypted(CompactHash, CompactHash),
+    ListRef(CompactHash, CompactHash),
+    List(Vec<Hkey>),
+    LongHkey(LongHkey),
+    LongHkeyExpanded(LongHkeyExpanded),
+}
+
+impl<'a> From<&'a Hkey> for HkeyRef<'a> {
+    fn from(hkey: &'a Hkey) -> Self {
+        match hkey {
+            Hkey::Empty => Self::Empty,
+            Hkey::Raw(raw) => Self::Raw(raw),
+            Hkey::Base64(base64) => Self::Base64(base64),
+            Hkey::Direct(hash) => Self::Direct(CompactHash(*hash)),
+            Hkey::Encrypted(hash, key) => Self::Encrypted(CompactHash(*hash), CompactHash(*key)),
+            Hkey::ListRef(hash, key) => Self::ListRef(CompactHash(*hash), CompactHash(*key)),
+            Hkey::List(list) => Self::List(list),
+            Hkey::LongHkey(lhkey) => Self::LongHkey(lhkey),
+            Hkey::LongHkeyExpanded(lhkey) => Self::LongHkeyExpanded(lhkey),
+        }
+    }
+}
+
+impl TryFrom<HkeyRepr> for Hkey {
+    type Error = HkeyError;
+
+    fn try_from(repr: HkeyRepr) -> Result<Self, Self::Error> {
+        let hkey = match repr {
+            HkeyRepr::Empty => Self::Empty,
+            HkeyRepr::Raw(raw) => Self::from_raw(&raw)?,
+            HkeyRepr::Base64(base64) => Self::from_base64_slice(&base64)?,
+            HkeyRepr::Direct(hash) => Self::Direct(hash.0),
+            HkeyRepr::Encrypted(hash, key) => Self::Encrypted(hash.0, key.0),
+            HkeyRepr::ListRef(hash, key) => Self::ListRef(hash.0, key.0),
+            HkeyRepr::List(list) => Self::List(list.into()),
+            HkeyRepr::LongHkey(lhkey) => Self::LongHkey(lhkey),
+            HkeyRepr::LongHkeyExpanded(lhkey) => Self::LongHkeyExpanded(lhkey),
+        };
+
+        Ok(hkey)
+    }
+}
+
+impl Serialize for Hkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            HkeyRef::from(self).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Hkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            ParseVisitor::deserialize(deserializer, "an hkey string", |value| {
+                Self::parse(value).map_err(|err| err.to_string())
+            })
+        } else {
+            Self::try_from(HkeyRepr::deserialize(deserializer)?).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "LongHkey")]
+struct LongHkeyRepr(CompactHash, CompactHash);
+
+impl Serialize for LongHkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            LongHkeyRepr(CompactHash(self.hash()), CompactHash(self.key())).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LongHkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            // The string form of a LongHkey is shared with ListRef.
+            ParseVisitor::deserialize(deserializer, "a LongHkey string", |value| {
+                match Hkey::try_parse(value) {
+                    Ok(Hkey::ListRef(hash, key)) => Ok(Self::from_hash_and_key(hash, key)),
+                    Ok(Hkey::LongHkey(lhkey)) => Ok(lhkey),
+                    Ok(hkey) => Err(format!("Expected a LongHkey, got {hkey}")),
+                    Err(err) => Err(err.to_string()),
+                }
+            })
+        } else {
+            let LongHkeyRepr(hash, key) = LongHkeyRepr::deserialize(deserializer)?;
+
+            Ok(Self::from_hash_and_key(hash.0, key.0))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "LongHkeyExpanded")]
+struct LongHkeyExpandedRef<'a> {
+    depth: u32,
+    size: usize,
+    parts: &'a [(Range, Hkey)],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "LongHkeyExpanded")]
+struct LongHkeyExpandedRepr {
+    depth: u32,
+    size: usize,
+    parts: Vec<(Range, Hkey)>,
+}
+
+impl Serialize for LongHkeyExpanded {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            LongHkeyExpandedRef {
+                depth: self.depth(),
+                size: self.size(),
+                parts: self.parts(),
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LongHkeyExpanded {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            ParseVisitor::deserialize(deserializer, "a LongHkeyExpanded string", |value| {
+                LongHkey::expand_from_lhkey_str(value.as_bytes()).map_err(|err| err.to_string())
+            })
+        } else {
+            let repr = LongHkeyExpandedRepr::deserialize(deserializer)?;
+
+            Ok(Self::new(repr.depth, repr.size, repr.parts.into()))
+        }
+    }
+}
+
+/// Error types wrapping foreign errors are serialized as their messages.
+impl Serialize for HkeyError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for HkeyFromCompactError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_hash::hash;
+
+    use crate::{Hkey, HkeyBug, HkeyConstructionError, HkeyError, LongHkey, LongHkeyExpanded};
+
+    fn mk_hash(data: &[u8]) -> ps_hash::Hash {
+        hash(data).expect("Failed to hash")
+    }
+
+    fn expanded() -> LongHkeyExpanded {
+        LongHkeyExpanded::new(
+            0,
+            8,
+            vec![
+                (0..4, Hkey::from_raw(b"abcd").expect("Failed to allocate")),
+                (4..8, Hkey::Direct(mk_hash(b"efgh"))),
+            ]
+            .into(),
+        )
+    }
+
+    /// [`expanded`] as read back from its string form, with the raw part as [`Hkey::Base64`].
+    fn expanded_from_string() -> LongHkeyExpanded {
+        LongHkeyExpanded::new(
+            0,
+            8,
+            vec![
+                (
+                    0..4,
+                    Hkey::from_base64_slice("YWJjZA").expect("Failed to allocate"),
+                ),
+                (4..8, Hkey::Direct(mk_hash(b"efgh"))),
+            ]
+            .into(),
+        )
+    }
+
+    fn every_variant() -> Vec<Hkey> {
+        vec![
+            Hkey::Empty,
+            Hkey::from_raw(&[0, 1, 2, 255]).expect("Failed to allocate"),
+            Hkey::from_base64_slice("SGVsbG8").expect("Failed to allocate"),
+            Hkey::Direct(mk_hash(b"direct")),
+            Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key")),
+            Hkey::ListRef(mk_hash(b"list-hash"), mk_hash(b"list-key")),
+            Hkey::List(
+                vec![
+                    Hkey::Direct(mk_hash(b"a")),
+                    Hkey::Encrypted(mk_hash(b"b"), mk_hash(b"c")),
+                ]
+                .into(),
+            ),
+            Hkey::LongHkey(LongHkey::from_hash_and_key(
+                mk_hash(b"long-hash"),
+                mk_hash(b"long-key"),
+            )),
+            Hkey::LongHkeyExpanded(expanded()),
+        ]
+    }
+
+    #[test]
+    fn hkey_json_uses_string_form() {
+        for hkey in every_variant() {
+            let json = serde_json::to_string(&hkey).expect("Failed to serialize");
+
+            assert_eq!(json, format!("\"{hkey}\""));
+
+            let restored: Hkey = serde_json::from_str(&json).expect("Failed to deserialize");
+
+            match &hkey {
+                Hkey::Raw(_) => assert_eq!(
+                    restored,
+                    Hkey::from_base64_slice("AAEC_w").expect("Failed to allocate")
+                ),
+                Hkey::LongHkey(long) => {
+                    assert_eq!(restored, Hkey::ListRef(long.hash(), long.key()));
+                }
+                Hkey::LongHkeyExpanded(_) => {
+                    assert_eq!(restored, Hkey::LongHkeyExpanded(expanded_from_string()));
+                }
+                _ => assert_eq!(restored, hkey),
+            }
+        }
+    }
+
+    #[test]
+    fn hkey_bincode_roundtrip() {
+        for hkey in every_variant() {
+            let bytes = bincode::serialize(&hkey).expect("Failed to serialize");
+            let restored: Hkey = bincode::deserialize(&bytes).expect("Failed to deserialize");
+
+            assert_eq!(restored, hkey);
+        }
+
+        let encrypted = Hkey::Encrypted(mk_hash(b"hash"), mk_hash(b"key"));
+        let bytes = bincode::serialize(&encrypted).expect("Failed to serialize");
+
+        assert!(bytes.len() < encrypted.to_string().len());
+    }
+
+    #[test]
+    fn long_hkey_roundtrip() {
+        let lhkey = LongHkey::from_hash_and_key(mk_hash(b"long-hash"), mk_hash(b"long-key"));
+
+        let json = serde_json::to_string(&lhkey).expect("Failed to serialize");
+        let restored: LongHkey = serde_json::from_str(&json).expect("Failed to deserialize");
+
+        assert_eq!(restored, lhkey);
+
+        let bytes = bincode::serialize(&lhkey).expect("Failed to serialize");
+        let restored: LongHkey = bincode::deserialize(&bytes).expect("Failed to deserialize");
+
+        assert_eq!(restored, lhkey);
+
+        let direct =
+            serde_json::to_string(&Hkey::Direct(mk_hash(b"direct"))).expect("Failed to serialize");
+
+        assert!(serde_json::from_str::<LongHkey>(&direct).is_err());
+    }
+
+    #[test]
+    fn long_hkey_expanded_roundtrip() {
+        let lhkey = expanded();
+
+        let json = serde_json::to_string(&lhkey).expect("Failed to serialize");
+        let restored: LongHkeyExpanded =
+            serde_json::from_str(&json).expect("Failed to deserialize");
+
+        assert_eq!(restored, expanded_from_string());
+
+        let bytes = bincode::serialize(&lhkey).expect("Failed to serialize");
+        let restored: LongHkeyExpanded =
+            bincode::deserialize(&bytes).expect("Failed to deserialize");
+
+        assert_eq!(restored, lhkey);
+    }
+
+    #[test]
+    fn invalid_input_is_rejected() {
+        let mut too_long =
+            bincode::serialize(&Hkey::Raw(Default::default())).expect("Failed to serialize");
+
+        // Replaces the empty Raw value with one exceeding its capacity.
+        too_long.truncate(4);
+        too_long.extend_from_slice(&100u64.to_le_bytes());
+        too_long.extend_from_slice(&[0; 100]);
+
+        assert!(matches!(
+            *bincode::deserialize::<Hkey>(&too_long).expect_err("Expected an error"),
+            bincode::ErrorKind::Custom(_)
+        ));
+
+        let direct =
+            bincode::serialize(&Hkey::Direct(mk_hash(b"direct"))).expect("Failed to serialize");
+
+        assert!(bincode::deserialize::<Hkey>(&direct[..direct.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn errors_serialize() {
+        assert_eq!(
+            serde_json::to_string(&HkeyError::Format).expect("Failed to serialize"),
+            "\"Invalid hkey format\""
+        );
+
+        let json =
+            serde_json::to_string(&HkeyConstructionError::TooLong).expect("Failed to serialize");
+        let restored: HkeyConstructionError =
+            serde_json::from_str(&json).expect("Failed to deserialize");
+
+        assert!(matches!(restored, HkeyConstructionError::TooLong));
+
+        let json =
+            serde_json::to_string(&HkeyBug::ResolvedSliceTooLong).expect("Failed to serialize");
+
+        assert_eq!(json, "\"ResolvedSliceTooLong\"");
+    }
+}
@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use parking_lot::Mutex;
+use ps_datachunk::{DataChunk, DataChunkError, OwnedDataChunk};
+use ps_hash::Hash;
+use ps_promise::{PromiseRejection, TaskFailure};
+
+use crate::{HkeyError, PutPolicy, Store, StoreContains, StoreIter, StoreRemove, TenantSecret};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CachingStoreError<F, S> {
+    #[error(transparent)]
+    DataChunk(#[from] DataChunkError),
+    #[error("Fast store: {0}")]
+    Fast(F),
+    #[error(transparent)]
+    Hkey(#[from] HkeyError),
+    #[error("The Promise was consumed more than once.")]
+    PromiseConsumedAlready,
+    #[error("Slow store: {0}")]
+    Slow(S),
+    #[error(transparent)]
+    TaskFailed(#[from] TaskFailure),
+}
+
+impl<F, S> PromiseRejection for CachingStoreError<F, S>
+where
+    F: Send + 'static,
+    S: Send + 'static,
+{
+    fn already_consumed() -> Self {
+        Self::PromiseConsumedAlready
+    }
+
+    fn task_failed(failure: TaskFailure) -> Self {
+        Self::TaskFailed(failure)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    /// The last use and size of each cached chunk.
+    entries: HashMap<Hash, (u64, usize)>,
+    /// The cached chunks by last use.
+    order: BTreeMap<u64, Hash>,
+    bytes: usize,
+    clock: u64,
+}
+
+/// The bookkeeping shared by [`CachingStore`] and
+/// [`AsyncCachingStore`](crate::AsyncCachingStore).
+#[derive(Debug)]
+pub(crate) struct CacheCore {
+    budget: usize,
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCore {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            lru: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn cached_bytes(&self) -> usize {
+        self.lru.lock().bytes
+    }
+
+    /// Whether a chunk of this size may be cached at all.
+    pub const fn fits(&self, size: usize) -> bool {
+        size <= self.budget
+    }
+
+    /// Records a hit on a chunk found in the fast store, marking it as the most recently used
+    /// if this cache copied it there.
+    ///
+    /// Chunks the fast store held on its own are left untracked, so they are never evicted.
+    pub fn hit(&self, hash: &Hash) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        let mut guard = self.lru.lock();
+        let lru = &mut *guard;
+
+        if let Some((used, _)) = lru.entries.get_mut(hash) {
+            lru.clock += 1;
+            lru.order.remove(used);
+            lru.order.insert(lru.clock, *hash);
+            *used = lru.clock;
+        }
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Tracks a chunk this cache copied into the fast store as the most recently used,
+    /// returning the chunks to evict from the fast store to stay within the budget.
+    pub fn insert(&self, hash: Hash, size: usize) -> Vec<Hash> {
+        let mut lru = self.lru.lock();
+
+        lru.clock += 1;
+
+        let clock = lru.clock;
+
+        if let Some((used, old_size)) = lru.entries.insert(hash, (clock, size)) {
+            lru.order.remove(&used);
+            lru.bytes -= old_size;
+        }
+
+        lru.order.insert(clock, hash);
+        lru.bytes += size;
+
+        let mut evicted = Vec::new();
+
+        while lru.bytes > self.budget {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+
+            if let Some((_, size)) = lru.entries.remove(&oldest) {
+                lru.bytes -= size;
+            }
+
+            evicted.push(oldest);
+        }
+
+        evicted
+    }
+
+    pub fn forget(&self, hash: &Hash) {
+        let mut lru = self.lru.lock();
+
+        if let Some((used, size)) = lru.entries.remove(hash) {
+            lru.order.remove(&used);
+            lru.bytes -= size;
+        }
+    }
+}
+
+/// A read-through cache, serving chunks from `fast` and copying those only found in `slow`
+/// into it.
+///
+/// The chunks copied into `fast` are evicted least recently used first, once their total size
+/// exceeds the budget. Chunks `fast` already held are served but never evicted, so it may be
+/// shared. Writes go to `slow` only; failures of `fast` are treated as misses.
+#[derive(Debug)]
+pub struct CachingStore<F, S> {
+    fast: F,
+    slow: S,
+    core: CacheCore,
+}
+
+impl<F, S> CachingStore<F, S> {
+    /// Creates a cache keeping at most `budget` bytes of chunks in `fast`.
+    pub fn new(fast: F, slow: S, budget: usize) -> Self {
+        Self {
+            fast,
+            slow,
+            core: CacheCore::new(budget),
+        }
+    }
+
+    pub const fn fast(&self) -> &F {
+        &self.fast
+    }
+
+    pub const fn slow(&self) -> &S {
+        &self.slow
+    }
+
+    /// Returns the number of chunks served from the fast store.
+    pub fn hits(&self) -> u64 {
+        self.core.hits()
+    }
+
+    /// Returns the number of chunks fetched from the slow store.
+    pub fn misses(&self) -> u64 {
+        self.core.misses()
+    }
+
+    /// Returns the total size of the chunks this cache keeps in the fast store.
+    pub fn cached_bytes(&self) -> usize {
+        self.core.cached_bytes()
+    }
+}
+
+impl<F, S> CachingStore<F, S>
+where
+    F: StoreRemove,
+    S: Store,
+{
+    fn evict(&self, evicted: Vec<Hash>) {
+        for hash in evicted {
+            let _ = self.fast.remove(&hash);
+        }
+    }
+}
+
+impl<F, S> Store for CachingStore<F, S>
+where
+    F: StoreRemove,
+    S: Store,
+{
+    type Chunk<'c>
+        = OwnedDataChunk
+    where
+        Self: 'c;
+    type Error = CachingStoreError<F::Error, S::Error>;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        if let Ok(chunk) = self.fast.get(hash) {
+            self.core.hit(hash);
+
+            return Ok(chunk.into_owned());
+        }
+
+        self.core.miss();
+
+        let chunk = self
+            .slow
+            .get(hash)
+            .map_err(CachingStoreError::Slow)?
+            .into_owned();
+        let size = chunk.data_ref().len();
+
+        if self.core.fits(size) && self.fast.put_encrypted(chunk.borrow()).is_ok() {
+            self.evict(self.core.insert(*hash, size));
+        }
+
+        Ok(chunk)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.slow
+            .put_encrypted(chunk)
+            .map_err(CachingStoreError::Slow)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.slow.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.slow.secret()
+    }
+}
+
+impl<F, S> StoreContains for CachingStore<F, S>
+where
+    F: StoreRemove,
+    S: StoreContains,
+{
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.slow.contains(hash).map_err(CachingStoreError::Slow)
+    }
+}
+
+impl<F, S> StoreIter for CachingStore<F, S>
+where
+    F: StoreRemove,
+    S: StoreIter,
+{
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.slow.hashes().map_err(CachingStoreError::Slow)
+    }
+}
+
+/// Removes the chunk from both stores, returning whether the slow store held it.
+impl<F, S> StoreRemove for CachingStore<F, S>
+where
+    F: StoreRemove,
+    S: StoreRemove,
+{
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.core.forget(hash);
+        self.fast.remove(hash).map_err(CachingStoreError::Fast)?;
+        self.slow.remove(hash).map_err(CachingStoreError::Slow)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_datachunk::DataChunk;
+
+    use crate::{CachingStore, Hkey, InMemoryStore, Store, StoreContains, StoreIter};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i + seed) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn populates_fast_store_on_miss() {
+        let store = CachingStore::new(InMemoryStore::default(), InMemoryStore::default(), 1 << 20);
+        let data = sequential_bytes(1000, 0);
+        let hkey = store.put(&data).expect("Failed to put");
+
+        assert!(store
+            .fast()
+            .hashes()
+            .expect("Failed to enumerate")
+            .is_empty());
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+        assert_eq!((store.hits(), store.misses()), (0, 1));
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+        assert_eq!((store.hits(), store.misses()), (1, 1));
+
+        assert_eq!(store.fast().hashes().expect("Failed to enumerate").len(), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let slow = InMemoryStore::default();
+        let hkeys: Vec<Hkey> = (0..3)
+            .map(|seed| {
+                slow.put(&sequential_bytes(1000, seed))
+                    .expect("Failed to put")
+            })
+            .collect();
+
+        let chunk_size = slow
+            .hashes()
+            .expect("Failed to enumerate")
+            .iter()
+            .map(|hash| slow.get(hash).expect("Failed to get").data_ref().len())
+            .max()
+            .expect("Expected chunks");
+
+        let store = CachingStore::new(InMemoryStore::default(), slow, 2 * chunk_size);
+
+        hkeys[0].resolve(&store).expect("Failed to resolve");
+        hkeys[1].resolve(&store).expect("Failed to resolve");
+        hkeys[0].resolve(&store).expect("Failed to resolve");
+        hkeys[2].resolve(&store).expect("Failed to resolve");
+
+        assert_eq!((store.hits(), store.misses()), (1, 3));
+        assert!(store.cached_bytes() <= 2 * chunk_size);
+        assert_eq!(store.fast().hashes().expect("Failed to enumerate").len(), 2);
+
+        // The second chunk was the least recently used, so only it was evicted.
+        hkeys[0].resolve(&store).expect("Failed to resolve");
+        hkeys[2].resolve(&store).expect("Failed to resolve");
+
+        assert_eq!((store.hits(), store.misses()), (3, 3));
+
+        hkeys[1].resolve(&store).expect("Failed to resolve");
+
+        assert_eq!((store.hits(), store.misses()), (3, 4));
+    }
+
+    #[test]
+    fn never_evicts_chunks_it_did_not_copy() {
+        let slow = InMemoryStore::default();
+        let fast = InMemoryStore::default();
+        let hkeys: Vec<Hkey> = (0..3)
+            .map(|seed| {
+                slow.put(&sequential_bytes(1000, seed))
+                    .expect("Failed to put")
+            })
+            .collect();
+
+        let Hkey::Encrypted(shared, _) = hkeys[0] else {
+            panic!("Expected an encrypted chunk, got {}", hkeys[0]);
+        };
+
+        let shared_chunk = slow.get(&shared).expect("Failed to get");
+
+        fast.put_encrypted(shared_chunk.borrow())
+            .expect("Failed to put");
+
+        let store = CachingStore::new(fast.clone(), slow, shared_chunk.data_ref().len());
+
+        for hkey in &hkeys {
+            hkey.resolve(&store).expect("Failed to resolve");
+        }
+
+        assert_eq!((store.hits(), store.misses()), (1, 2));
+        assert!(store.cached_bytes() <= shared_chunk.data_ref().len());
+        assert!(fast.contains(&shared).expect("Failed to check"));
+    }
+}
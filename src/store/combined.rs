@@ -1,15 +1,43 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::HashSet,
+    ops::{Deref, DerefMut},
+};
 
 use ps_datachunk::{BorrowedDataChunk, DataChunk, DataChunkError, OwnedDataChunk};
 use ps_hash::Hash;
 
-use crate::{HkeyError, Store};
+use crate::{HkeyError, PutPolicy, Store, StoreContains, StoreIter, StoreRemove, TenantSecret};
 
 pub trait DynStore: Send + Sync {
     type Error: From<DataChunkError> + From<HkeyError> + Send + 'static;
 
     fn get(&self, hash: &Hash) -> Result<OwnedDataChunk, Self::Error>;
     fn put_encrypted(&self, chunk: BorrowedDataChunk<'_>) -> Result<(), Self::Error>;
+
+    /// Returns the store's [`Store::policy`].
+    fn policy(&self) -> PutPolicy {
+        PutPolicy::default()
+    }
+
+    /// Returns the store's [`Store::secret`].
+    fn secret(&self) -> Option<TenantSecret> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`StoreContains`].
+    fn contains(&self, _hash: &Hash) -> Option<Result<bool, Self::Error>> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`StoreIter`].
+    fn hashes(&self) -> Option<Result<Vec<Hash>, Self::Error>> {
+        None
+    }
+
+    /// Returns `None` if the store does not implement [`StoreRemove`].
+    fn remove(&self, _hash: &Hash) -> Option<Result<bool, Self::Error>> {
+        None
+    }
 }
 
 impl<T> DynStore for T
@@ -25,8 +53,63 @@ where
     fn put_encrypted(&self, chunk: BorrowedDataChunk<'_>) -> Result<(), Self::Error> {
         Store::put_encrypted(self, chunk)
     }
+
+    fn policy(&self) -> PutPolicy {
+        Store::policy(self)
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        Store::secret(self)
+    }
+}
+
+/// Returns the error reported when a store added without its extension traits cannot answer.
+pub(crate) fn unsupported<E: From<HkeyError>>(extension: &'static str) -> E {
+    HkeyError::ExtensionUnsupported(extension).into()
 }
 
+/// Wraps a store so that its extension traits are reachable through [`DynStore`].
+pub(crate) struct Extended<S>(pub S);
+
+impl<S> DynStore for Extended<S>
+where
+    S: Store + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+{
+    type Error = S::Error;
+
+    fn get(&self, hash: &Hash) -> Result<OwnedDataChunk, Self::Error> {
+        Ok(Store::get(&self.0, hash)?.into_owned())
+    }
+
+    fn put_encrypted(&self, chunk: BorrowedDataChunk<'_>) -> Result<(), Self::Error> {
+        Store::put_encrypted(&self.0, chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        Store::policy(&self.0)
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        Store::secret(&self.0)
+    }
+
+    fn contains(&self, hash: &Hash) -> Option<Result<bool, Self::Error>> {
+        Some(StoreContains::contains(&self.0, hash))
+    }
+
+    fn hashes(&self) -> Option<Result<Vec<Hash>, Self::Error>> {
+        Some(StoreIter::hashes(&self.0))
+    }
+
+    fn remove(&self, hash: &Hash) -> Option<Result<bool, Self::Error>> {
+        Some(StoreRemove::remove(&self.0, hash))
+    }
+}
+
+/// Reads from the first of its stores which holds a chunk, and writes to one or all of them.
+///
+/// Writes are tried on the first store first, so its [`PutPolicy`] and [`TenantSecret`] apply
+/// to the whole combination.
 #[derive(Default)]
 pub struct CombinedStore<E: CombinedStoreError, const WRITE_TO_ALL: bool> {
     stores: Vec<Box<dyn DynStore<Error = E>>>,
@@ -61,6 +144,40 @@ impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> CombinedStore<E, WRITE_TO_
             .extend(iter.into_iter().map(|s| Box::new(s) as _));
     }
 
+    /// Creates a `CombinedStore` whose stores also take part in
+    /// [`StoreContains`], [`StoreIter`] and [`StoreRemove`].
+    #[must_use]
+    pub fn new_with_extensions<S, I>(stores: I) -> Self
+    where
+        S: Store<Error = E> + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+        I: IntoIterator<Item = S>,
+    {
+        Self {
+            stores: stores
+                .into_iter()
+                .map(|s| Box::new(Extended(s)) as _)
+                .collect(),
+        }
+    }
+
+    /// Like [`push`](Self::push), but keeps the store's extension traits.
+    pub fn push_with_extensions<S>(&mut self, store: S)
+    where
+        S: Store<Error = E> + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+    {
+        self.stores.push(Box::new(Extended(store)));
+    }
+
+    /// Like [`extend`](Self::extend), but keeps the stores' extension traits.
+    pub fn extend_with_extensions<S, I>(&mut self, iter: I)
+    where
+        S: Store<Error = E> + StoreContains + StoreIter + StoreRemove + Send + Sync + 'static,
+        I: IntoIterator<Item = S>,
+    {
+        self.stores
+            .extend(iter.into_iter().map(|s| Box::new(Extended(s)) as _));
+    }
+
     #[must_use]
     pub fn write_to_all(self) -> CombinedStore<E, true> {
         CombinedStore {
@@ -87,6 +204,15 @@ impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> CombinedStore<E, WRITE_TO_
 
         Err(last_err.unwrap_or_else(E::no_stores))
     }
+
+    fn policy(&self) -> PutPolicy {
+        self.first()
+            .map_or_else(PutPolicy::default, |store| store.policy())
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.first().and_then(|store| store.secret())
+    }
 }
 
 impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> Deref for CombinedStore<E, WRITE_TO_ALL> {
@@ -111,6 +237,14 @@ impl<E: CombinedStoreError> Store for CombinedStore<E, true> {
         self.get(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
         if self.is_empty() {
             return Err(E::no_stores());
@@ -138,6 +272,14 @@ impl<E: CombinedStoreError> Store for CombinedStore<E, false> {
         self.get(hash)
     }
 
+    fn policy(&self) -> PutPolicy {
+        self.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.secret()
+    }
+
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
         let mut last_err = None;
 
@@ -152,6 +294,219 @@ impl<E: CombinedStoreError> Store for CombinedStore<E, false> {
     }
 }
 
+/// Reports whether any of the stores holds the chunk.
+///
+/// Fails if none of the stores that can tell holds it, but some were added without their
+/// extension traits.
+impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> StoreContains
+    for CombinedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        let mut last_err = None;
+
+        for store in self.iter() {
+            match store.contains(hash) {
+                None => last_err = Some(unsupported("StoreContains")),
+                Some(Ok(false)) => {}
+                Some(Ok(true)) => return Ok(true),
+                Some(Err(err)) => last_err = Some(err),
+            }
+        }
+
+        last_err.map_or(Ok(false), Err)
+    }
+}
+
+/// Enumerates the chunks of all stores, each hash once.
+///
+/// Fails if any of the stores was added without its extension traits.
+impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> StoreIter for CombinedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        let mut seen = HashSet::new();
+        let mut hashes = Vec::new();
+
+        for store in self.iter() {
+            let batch = store
+                .hashes()
+                .unwrap_or_else(|| Err(unsupported("StoreIter")))?;
+
+            for hash in batch {
+                if seen.insert(hash) {
+                    hashes.push(hash);
+                }
+            }
+        }
+
+        Ok(hashes)
+    }
+}
+
+/// Removes the chunk from every store, returning whether any of them held it.
+///
+/// Fails if any of the stores was added without its extension traits, as the chunk may remain
+/// in them.
+impl<E: CombinedStoreError, const WRITE_TO_ALL: bool> StoreRemove for CombinedStore<E, WRITE_TO_ALL>
+where
+    Self: Store<Error = E>,
+{
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        let mut removed = false;
+        let mut first_err = None;
+
+        for store in self.iter() {
+            match store.remove(hash) {
+                None => {
+                    first_err.get_or_insert_with(|| unsupported("StoreRemove"));
+                }
+                Some(Ok(was_present)) => removed |= was_present,
+                Some(Err(err)) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        first_err.map_or(Ok(removed), Err)
+    }
+}
+
 pub trait CombinedStoreError: From<DataChunkError> + From<HkeyError> + Send + 'static {
     fn no_stores() -> Self;
 }
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_datachunk::{DataChunk, OwnedDataChunk};
+
+    use crate::{
+        CombinedStore, CombinedStoreError, HkeyError, InMemoryStore, InMemoryStoreError,
+        KeyedStore, PolicyStore, PutPolicy, Store, StoreContains, StoreIter, StoreRemove,
+        TenantSecret,
+    };
+
+    impl CombinedStoreError for InMemoryStoreError {
+        fn no_stores() -> Self {
+            Self::NotFound
+        }
+    }
+
+    #[test]
+    fn extension_traits_span_all_stores() {
+        let first = InMemoryStore::default();
+        let second = InMemoryStore::default();
+
+        let shared = OwnedDataChunk::from_data(vec![1; 100])
+            .expect("Failed to allocate")
+            .encrypt()
+            .expect("Failed to encrypt");
+        let only_second = OwnedDataChunk::from_data(vec![2; 100])
+            .expect("Failed to allocate")
+            .encrypt()
+            .expect("Failed to encrypt");
+
+        first.put_encrypted(shared.borrow()).expect("Failed to put");
+        second
+            .put_encrypted(shared.borrow())
+            .expect("Failed to put");
+        second
+            .put_encrypted(only_second.borrow())
+            .expect("Failed to put");
+
+        let combined =
+            CombinedStore::<_, true>::new_with_extensions([first.clone(), second.clone()]);
+
+        assert!(combined
+            .contains(&only_second.hash())
+            .expect("Failed to check"));
+
+        let mut hashes = combined.hashes().expect("Failed to enumerate");
+        let mut expected = vec![shared.hash(), only_second.hash()];
+
+        hashes.sort();
+        expected.sort();
+
+        assert_eq!(hashes, expected);
+
+        assert!(combined.remove(&shared.hash()).expect("Failed to remove"));
+        assert!(!first.contains(&shared.hash()).expect("Failed to check"));
+        assert!(!second.contains(&shared.hash()).expect("Failed to check"));
+        assert!(!combined.remove(&shared.hash()).expect("Failed to remove"));
+    }
+
+    #[test]
+    fn stores_without_extensions_cannot_answer() {
+        let plain = InMemoryStore::default();
+        let extended = InMemoryStore::default();
+
+        let in_plain = OwnedDataChunk::from_data(vec![1; 100])
+            .expect("Failed to allocate")
+            .encrypt()
+            .expect("Failed to encrypt");
+        let in_extended = OwnedDataChunk::from_data(vec![2; 100])
+            .expect("Failed to allocate")
+            .encrypt()
+            .expect("Failed to encrypt");
+
+        plain
+            .put_encrypted(in_plain.borrow())
+            .expect("Failed to put");
+        extended
+            .put_encrypted(in_extended.borrow())
+            .expect("Failed to put");
+
+        let mut combined = CombinedStore::<_, false>::new([plain.clone()]);
+
+        combined.push_with_extensions(extended);
+
+        let is_unsupported = |err: InMemoryStoreError| {
+            matches!(
+                err,
+                InMemoryStoreError::Hkey(HkeyError::ExtensionUnsupported(_))
+            )
+        };
+
+        assert!(combined.get(&in_plain.hash()).is_ok());
+        assert!(combined
+            .contains(&in_plain.hash())
+            .is_err_and(is_unsupported));
+        assert!(combined
+            .contains(&in_extended.hash())
+            .expect("Failed to check"));
+        assert!(combined.hashes().is_err_and(is_unsupported));
+        assert!(combined.remove(&in_plain.hash()).is_err_and(is_unsupported));
+        assert!(plain.contains(&in_plain.hash()).expect("Failed to check"));
+    }
+
+    #[test]
+    fn forwards_policy_and_secret_of_first_store() {
+        let policy = PutPolicy::new().with_store_direct(false);
+        let keyed = KeyedStore::new(
+            PolicyStore::new(InMemoryStore::default(), policy),
+            TenantSecret::derive(b"alice"),
+        );
+        let combined = CombinedStore::<_, true>::new([keyed.clone()]);
+
+        assert_eq!(combined.policy(), policy);
+        assert!(combined.secret().is_some());
+
+        let data = vec![3; 2000];
+        let hkey = combined.put(&data).expect("Failed to put");
+
+        assert_eq!(hkey, keyed.put(&data).expect("Failed to put"));
+        assert_ne!(
+            hkey,
+            InMemoryStore::default().put(&data).expect("Failed to put")
+        );
+        assert_eq!(hkey.resolve(&combined).expect("Failed to resolve"), data);
+
+        let empty = CombinedStore::<_, false>::new(Vec::<InMemoryStore>::new());
+
+        assert_eq!(empty.policy(), PutPolicy::new());
+        assert!(empty.secret().is_none());
+    }
+}
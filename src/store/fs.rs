@@ -0,0 +1,417 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ps_datachunk::{DataChunk, DataChunkError, OwnedDataChunk};
+use ps_hash::{Hash, HashError};
+use ps_promise::{Promise, PromiseRejection, TaskFailure};
+
+use crate::{
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove, HkeyError, Store,
+    StoreContains, StoreIter, StoreRemove,
+};
+
+/// Number of leading hash characters naming the directory a chunk is stored in.
+const SHARD_LENGTH: usize = 2;
+
+/// Distinguishes the temporary files of concurrent writes within a process.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A store keeping each chunk in a file named by its hash, under `root/<first two characters>/`.
+///
+/// Files are written under a temporary name and renamed into place, so a chunk is either
+/// complete or absent. Every chunk read is hashed, and a file whose contents do not match its
+/// name is reported as [`FsStoreError::Corrupted`].
+///
+/// The [`AsyncStore`] implementation performs the same blocking file system calls when its
+/// promises are polled.
+#[derive(Clone, Debug)]
+pub struct FsStore {
+    root: PathBuf,
+    fsync: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FsStoreError {
+    #[error("The file of chunk {0} does not match its hash.")]
+    Corrupted(Hash),
+    #[error(transparent)]
+    DataChunk(#[from] DataChunkError),
+    #[error(transparent)]
+    Hash(#[from] HashError),
+    #[error(transparent)]
+    Hkey(#[from] HkeyError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("The data with this hash was not found.")]
+    NotFound,
+    #[error("The Promise was consumed more than once.")]
+    PromiseConsumedAlready,
+    #[error(transparent)]
+    TaskFailed(#[from] TaskFailure),
+}
+
+impl PromiseRejection for FsStoreError {
+    fn already_consumed() -> Self {
+        Self::PromiseConsumedAlready
+    }
+
+    fn task_failed(failure: TaskFailure) -> Self {
+        Self::TaskFailed(failure)
+    }
+}
+
+impl FsStore {
+    /// Creates a store rooted at `root`, which is created on the first write.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            fsync: false,
+        }
+    }
+
+    /// Sets whether chunks and their directories are synced to disk before a write completes.
+    #[must_use]
+    pub const fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of the file holding the chunk with this hash.
+    #[must_use]
+    pub fn path_of(&self, hash: &Hash) -> PathBuf {
+        let name = hash.to_string();
+
+        self.root.join(&name[..SHARD_LENGTH]).join(name)
+    }
+
+    fn read(&self, hash: &Hash) -> Result<OwnedDataChunk, FsStoreError> {
+        let data = match fs::read(self.path_of(hash)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Err(FsStoreError::NotFound)?,
+            Err(err) => Err(err)?,
+        };
+
+        let chunk = OwnedDataChunk::from_data(data)?;
+
+        if chunk.hash_ref() != hash {
+            return Err(FsStoreError::Corrupted(*hash));
+        }
+
+        Ok(chunk)
+    }
+
+    fn write(&self, hash: &Hash, data: &[u8]) -> Result<(), FsStoreError> {
+        let path = self.path_of(hash);
+
+        // Chunks are immutable, so an existing file already holds this data.
+        if path.exists() {
+            return Ok(());
+        }
+
+        let directory = path.parent().unwrap_or(&self.root);
+
+        fs::create_dir_all(directory)?;
+
+        let temp_path = directory.join(format!(
+            ".{hash}.{}.{}.tmp",
+            process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = self.write_then_rename(&temp_path, &path, data);
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+
+        result
+    }
+
+    fn write_then_rename(
+        &self,
+        temp_path: &Path,
+        path: &Path,
+        data: &[u8],
+    ) -> Result<(), FsStoreError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_path)?;
+
+        file.write_all(data)?;
+
+        if self.fsync {
+            file.sync_all()?;
+        }
+
+        drop(file);
+
+        fs::rename(temp_path, path)?;
+
+        if self.fsync {
+            if let Some(directory) = path.parent() {
+                File::open(directory)?.sync_all()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn contains_hash(&self, hash: &Hash) -> bool {
+        self.path_of(hash).is_file()
+    }
+
+    fn list(&self) -> Result<Vec<Hash>, FsStoreError> {
+        let shards = match fs::read_dir(&self.root) {
+            Ok(shards) => shards,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => Err(err)?,
+        };
+
+        let mut hashes = Vec::new();
+
+        for shard in shards {
+            let shard = shard?;
+
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+
+            for entry in fs::read_dir(shard.path())? {
+                let name = entry?.file_name();
+
+                // Skips temporary files and anything else not named by a hash.
+                let Some(hash) = name.to_str().and_then(|name| {
+                    Hash::try_from(name)
+                        .ok()
+                        .filter(|hash| hash.to_string() == name)
+                }) else {
+                    continue;
+                };
+
+                hashes.push(hash);
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    fn delete(&self, hash: &Hash) -> Result<bool, FsStoreError> {
+        match fs::remove_file(self.path_of(hash)) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err)?,
+        }
+    }
+}
+
+impl Store for FsStore {
+    type Chunk<'c> = OwnedDataChunk;
+    type Error = FsStoreError;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        self.read(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.write(chunk.hash_ref(), chunk.data_ref())
+    }
+}
+
+impl StoreContains for FsStore {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        Ok(self.contains_hash(hash))
+    }
+}
+
+impl StoreIter for FsStore {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.list()
+    }
+}
+
+impl StoreRemove for FsStore {
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.delete(hash)
+    }
+}
+
+impl AsyncStore for FsStore {
+    type Chunk = OwnedDataChunk;
+    type Error = FsStoreError;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        let this = self.clone();
+        let hash = *hash;
+
+        Promise::lazy(async move { this.read(&hash) })
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        let this = self.clone();
+        let chunk = chunk.into_owned();
+
+        Promise::lazy(async move { this.write(chunk.hash_ref(), chunk.data_ref()) })
+    }
+}
+
+impl AsyncStoreContains for FsStore {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        let this = self.clone();
+        let hash = *hash;
+
+        Promise::lazy(async move { Ok(this.contains_hash(&hash)) })
+    }
+}
+
+impl AsyncStoreIter for FsStore {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        let this = self.clone();
+
+        Promise::lazy(async move { this.list() })
+    }
+}
+
+impl AsyncStoreRemove for FsStore {
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        let this = self.clone();
+        let hash = *hash;
+
+        Promise::lazy(async move { this.delete(&hash) })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{fs, path::PathBuf, process};
+
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{
+        AsyncStore, FsStore, FsStoreError, Hkey, Store, StoreContains, StoreIter, StoreRemove,
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// A directory under the system's temporary directory, removed when dropped.
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("ps-hkey-fs-{name}-{}", process::id()));
+
+            let _ = fs::remove_dir_all(&path);
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let root = TempRoot::new("roundtrip");
+        let store = FsStore::new(&root.0).with_fsync(true);
+        let data = sequential_bytes(100_000);
+
+        let hkey = Store::put(&store, &data).expect("Failed to put");
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+
+        let reopened = FsStore::new(&root.0);
+
+        assert_eq!(hkey.resolve(&reopened).expect("Failed to resolve"), data);
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        let root = TempRoot::new("roundtrip-async");
+        let store = FsStore::new(&root.0);
+        let data = sequential_bytes(100_000);
+
+        block_on(async {
+            let hkey = AsyncStore::put(&store, Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let resolved = hkey
+                .resolve_async(store.clone())
+                .await
+                .expect("Failed to resolve");
+
+            assert_eq!(resolved, data);
+        });
+    }
+
+    #[test]
+    fn corrupted_files_are_rejected() {
+        let root = TempRoot::new("corrupted");
+        let store = FsStore::new(&root.0);
+
+        let hkey = Store::put(&store, &sequential_bytes(1000)).expect("Failed to put");
+
+        let Hkey::Encrypted(hash, _) = hkey else {
+            panic!("Expected Hkey::Encrypted, got {hkey}");
+        };
+
+        let path = store.path_of(&hash);
+        let mut contents = fs::read(&path).expect("Failed to read");
+
+        contents[100] ^= 1;
+
+        fs::write(&path, contents).expect("Failed to write");
+
+        assert!(matches!(
+            Store::get(&store, &hash),
+            Err(FsStoreError::Corrupted(corrupted)) if corrupted == hash
+        ));
+    }
+
+    #[test]
+    fn contains_iter_and_remove() {
+        let root = TempRoot::new("extensions");
+        let store = FsStore::new(&root.0);
+
+        assert!(store.hashes().expect("Failed to enumerate").is_empty());
+
+        let hkey = Store::put(&store, &sequential_bytes(1000)).expect("Failed to put");
+
+        let Hkey::Encrypted(hash, _) = hkey else {
+            panic!("Expected Hkey::Encrypted, got {hkey}");
+        };
+
+        assert!(matches!(
+            Store::get(
+                &store,
+                &ps_hash::Hash::hash(b"missing").expect("Failed to hash")
+            ),
+            Err(FsStoreError::NotFound)
+        ));
+
+        assert!(StoreContains::contains(&store, &hash).expect("Failed to check"));
+        assert_eq!(store.hashes().expect("Failed to enumerate"), [hash]);
+
+        assert!(StoreRemove::remove(&store, &hash).expect("Failed to remove"));
+        assert!(!StoreRemove::remove(&store, &hash).expect("Failed to remove"));
+        assert!(!StoreContains::contains(&store, &hash).expect("Failed to check"));
+    }
+}
@@ -8,7 +8,7 @@ use ps_hash::{Hash, HashError};
 
 use crate::HkeyError;
 
-use super::Store;
+use super::{Store, StoreContains, StoreIter, StoreRemove};
 
 #[derive(Clone, Debug, Default)]
 pub struct InMemoryStore {
@@ -56,3 +56,114 @@ impl Store for InMemoryStore {
         Ok(())
     }
 }
+
+impl StoreContains for InMemoryStore {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        Ok(self.hashmap.lock()?.contains_key(hash))
+    }
+}
+
+impl StoreIter for InMemoryStore {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        Ok(self.hashmap.lock()?.keys().copied().collect())
+    }
+}
+
+impl StoreRemove for InMemoryStore {
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        Ok(self.hashmap.lock()?.remove(hash).is_some())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use ps_datachunk::{DataChunk, OwnedDataChunk};
+
+    use crate::{Hkey, InMemoryStore, PutPolicy, Store, StoreContains, StoreIter, StoreRemove};
+
+    #[test]
+    fn contains_iter_and_remove() {
+        let store = InMemoryStore::default();
+        let hkey = store.put(&[7; 1000]).expect("Failed to put");
+
+        let Hkey::Encrypted(hash, _) = hkey else {
+            panic!("Expected Hkey::Encrypted, got {hkey}");
+        };
+
+        assert!(store.contains(&hash).expect("Failed to check"));
+        assert_eq!(store.hashes().expect("Failed to enumerate"), [hash]);
+
+        assert!(store.remove(&hash).expect("Failed to remove"));
+        assert!(!store.remove(&hash).expect("Failed to remove"));
+
+        assert!(!store.contains(&hash).expect("Failed to check"));
+        assert!(store.hashes().expect("Failed to enumerate").is_empty());
+    }
+
+    #[test]
+    fn compressible_data_is_stored_compressed() {
+        let store = InMemoryStore::default();
+        let data: Vec<u8> = (0..10_000)
+            .flat_map(|i| {
+                format!("{{\"id\":{i},\"level\":\"info\",\"ms\":{}}}\n", i % 13).into_bytes()
+            })
+            .collect();
+
+        let hkey = store.put(&data).expect("Failed to put");
+        let stored: usize = store
+            .hashes()
+            .expect("Failed to enumerate")
+            .iter()
+            .map(|hash| store.get(hash).expect("Failed to get").data_ref().len())
+            .sum();
+
+        assert!(
+            stored * 4 < data.len(),
+            "stored {stored} of {} bytes",
+            data.len()
+        );
+
+        let range = 123_456..234_567;
+
+        assert_eq!(
+            hkey.resolve_slice(&store, range.clone())
+                .expect("Failed to slice")
+                .as_ref(),
+            &data[range]
+        );
+    }
+
+    #[test]
+    fn compressing_before_put_does_not_shrink_chunks() {
+        let data: Vec<u8> = (0..10_000)
+            .flat_map(|i| {
+                format!("{{\"id\":{i},\"level\":\"info\",\"ms\":{}}}\n", i % 13).into_bytes()
+            })
+            .collect();
+
+        let stored_size = |segment: &[u8]| {
+            OwnedDataChunk::from_data(segment.to_vec())
+                .expect("Failed to allocate")
+                .encrypt()
+                .expect("Failed to encrypt")
+                .data_ref()
+                .len()
+        };
+
+        let (once, twice) = data
+            .chunks(PutPolicy::default().segment_size())
+            .map(|segment| {
+                let compressed = ps_compress::compress(segment).expect("Failed to compress");
+
+                (stored_size(segment), stored_size(&compressed))
+            })
+            .fold((0, 0), |(once, twice), (a, b)| (once + a, twice + b));
+
+        // encrypt() compresses already, so a second pass only adds framing.
+        assert!(
+            twice >= once,
+            "stored {twice} bytes, {once} without recompressing"
+        );
+    }
+}
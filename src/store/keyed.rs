@@ -0,0 +1,372 @@
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305,
+};
+use ps_cypher::{Encrypted, EncryptionError};
+use ps_datachunk::{
+    BorrowedDataChunk, DataChunk, DataChunkError, EncryptedDataChunk, SerializedDataChunk,
+};
+use ps_hash::{Hash, DIGEST_SIZE, HASH_SIZE_BIN, PARITY_OFFSET, PARITY_SIZE};
+use ps_promise::Promise;
+use ps_util::subarray;
+
+use crate::{
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove, HkeyError, PutPolicy, Store,
+    StoreContains, StoreIter, StoreRemove,
+};
+
+/// The length of a [`TenantSecret`], in bytes.
+pub const TENANT_SECRET_SIZE: usize = 32;
+
+/// Parity of the ECC codeword holding a chunk's ciphertext, as written by `ps_cypher::encrypt`.
+const CODEWORD_PARITY: u8 = 12;
+
+/// Length of the ChaCha20 nonce, which is taken from the tail of the key's parity.
+const NONCE_SIZE: usize = 12;
+
+/// A secret mixed into the keys of everything a tenant stores.
+///
+/// Plain convergent encryption derives a chunk's key from its content, so anyone knowing some
+/// plaintext can compute its hash and confirm whether a store holds it. Under a secret, the
+/// key is a keyed BLAKE3 hash of the convergent key instead. Equal data still yields equal
+/// chunks for the same secret, so deduplication works within a tenant, but nothing can be
+/// confirmed without the secret.
+///
+/// The resulting keys are ordinary [`Hkey::Encrypted`](crate::Hkey::Encrypted) and
+/// [`Hkey::LongHkey`](crate::Hkey::LongHkey) keys, and resolve from any store without it.
+#[derive(Clone)]
+pub struct TenantSecret {
+    key: [u8; TENANT_SECRET_SIZE],
+}
+
+impl TenantSecret {
+    #[must_use]
+    pub const fn new(key: [u8; TENANT_SECRET_SIZE]) -> Self {
+        Self { key }
+    }
+
+    /// Derives a secret from arbitrary key material, such as a tenant's master key.
+    #[must_use]
+    pub fn derive(material: &[u8]) -> Self {
+        Self::new(blake3::derive_key("ps-hkey 2026 tenant secret", material))
+    }
+
+    /// Encrypts `data` into a chunk whose key depends on both `data` and this secret.
+    ///
+    /// The chunk has the layout `ps_cypher::encrypt` produces, so it decrypts as usual.
+    pub fn encrypt(&self, data: &[u8]) -> Result<EncryptedDataChunk, HkeyError> {
+        let serialized = SerializedDataChunk::from_data(data)?;
+        let plaintext = serialized.serialized_bytes();
+        let key = self.derive_key(&ps_hash::hash(plaintext)?)?;
+
+        let compressed = ps_compress::compress(plaintext).map_err(encryption_error)?;
+        let nonce: [u8; NONCE_SIZE] = *subarray(key.parity(), PARITY_SIZE - NONCE_SIZE);
+
+        let ciphertext = ChaCha20Poly1305::new(&(*key.digest()).into())
+            .encrypt(&nonce.into(), compressed.as_ref())
+            .map_err(|_| encryption_error(EncryptionError::ChaCha))?;
+
+        let bytes = ps_ecc::encode(&ciphertext, CODEWORD_PARITY).map_err(encryption_error)?;
+        let hash = Hash::hash(&bytes)?;
+
+        Ok(Encrypted { bytes, hash, key }.into())
+    }
+
+    /// Replaces the digest of a convergent key by its keyed hash, keeping its length, and
+    /// regenerates its parity.
+    fn derive_key(&self, convergent: &Hash) -> Result<Hash, HkeyError> {
+        let mut inner = [0u8; HASH_SIZE_BIN];
+
+        inner[..PARITY_OFFSET].copy_from_slice(&convergent.compact()[..PARITY_OFFSET]);
+        inner[..DIGEST_SIZE]
+            .copy_from_slice(blake3::keyed_hash(&self.key, convergent.digest()).as_bytes());
+
+        let parity = ps_hash::RS
+            .generate_parity(&inner[..PARITY_OFFSET])
+            .map_err(ps_hash::HashError::from)?;
+
+        inner[PARITY_OFFSET..].copy_from_slice(&parity);
+
+        Ok(Hash::validate(inner)?)
+    }
+}
+
+impl From<[u8; TENANT_SECRET_SIZE]> for TenantSecret {
+    fn from(key: [u8; TENANT_SECRET_SIZE]) -> Self {
+        Self::new(key)
+    }
+}
+
+impl std::fmt::Debug for TenantSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantSecret")
+            .field("key", &"<REDACTED>")
+            .finish()
+    }
+}
+
+/// Converts a failure of any encryption step into the error `DataChunk::encrypt` would return.
+fn encryption_error(err: impl Into<EncryptionError>) -> HkeyError {
+    DataChunkError::from(err.into()).into()
+}
+
+/// Encrypts `data` under `secret`, or convergently without one.
+pub(crate) fn encrypt(
+    data: &[u8],
+    secret: Option<&TenantSecret>,
+) -> Result<EncryptedDataChunk, HkeyError> {
+    match secret {
+        Some(secret) => secret.encrypt(data),
+        None => Ok(BorrowedDataChunk::from_data(data)?.encrypt()?),
+    }
+}
+
+/// A wrapper encrypting everything put into the inner store under a [`TenantSecret`], which
+/// it returns from [`Store::secret`] and [`AsyncStore::secret`].
+///
+/// Pre-encrypted data is encrypted again rather than stored as
+/// [`Hkey::Direct`](crate::Hkey::Direct), since a verbatim chunk could be confirmed.
+#[derive(Clone, Debug)]
+pub struct KeyedStore<S> {
+    inner: S,
+    secret: TenantSecret,
+}
+
+impl<S> KeyedStore<S> {
+    pub const fn new(inner: S, secret: TenantSecret) -> Self {
+        Self { inner, secret }
+    }
+
+    pub const fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Store> Store for KeyedStore<S> {
+    type Chunk<'c>
+        = S::Chunk<'c>
+    where
+        Self: 'c;
+    type Error = S::Error;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        self.inner.get(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.inner.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        Some(self.secret.clone())
+    }
+}
+
+impl<S: StoreContains> StoreContains for KeyedStore<S> {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: StoreIter> StoreIter for KeyedStore<S> {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: StoreRemove> StoreRemove for KeyedStore<S> {
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+impl<S: AsyncStore> AsyncStore for KeyedStore<S> {
+    type Chunk = S::Chunk;
+    type Error = S::Error;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        self.inner.get(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.inner.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        Some(self.secret.clone())
+    }
+}
+
+impl<S: AsyncStoreContains> AsyncStoreContains for KeyedStore<S> {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: AsyncStoreIter> AsyncStoreIter for KeyedStore<S> {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: AsyncStoreRemove> AsyncStoreRemove for KeyedStore<S> {
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::{BorrowedDataChunk, Bytes, DataChunk, SerializedDataChunk};
+
+    use super::{KeyedStore, TenantSecret};
+    use crate::{
+        AsyncStore, Hkey, InMemoryAsyncStore, InMemoryStore, Store, StoreContains, StoreIter,
+    };
+
+    fn pseudorandom_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    fn tenant(name: &str) -> KeyedStore<InMemoryStore> {
+        KeyedStore::new(
+            InMemoryStore::default(),
+            TenantSecret::derive(name.as_bytes()),
+        )
+    }
+
+    #[test]
+    fn keyed_chunks_decrypt_with_ps_cypher() {
+        let secret = TenantSecret::derive(b"alice");
+
+        for len in [0, 1, 1000, 4096] {
+            let data = pseudorandom_bytes(len, 11);
+            let encrypted = secret.encrypt(&data).expect("Failed to encrypt");
+            let plaintext = ps_cypher::decrypt(encrypted.data_ref(), &encrypted.key())
+                .expect("Failed to decrypt");
+            let serialized = SerializedDataChunk::from_data(&data).expect("Failed to serialize");
+
+            assert_eq!(&plaintext[..], serialized.serialized_bytes());
+        }
+    }
+
+    #[test]
+    fn keyed_keys_resolve_without_secret() {
+        let store = tenant("alice");
+
+        for len in [1000, 200_000] {
+            let data = pseudorandom_bytes(len, 3);
+            let hkey = store.put(&data).expect("Failed to put");
+
+            assert!(matches!(hkey, Hkey::Encrypted(..) | Hkey::LongHkey(_)));
+            assert_eq!(
+                hkey.resolve(store.inner()).expect("Failed to resolve"),
+                data
+            );
+        }
+
+        let list = Hkey::List(vec![store.put(&[7; 1000]).expect("Failed to put"); 3].into());
+        let list_ref = list
+            .shrink_or_not(&store)
+            .expect("Failed to shrink")
+            .expect("List was not stored");
+
+        assert_eq!(
+            list_ref.resolve(store.inner()).expect("Failed to resolve"),
+            [7; 3000].as_slice()
+        );
+        assert!(!format!("{:?}", TenantSecret::new([42; 32])).contains("42"));
+    }
+
+    #[test]
+    fn deduplicates_within_tenant_only() {
+        let data = pseudorandom_bytes(100_000, 5);
+        let alice = tenant("alice");
+
+        let first = alice.put(&data).expect("Failed to put");
+        let count = alice.hashes().expect("Failed to list").len();
+        let second = alice.put(&data).expect("Failed to put");
+
+        assert_eq!(first, second);
+        assert_eq!(alice.hashes().expect("Failed to list").len(), count);
+
+        let bob = tenant("bob").put(&data).expect("Failed to put");
+        let plain = InMemoryStore::default();
+        let convergent = plain.put(&data).expect("Failed to put");
+
+        assert_ne!(first.to_string(), bob.to_string());
+        assert_ne!(first.to_string(), convergent.to_string());
+
+        for hash in plain.hashes().expect("Failed to list") {
+            assert!(!alice.contains(&hash).expect("Failed to check"));
+        }
+    }
+
+    #[test]
+    fn encrypts_pre_encrypted_data() {
+        let encrypted = BorrowedDataChunk::from_data(&pseudorandom_bytes(1000, 7))
+            .expect("Failed to borrow")
+            .encrypt()
+            .expect("Failed to encrypt");
+        let store = tenant("alice");
+
+        let hkey = store.put(encrypted.data_ref()).expect("Failed to put");
+
+        assert!(matches!(hkey, Hkey::Encrypted(..)));
+        assert!(!store.contains(&encrypted.hash()).expect("Failed to check"));
+        assert_eq!(
+            hkey.resolve(store.inner()).expect("Failed to resolve"),
+            encrypted.data_ref()
+        );
+    }
+
+    #[test]
+    fn async_matches_sync() {
+        let data = pseudorandom_bytes(200_000, 11);
+        let sync = tenant("alice").put(&data).expect("Failed to put");
+        let store = KeyedStore::new(
+            InMemoryAsyncStore::default(),
+            TenantSecret::derive(b"alice"),
+        );
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            assert_eq!(hkey.to_string(), sync.to_string());
+            assert_eq!(
+                hkey.resolve_async(store.into_inner())
+                    .await
+                    .expect("Failed to resolve")
+                    .as_ref(),
+                data.as_slice()
+            );
+        });
+    }
+}
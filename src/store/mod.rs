@@ -1,15 +1,23 @@
+pub mod caching;
 pub mod combined;
+pub mod fs;
 pub mod in_memory;
+pub mod keyed;
+pub mod pack;
+pub mod policy;
+pub mod verifying;
 
 use ps_cypher::validate_ecc;
 use ps_datachunk::{BorrowedDataChunk, DataChunk, DataChunkError};
 use ps_hash::Hash;
 
 use crate::{
-    constants::{MAX_DECRYPTED_SIZE, MAX_ENCRYPTED_SIZE, MAX_SIZE_RAW},
-    Hkey, HkeyError, LongHkeyExpanded,
+    constants::{MAX_DECRYPTED_SIZE, MAX_ENCRYPTED_SIZE},
+    Hkey, HkeyError, LongHkeyExpanded, PutPolicy, TenantSecret,
 };
 
+use self::keyed::encrypt;
+
 pub trait Store
 where
     Self: Sized + Sync,
@@ -24,14 +32,44 @@ where
 
     fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error>;
 
+    /// The policy [`Store::put`] follows, which is also honoured by
+    /// [`LongHkeyExpanded::from_blob`], [`LongHkeyExpanded::update`] and
+    /// [`Hkey::shrink_or_not`].
+    fn policy(&self) -> PutPolicy {
+        PutPolicy::default()
+    }
+
+    /// The secret everything put into this store is encrypted under, see [`TenantSecret`].
+    fn secret(&self) -> Option<TenantSecret> {
+        None
+    }
+
+    /// Stores `data` following [`Store::policy`], returning the [`Hkey`] referencing it.
     fn put(&self, data: &[u8]) -> Result<Hkey, Self::Error> {
-        if data.len() <= MAX_SIZE_RAW {
+        self.put_with(data, self.policy())
+    }
+
+    /// Stores `data`, returning the [`Hkey`] referencing it.
+    ///
+    /// Data up to the policy's inline threshold is inlined, and pre-encrypted chunks are stored
+    /// as [`Hkey::Direct`] unless the policy forbids it. Other chunks are compressed with zstd
+    /// before being encrypted, as part of [`DataChunk::encrypt`], so compressible data takes
+    /// correspondingly less space. Longer data is split by [`LongHkeyExpanded::from_blob_with`],
+    /// whose part ranges are in uncompressed coordinates.
+    fn put_with(&self, data: &[u8], policy: PutPolicy) -> Result<Hkey, Self::Error> {
+        if data.len() <= policy.inline_threshold() {
             return Hkey::from_raw(data)
                 .map_err(HkeyError::Construction)
                 .map_err(Into::into);
         }
 
-        if data.len() <= MAX_ENCRYPTED_SIZE && validate_ecc(data) {
+        let secret = self.secret();
+
+        if policy.store_direct()
+            && secret.is_none()
+            && data.len() <= MAX_ENCRYPTED_SIZE
+            && validate_ecc(data)
+        {
             let chunk = BorrowedDataChunk::from_data(data)?;
             let hash = chunk.hash();
 
@@ -39,15 +77,32 @@ where
 
             Ok(Hkey::Direct(hash))
         } else if data.len() <= MAX_DECRYPTED_SIZE {
-            let chunk = BorrowedDataChunk::from_data(data)?;
-            let encrypted = chunk.encrypt()?;
+            let encrypted = encrypt(data, secret.as_ref())?;
             let hkey = Hkey::Encrypted(encrypted.hash(), encrypted.key());
 
             self.put_encrypted(encrypted)?;
 
             Ok(hkey)
         } else {
-            LongHkeyExpanded::from_blob(self, data)?.shrink(self)
+            LongHkeyExpanded::from_blob_with(self, data, policy)?.shrink(self)
         }
     }
 }
+
+/// A [`Store`] that can tell whether it holds a chunk without fetching it.
+pub trait StoreContains: Store {
+    /// Returns whether a chunk with this hash is in this store.
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error>;
+}
+
+/// A [`Store`] that can enumerate the chunks it holds.
+pub trait StoreIter: Store {
+    /// Returns the hashes of all chunks in this store.
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error>;
+}
+
+/// A [`Store`] that chunks can be deleted from.
+pub trait StoreRemove: Store {
+    /// Removes the chunk with this hash, returning whether it was present.
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error>;
+}
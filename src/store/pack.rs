@@ -0,0 +1,964 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use ps_datachunk::{DataChunk, DataChunkError, OwnedDataChunk};
+use ps_hash::{Hash, HashError};
+use ps_promise::{Promise, PromiseRejection, TaskFailure};
+
+use crate::{
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, HkeyError, Store, StoreContains, StoreIter,
+    HASH_SIZE,
+};
+
+/// A record in a pack file is the chunk's hash, its length as a little-endian `u32`, and its data.
+const RECORD_HEADER_SIZE: usize = HASH_SIZE + 4;
+
+/// An index entry is the chunk's hash, followed by the little-endian pack number, data offset
+/// and data length.
+const INDEX_ENTRY_SIZE: usize = HASH_SIZE + 4 + 8 + 4;
+
+const INDEX_FILE_NAME: &str = "index";
+const INDEX_TEMP_FILE_NAME: &str = "index.tmp";
+const PACK_EXTENSION: &str = "pack";
+
+/// Where a chunk's data lies within the packs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Location {
+    pack: u32,
+    offset: u64,
+    length: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PackStoreOptions {
+    /// A new pack is started once the current one reaches this many bytes.
+    pub max_pack_size: u64,
+    /// Whether packs and the index are synced to disk before a write completes.
+    pub fsync: bool,
+}
+
+impl Default for PackStoreOptions {
+    fn default() -> Self {
+        Self {
+            max_pack_size: 1 << 30,
+            fsync: false,
+        }
+    }
+}
+
+/// A store appending chunks to large pack files, with an append-only index mapping each hash
+/// to its position.
+///
+/// A chunk is appended to its pack before its index entry is written. When opened, the part of
+/// the last pack not covered by the index is rescanned, so chunks written before a crash are
+/// recovered, and an incomplete record at its end is truncated. Index entries pointing past the
+/// end of their pack, whose data never reached the disk, are dropped.
+///
+/// Chunks are never deleted individually; [`PackStore::compact`] rewrites the packs keeping only
+/// a given set of live chunks. As with [`FsStore`](crate::FsStore), the [`AsyncStore`]
+/// implementation performs blocking file system calls when its promises are polled.
+#[derive(Clone, Debug)]
+pub struct PackStore {
+    state: Arc<RwLock<PackState>>,
+}
+
+#[derive(Debug)]
+struct PackState {
+    root: PathBuf,
+    options: PackStoreOptions,
+    index: File,
+    pack: ActivePack,
+    locations: HashMap<Hash, Location>,
+}
+
+/// The pack new records are appended to.
+#[derive(Debug)]
+struct ActivePack {
+    file: File,
+    id: u32,
+    length: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PackStoreError {
+    #[error("Chunks of {0} bytes are too large for a pack.")]
+    ChunkTooLarge(usize),
+    #[error("The packed data of chunk {0} does not match its hash.")]
+    Corrupted(Hash),
+    #[error(transparent)]
+    DataChunk(#[from] DataChunkError),
+    #[error(transparent)]
+    Hash(#[from] HashError),
+    #[error(transparent)]
+    Hkey(#[from] HkeyError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("The data with this hash was not found.")]
+    NotFound,
+    #[error("The Promise was consumed more than once.")]
+    PromiseConsumedAlready,
+    #[error(transparent)]
+    TaskFailed(#[from] TaskFailure),
+}
+
+impl PromiseRejection for PackStoreError {
+    fn already_consumed() -> Self {
+        Self::PromiseConsumedAlready
+    }
+
+    fn task_failed(failure: TaskFailure) -> Self {
+        Self::TaskFailed(failure)
+    }
+}
+
+fn pack_path(root: &Path, id: u32) -> PathBuf {
+    root.join(format!("{id:08}.{PACK_EXTENSION}"))
+}
+
+/// Returns the numbers of the packs in `root`, in ascending order.
+fn pack_ids(root: &Path) -> Result<Vec<u32>, PackStoreError> {
+    let mut ids = Vec::new();
+
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some(PACK_EXTENSION) {
+            continue;
+        }
+
+        if let Some(id) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            ids.push(id);
+        }
+    }
+
+    ids.sort_unstable();
+
+    Ok(ids)
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+}
+
+/// Syncs the entries of the directory at `root`, so that files created or renamed in it survive
+/// a crash.
+fn sync_dir(root: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(root)?.sync_all()?;
+
+    #[cfg(not(unix))]
+    let _ = root;
+
+    Ok(())
+}
+
+fn encode_index_entry(hash: &Hash, location: Location) -> [u8; INDEX_ENTRY_SIZE] {
+    let mut entry = [0; INDEX_ENTRY_SIZE];
+
+    entry[..HASH_SIZE].copy_from_slice(hash.to_string().as_bytes());
+    entry[HASH_SIZE..HASH_SIZE + 4].copy_from_slice(&location.pack.to_le_bytes());
+    entry[HASH_SIZE + 4..HASH_SIZE + 12].copy_from_slice(&location.offset.to_le_bytes());
+    entry[HASH_SIZE + 12..].copy_from_slice(&location.length.to_le_bytes());
+
+    entry
+}
+
+fn decode_index_entry(entry: &[u8]) -> Option<(Hash, Location)> {
+    let hash = Hash::try_from(&entry[..HASH_SIZE]).ok()?;
+    let pack = u32::from_le_bytes(entry[HASH_SIZE..HASH_SIZE + 4].try_into().ok()?);
+    let offset = u64::from_le_bytes(entry[HASH_SIZE + 4..HASH_SIZE + 12].try_into().ok()?);
+    let length = u32::from_le_bytes(entry[HASH_SIZE + 12..].try_into().ok()?);
+
+    Some((
+        hash,
+        Location {
+            pack,
+            offset,
+            length,
+        },
+    ))
+}
+
+fn encode_record_header(hash: &Hash, length: u32) -> [u8; RECORD_HEADER_SIZE] {
+    let mut header = [0; RECORD_HEADER_SIZE];
+
+    header[..HASH_SIZE].copy_from_slice(hash.to_string().as_bytes());
+    header[HASH_SIZE..].copy_from_slice(&length.to_le_bytes());
+
+    header
+}
+
+/// Reads the length of data at `offset`, or `None` if the file ends first.
+fn read_at(file: &mut File, offset: u64, length: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut data = vec![0; length];
+
+    file.seek(SeekFrom::Start(offset))?;
+
+    match file.read_exact(&mut data) {
+        Ok(()) => Ok(Some(data)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+impl PackStore {
+    /// Opens the store in `root` with the default options, creating it if necessary.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, PackStoreError> {
+        Self::open_with_options(root, PackStoreOptions::default())
+    }
+
+    /// Opens the store in `root`, creating it if necessary, and recovers any chunks appended
+    /// to the last pack but missing from the index.
+    pub fn open_with_options(
+        root: impl Into<PathBuf>,
+        options: PackStoreOptions,
+    ) -> Result<Self, PackStoreError> {
+        let root = root.into();
+
+        fs::create_dir_all(&root)?;
+
+        let mut index = open_append(&root.join(INDEX_FILE_NAME))?;
+        let mut index_bytes = Vec::new();
+
+        index.read_to_end(&mut index_bytes)?;
+
+        let mut locations = HashMap::new();
+
+        // An incomplete trailing entry is the remnant of an interrupted write.
+        for entry in index_bytes.chunks_exact(INDEX_ENTRY_SIZE) {
+            if let Some((hash, location)) = decode_index_entry(entry) {
+                locations.insert(hash, location);
+            }
+        }
+
+        let complete_length = index_bytes.len() - index_bytes.len() % INDEX_ENTRY_SIZE;
+
+        if complete_length != index_bytes.len() {
+            index.set_len(complete_length as u64)?;
+        }
+
+        let pack_id = pack_ids(&root)?.last().copied().unwrap_or_default();
+        let pack = ActivePack {
+            file: open_append(&pack_path(&root, pack_id))?,
+            id: pack_id,
+            length: 0,
+        };
+
+        let mut state = PackState {
+            root,
+            options,
+            index,
+            pack,
+            locations,
+        };
+
+        state.recover()?;
+
+        Ok(Self {
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// Returns the number of chunks in this store.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.state.read().locations.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Rewrites the packs to hold only the chunks in `live`, returning how many were dropped.
+    ///
+    /// The live chunks are copied into new packs, which are synced to disk before a new index
+    /// is swapped in. The old packs are deleted only once the swap is synced too, so an
+    /// interruption leaves either the old or the new state intact. If compaction fails, the
+    /// store keeps serving the old packs.
+    pub fn compact(&self, live: &HashSet<Hash>) -> Result<usize, PackStoreError> {
+        self.state.write().compact(live)
+    }
+
+    fn read(&self, hash: &Hash) -> Result<OwnedDataChunk, PackStoreError> {
+        let state = self.state.read();
+        let location = *state.locations.get(hash).ok_or(PackStoreError::NotFound)?;
+        let data = state
+            .read_location(location)?
+            .ok_or(PackStoreError::Corrupted(*hash))?;
+
+        drop(state);
+
+        let chunk = OwnedDataChunk::from_data(data)?;
+
+        if chunk.hash_ref() != hash {
+            return Err(PackStoreError::Corrupted(*hash));
+        }
+
+        Ok(chunk)
+    }
+
+    fn write(&self, hash: &Hash, data: &[u8]) -> Result<(), PackStoreError> {
+        let mut state = self.state.write();
+
+        if state.locations.contains_key(hash) {
+            return Ok(());
+        }
+
+        state.append(hash, data)
+    }
+
+    fn contains_hash(&self, hash: &Hash) -> bool {
+        self.state.read().locations.contains_key(hash)
+    }
+
+    fn list(&self) -> Vec<Hash> {
+        self.state.read().locations.keys().copied().collect()
+    }
+}
+
+impl PackState {
+    fn read_location(&self, location: Location) -> Result<Option<Vec<u8>>, PackStoreError> {
+        let mut file = File::open(pack_path(&self.root, location.pack))?;
+
+        Ok(read_at(
+            &mut file,
+            location.offset,
+            location.length as usize,
+        )?)
+    }
+
+    /// Drops the index entries pointing past the end of their pack, indexes the records of the
+    /// current pack which follow the last indexed one, and truncates the pack after the last
+    /// complete record.
+    fn recover(&mut self) -> Result<(), PackStoreError> {
+        let mut pack_lengths = HashMap::new();
+
+        for id in pack_ids(&self.root)? {
+            pack_lengths.insert(id, fs::metadata(pack_path(&self.root, id))?.len());
+        }
+
+        // Without fsync, the index may reach the disk while the tail of a pack does not.
+        let indexed = self.locations.len();
+
+        self.locations.retain(|_, location| {
+            pack_lengths
+                .get(&location.pack)
+                .is_some_and(|&length| location.offset + u64::from(location.length) <= length)
+        });
+
+        if self.locations.len() < indexed {
+            let mut entries: Vec<_> = self.locations.iter().collect();
+
+            entries.sort_unstable_by_key(|(_, location)| (location.pack, location.offset));
+
+            let index_bytes: Vec<u8> = entries
+                .into_iter()
+                .flat_map(|(hash, location)| encode_index_entry(hash, *location))
+                .collect();
+
+            self.replace_index(&index_bytes)?;
+
+            sync_dir(&self.root)?;
+        }
+
+        let mut position = self
+            .locations
+            .values()
+            .filter(|location| location.pack == self.pack.id)
+            .map(|location| location.offset + u64::from(location.length))
+            .max()
+            .unwrap_or_default();
+
+        let mut file = File::open(pack_path(&self.root, self.pack.id))?;
+        let file_length = file.metadata()?.len();
+
+        while position < file_length {
+            let Some(header) = read_at(&mut file, position, RECORD_HEADER_SIZE)? else {
+                break;
+            };
+
+            let Ok(hash) = Hash::try_from(&header[..HASH_SIZE]) else {
+                break;
+            };
+
+            let length = u32::from_le_bytes(
+                header[HASH_SIZE..]
+                    .try_into()
+                    .map_err(|_| HkeyError::Format)?,
+            );
+
+            let offset = position + RECORD_HEADER_SIZE as u64;
+
+            let Some(data) = read_at(&mut file, offset, length as usize)? else {
+                break;
+            };
+
+            if ps_hash::hash(&data)? != hash {
+                break;
+            }
+
+            let location = Location {
+                pack: self.pack.id,
+                offset,
+                length,
+            };
+
+            self.index.write_all(&encode_index_entry(&hash, location))?;
+            self.locations.insert(hash, location);
+
+            position = offset + u64::from(length);
+        }
+
+        if position < file_length {
+            self.pack.file.set_len(position)?;
+        }
+
+        self.pack.length = position;
+
+        if self.options.fsync {
+            self.pack.file.sync_all()?;
+            self.index.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    fn append(&mut self, hash: &Hash, data: &[u8]) -> Result<(), PackStoreError> {
+        self.pack
+            .roll_over(&self.root, self.options.max_pack_size, false)?;
+
+        let location = self.pack.append_record(hash, data)?;
+
+        if self.options.fsync {
+            self.pack.file.sync_data()?;
+        }
+
+        self.index.write_all(&encode_index_entry(hash, location))?;
+
+        if self.options.fsync {
+            self.index.sync_data()?;
+        }
+
+        self.locations.insert(*hash, location);
+
+        Ok(())
+    }
+
+    fn compact(&mut self, live: &HashSet<Hash>) -> Result<usize, PackStoreError> {
+        let old_ids = pack_ids(&self.root)?;
+
+        let mut kept: Vec<(Hash, Location)> = self
+            .locations
+            .iter()
+            .filter(|(hash, _)| live.contains(hash))
+            .map(|(hash, location)| (*hash, *location))
+            .collect();
+
+        // Copying in pack order keeps the reads sequential.
+        kept.sort_unstable_by_key(|(_, location)| (location.pack, location.offset));
+
+        let dropped = self.locations.len() - kept.len();
+
+        let first_id = self.pack.id + 1;
+        let mut pack = ActivePack::create(&self.root, first_id)?;
+        let mut locations = HashMap::with_capacity(kept.len());
+        let mut index_bytes = Vec::with_capacity(kept.len() * INDEX_ENTRY_SIZE);
+
+        for (hash, location) in kept {
+            let data = self
+                .read_location(location)?
+                .ok_or(PackStoreError::Corrupted(hash))?;
+
+            pack.roll_over(&self.root, self.options.max_pack_size, true)?;
+
+            let location = pack.append_record(&hash, &data)?;
+
+            index_bytes.extend_from_slice(&encode_index_entry(&hash, location));
+            locations.insert(hash, location);
+        }
+
+        pack.file.sync_all()?;
+
+        self.replace_index(&index_bytes)?;
+
+        self.pack = pack;
+        self.locations = locations;
+
+        sync_dir(&self.root)?;
+
+        // Packs left behind by an earlier failed compaction may have been reused.
+        let new_ids = first_id..=self.pack.id;
+
+        for id in old_ids.into_iter().filter(|id| !new_ids.contains(id)) {
+            fs::remove_file(pack_path(&self.root, id))?;
+        }
+
+        Ok(dropped)
+    }
+
+    /// Atomically replaces the index with `index_bytes`. The swap is durable once the root
+    /// directory is synced.
+    fn replace_index(&mut self, index_bytes: &[u8]) -> Result<(), PackStoreError> {
+        let temp_path = self.root.join(INDEX_TEMP_FILE_NAME);
+        let mut temp = File::create(&temp_path)?;
+
+        temp.write_all(index_bytes)?;
+        temp.sync_all()?;
+
+        drop(temp);
+
+        fs::rename(&temp_path, self.root.join(INDEX_FILE_NAME))?;
+
+        self.index = open_append(&self.root.join(INDEX_FILE_NAME))?;
+
+        Ok(())
+    }
+}
+
+impl ActivePack {
+    /// Creates pack `id` in `root`, discarding the remnants of an earlier failed compaction.
+    fn create(root: &Path, id: u32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(pack_path(root, id))?;
+
+        Ok(Self {
+            file,
+            id,
+            length: 0,
+        })
+    }
+
+    /// Starts a new pack in `root` if this one holds `max_size` bytes or more, syncing it to
+    /// disk first if `sync` is set.
+    fn roll_over(&mut self, root: &Path, max_size: u64, sync: bool) -> io::Result<()> {
+        if self.length >= max_size {
+            if sync {
+                self.file.sync_all()?;
+            }
+
+            *self = Self::create(root, self.id + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a record to this pack, and returns where its data lies.
+    fn append_record(&mut self, hash: &Hash, data: &[u8]) -> Result<Location, PackStoreError> {
+        let length =
+            u32::try_from(data.len()).map_err(|_| PackStoreError::ChunkTooLarge(data.len()))?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + data.len());
+
+        record.extend_from_slice(&encode_record_header(hash, length));
+        record.extend_from_slice(data);
+
+        if let Err(err) = self.file.write_all(&record) {
+            // A partial write leaves a torn record behind, which would shift every later one.
+            if self.file.set_len(self.length).is_err() {
+                self.length = self.file.metadata()?.len();
+            }
+
+            return Err(err.into());
+        }
+
+        let location = Location {
+            pack: self.id,
+            offset: self.length + RECORD_HEADER_SIZE as u64,
+            length,
+        };
+
+        self.length += record.len() as u64;
+
+        Ok(location)
+    }
+}
+
+impl Store for PackStore {
+    type Chunk<'c> = OwnedDataChunk;
+    type Error = PackStoreError;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        self.read(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.write(chunk.hash_ref(), chunk.data_ref())
+    }
+}
+
+impl StoreContains for PackStore {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        Ok(self.contains_hash(hash))
+    }
+}
+
+impl StoreIter for PackStore {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        Ok(self.list())
+    }
+}
+
+impl AsyncStore for PackStore {
+    type Chunk = OwnedDataChunk;
+    type Error = PackStoreError;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        let this = self.clone();
+        let hash = *hash;
+
+        Promise::lazy(async move { this.read(&hash) })
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        let this = self.clone();
+        let chunk = chunk.into_owned();
+
+        Promise::lazy(async move { this.write(chunk.hash_ref(), chunk.data_ref()) })
+    }
+}
+
+impl AsyncStoreContains for PackStore {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        Promise::resolve(self.contains_hash(hash))
+    }
+}
+
+impl AsyncStoreIter for PackStore {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        Promise::resolve(self.list())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        fs::{self, File, OpenOptions},
+        io::Write,
+        path::PathBuf,
+        process,
+    };
+
+    use futures::executor::block_on;
+    use ps_datachunk::Bytes;
+
+    use crate::{collect_garbage, AsyncStore, PackStore, PackStoreOptions, Store, StoreIter};
+
+    use super::{open_append, pack_ids, pack_path, INDEX_ENTRY_SIZE, INDEX_FILE_NAME};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i + seed) % 251) as u8).collect()
+    }
+
+    /// A directory under the system's temporary directory, removed when dropped.
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("ps-hkey-pack-{name}-{}", process::id()));
+
+            let _ = fs::remove_dir_all(&path);
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn small_packs() -> PackStoreOptions {
+        PackStoreOptions {
+            max_pack_size: 20_000,
+            fsync: false,
+        }
+    }
+
+    #[test]
+    fn roundtrip_and_reopen() {
+        let root = TempRoot::new("roundtrip");
+        let data = sequential_bytes(200_000, 0);
+
+        let hkey = {
+            let store =
+                PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+            let hkey = Store::put(&store, &data).expect("Failed to put");
+
+            assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+
+            hkey
+        };
+
+        assert!(pack_ids(&root.0).expect("Failed to list packs").len() > 1);
+
+        let store = PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        let root = TempRoot::new("roundtrip-async");
+        let store = PackStore::open(&root.0).expect("Failed to open");
+        let data = sequential_bytes(100_000, 0);
+
+        block_on(async {
+            let hkey = AsyncStore::put(&store, Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            let resolved = hkey
+                .resolve_async(store.clone())
+                .await
+                .expect("Failed to resolve");
+
+            assert_eq!(resolved, data);
+        });
+    }
+
+    #[test]
+    fn recovers_unindexed_records_and_truncates_torn_tail() {
+        let root = TempRoot::new("recovery");
+        let data = sequential_bytes(3000, 0);
+
+        let (hkey, count) = {
+            let store = PackStore::open(&root.0).expect("Failed to open");
+            let hkey = Store::put(&store, &data).expect("Failed to put");
+
+            Store::put(&store, &sequential_bytes(3000, 1)).expect("Failed to put");
+
+            (hkey, store.len())
+        };
+
+        // Loses both index entries, as if the process died before writing them, plus half of
+        // a third entry.
+        let index_path = root.0.join(INDEX_FILE_NAME);
+        let index_length = fs::metadata(&index_path).expect("Failed to stat").len();
+
+        OpenOptions::new()
+            .write(true)
+            .open(&index_path)
+            .expect("Failed to open index")
+            .set_len(index_length - 2 * INDEX_ENTRY_SIZE as u64 + 30)
+            .expect("Failed to truncate");
+
+        // Leaves a torn record at the end of the pack.
+        let pack = pack_path(&root.0, 0);
+        let pack_length = fs::metadata(&pack).expect("Failed to stat").len();
+
+        OpenOptions::new()
+            .append(true)
+            .open(&pack)
+            .expect("Failed to open pack")
+            .write_all(&[1; 90])
+            .expect("Failed to write");
+
+        let store = PackStore::open(&root.0).expect("Failed to open");
+
+        assert_eq!(store.len(), count);
+        assert_eq!(
+            fs::metadata(&pack).expect("Failed to stat").len(),
+            pack_length
+        );
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+
+        let more = sequential_bytes(3000, 2);
+        let more_hkey = Store::put(&store, &more).expect("Failed to put");
+
+        drop(store);
+
+        let store = PackStore::open(&root.0).expect("Failed to open");
+
+        assert_eq!(more_hkey.resolve(&store).expect("Failed to resolve"), more);
+        assert_eq!(store.len(), count + 1);
+    }
+
+    #[test]
+    fn drops_index_entries_past_lost_tail() {
+        let root = TempRoot::new("lost-tail");
+        let data = sequential_bytes(3000, 0);
+
+        let (hkey, lost, count) = {
+            let store = PackStore::open(&root.0).expect("Failed to open");
+            let hkey = Store::put(&store, &data).expect("Failed to put");
+            let count = store.len();
+            let lost = Store::put(&store, &sequential_bytes(3000, 1)).expect("Failed to put");
+
+            (hkey, lost, count)
+        };
+
+        // Loses the end of the pack but keeps its index entry, as may happen without fsync.
+        let pack = pack_path(&root.0, 0);
+        let pack_length = fs::metadata(&pack).expect("Failed to stat").len();
+
+        OpenOptions::new()
+            .write(true)
+            .open(&pack)
+            .expect("Failed to open pack")
+            .set_len(pack_length - 100)
+            .expect("Failed to truncate");
+
+        let store = PackStore::open(&root.0).expect("Failed to open");
+
+        assert_eq!(store.len(), count);
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+        assert!(lost.resolve(&store).is_err());
+
+        let more = sequential_bytes(3000, 2);
+        let more_hkey = Store::put(&store, &more).expect("Failed to put");
+
+        assert_eq!(more_hkey.resolve(&store).expect("Failed to resolve"), more);
+
+        drop(store);
+
+        let store = PackStore::open(&root.0).expect("Failed to open");
+
+        assert_eq!(store.len(), count + 1);
+        assert_eq!(more_hkey.resolve(&store).expect("Failed to resolve"), more);
+        assert!(lost.resolve(&store).is_err());
+    }
+
+    #[test]
+    fn failed_append_keeps_offsets_in_step() {
+        let root = TempRoot::new("failed-append");
+        let store = PackStore::open(&root.0).expect("Failed to open");
+        let data = sequential_bytes(3000, 0);
+        let hkey = Store::put(&store, &data).expect("Failed to put");
+        let pack = pack_path(&root.0, 0);
+
+        // Leaves part of a record in the pack and fails the write, as a full disk might. The
+        // read-only handle cannot truncate it either.
+        OpenOptions::new()
+            .append(true)
+            .open(&pack)
+            .expect("Failed to open pack")
+            .write_all(&[1; 90])
+            .expect("Failed to write");
+
+        store.state.write().pack.file = File::open(&pack).expect("Failed to open pack");
+
+        assert!(Store::put(&store, &sequential_bytes(3000, 1)).is_err());
+
+        store.state.write().pack.file = open_append(&pack).expect("Failed to open pack");
+
+        let more = sequential_bytes(3000, 2);
+        let more_hkey = Store::put(&store, &more).expect("Failed to put");
+
+        assert_eq!(more_hkey.resolve(&store).expect("Failed to resolve"), more);
+
+        drop(store);
+
+        let store = PackStore::open(&root.0).expect("Failed to open");
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+        assert_eq!(more_hkey.resolve(&store).expect("Failed to resolve"), more);
+    }
+
+    #[test]
+    fn compaction_keeps_only_live_chunks() {
+        let root = TempRoot::new("compaction");
+        let store = PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+
+        let live_data = sequential_bytes(100_000, 0);
+        let live = Store::put(&store, &live_data).expect("Failed to put");
+        let dead = Store::put(&store, &sequential_bytes(100_000, 1)).expect("Failed to put");
+
+        let garbage = collect_garbage([&live], &store).expect("Failed to collect");
+        let all: HashSet<_> = store
+            .hashes()
+            .expect("Failed to enumerate")
+            .into_iter()
+            .collect();
+        let live_hashes: HashSet<_> = all.difference(&garbage).copied().collect();
+
+        let dropped = store.compact(&live_hashes).expect("Failed to compact");
+
+        assert_eq!(dropped, garbage.len());
+        assert_eq!(store.len(), live_hashes.len());
+        assert_eq!(live.resolve(&store).expect("Failed to resolve"), live_data);
+        assert!(dead.resolve(&store).is_err());
+
+        drop(store);
+
+        let store = PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+
+        assert_eq!(store.len(), live_hashes.len());
+        assert_eq!(live.resolve(&store).expect("Failed to resolve"), live_data);
+    }
+
+    #[test]
+    fn failed_compaction_keeps_the_old_state() {
+        let root = TempRoot::new("failed-compaction");
+        let store = PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+
+        Store::put(&store, &sequential_bytes(400_000, 0)).expect("Failed to put");
+
+        let count = store.len();
+        let last_pack = store.state.read().pack.id;
+        let (in_last, in_others): (Vec<_>, Vec<_>) = store
+            .state
+            .read()
+            .locations
+            .iter()
+            .map(|(hash, location)| (*hash, location.pack))
+            .partition(|(_, pack)| *pack == last_pack);
+
+        assert!(!in_last.is_empty() && !in_others.is_empty());
+
+        // Compaction copies the other packs first, then fails to read the last one.
+        fs::remove_file(pack_path(&root.0, last_pack)).expect("Failed to remove pack");
+
+        let all: HashSet<_> = store
+            .hashes()
+            .expect("Failed to enumerate")
+            .into_iter()
+            .collect();
+
+        assert!(store.compact(&all).is_err());
+        assert_eq!(store.len(), count);
+
+        for (hash, _) in &in_others {
+            Store::get(&store, hash).expect("Failed to get");
+        }
+
+        let readable: HashSet<_> = in_others.iter().map(|(hash, _)| *hash).collect();
+
+        assert_eq!(
+            store.compact(&readable).expect("Failed to compact"),
+            in_last.len()
+        );
+
+        drop(store);
+
+        let store = PackStore::open_with_options(&root.0, small_packs()).expect("Failed to open");
+
+        assert_eq!(store.len(), readable.len());
+
+        for hash in &readable {
+            Store::get(&store, hash).expect("Failed to get");
+        }
+    }
+}
@@ -0,0 +1,590 @@
+use ps_datachunk::DataChunk;
+use ps_hash::Hash;
+use ps_promise::Promise;
+
+use crate::{
+    long::{LHKEY_LEVEL_MAX_LENGTH, LHKEY_PART_COUNT_LOG2, LHKEY_SEGMENT_MAX_LENGTH},
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove, HkeyError, Store,
+    StoreContains, StoreIter, StoreRemove, TenantSecret, MAX_SIZE_RAW,
+};
+
+/// The smallest segment size a [`PutPolicy`] accepts.
+pub const MIN_SEGMENT_SIZE: usize = 64;
+
+// The default policy keeps the historic tree geometry.
+const _: () = assert!(PutPolicy::new().level_length() == LHKEY_LEVEL_MAX_LENGTH);
+
+/// Decides how [`Store::put`](crate::Store::put) and
+/// [`AsyncStore::put`](crate::AsyncStore::put) turn data into keys.
+///
+/// A store follows the policy returned by [`Store::policy`](crate::Store::policy), which a
+/// [`PolicyStore`](crate::PolicyStore) overrides, and [`Store::put_with`](crate::Store::put_with)
+/// takes one explicitly. The default policy is the historic behaviour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PutPolicy {
+    inline_threshold: usize,
+    store_direct: bool,
+    segment_size: usize,
+    inline_base64: bool,
+}
+
+impl PutPolicy {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            inline_threshold: MAX_SIZE_RAW,
+            store_direct: true,
+            segment_size: LHKEY_SEGMENT_MAX_LENGTH,
+            inline_base64: true,
+        }
+    }
+
+    /// Inlines data of at most `inline_threshold` bytes into its key, instead of storing it.
+    ///
+    /// The threshold must not exceed [`MAX_SIZE_RAW`].
+    pub const fn with_inline_threshold(
+        mut self,
+        inline_threshold: usize,
+    ) -> Result<Self, HkeyError> {
+        if inline_threshold > MAX_SIZE_RAW {
+            return Err(HkeyError::InlineThreshold(inline_threshold));
+        }
+
+        self.inline_threshold = inline_threshold;
+
+        Ok(self)
+    }
+
+    /// Whether data which already is a valid encrypted chunk is stored as
+    /// [`Hkey::Direct`](crate::Hkey::Direct). Otherwise, all data is encrypted.
+    #[must_use]
+    pub const fn with_store_direct(mut self, store_direct: bool) -> Self {
+        self.store_direct = store_direct;
+        self
+    }
+
+    /// Splits long data into segments of `segment_size` bytes, grouped sixteen to a node.
+    ///
+    /// The size must be a power of two from [`MIN_SEGMENT_SIZE`] to 4096, so that every
+    /// segment fits a single chunk. Besides `put`, it shapes the trees built by
+    /// [`HkeyWriter`](crate::HkeyWriter), [`resize`](crate::LongHkeyExpanded::resize) and
+    /// [`splice`](crate::LongHkeyExpanded::splice), and bounds content-defined segments.
+    pub const fn with_segment_size(mut self, segment_size: usize) -> Result<Self, HkeyError> {
+        if !segment_size.is_power_of_two()
+            || segment_size < MIN_SEGMENT_SIZE
+            || segment_size > LHKEY_SEGMENT_MAX_LENGTH
+        {
+            return Err(HkeyError::SegmentSize(segment_size));
+        }
+
+        self.segment_size = segment_size;
+
+        Ok(self)
+    }
+
+    /// Whether shrinking keeps short [`Hkey::Base64`](crate::Hkey::Base64) keys as they are.
+    /// Otherwise, their data is put again, which inlines it as
+    /// [`Hkey::Raw`](crate::Hkey::Raw) or stores it, as the inline threshold decides.
+    #[must_use]
+    pub const fn with_inline_base64(mut self, inline_base64: bool) -> Self {
+        self.inline_base64 = inline_base64;
+        self
+    }
+
+    #[must_use]
+    pub const fn inline_threshold(&self) -> usize {
+        self.inline_threshold
+    }
+
+    #[must_use]
+    pub const fn store_direct(&self) -> bool {
+        self.store_direct
+    }
+
+    #[must_use]
+    pub const fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    #[must_use]
+    pub const fn inline_base64(&self) -> bool {
+        self.inline_base64
+    }
+
+    /// Returns the length of the data a node of depth 0 holds.
+    #[must_use]
+    pub const fn level_length(&self) -> usize {
+        self.segment_size << LHKEY_PART_COUNT_LOG2
+    }
+
+    /// Returns the length of the parts of a node of depth `depth`.
+    #[must_use]
+    pub const fn segment_length(&self, depth: u32) -> usize {
+        let log2 = self.segment_size.ilog2() + depth * LHKEY_PART_COUNT_LOG2;
+
+        if log2 >= usize::BITS {
+            1 << (usize::BITS - 1)
+        } else {
+            1 << log2
+        }
+    }
+
+    /// Returns the depth of a node holding data up to `end`, and at least `min`.
+    #[must_use]
+    pub const fn depth(&self, min: u32, end: usize) -> u32 {
+        if end <= self.level_length() {
+            return min;
+        }
+
+        let log2 = (end - 1).ilog2() + 1;
+        let derived = (log2 - self.level_length().ilog2()).div_ceil(LHKEY_PART_COUNT_LOG2);
+
+        if derived > min {
+            derived
+        } else {
+            min
+        }
+    }
+}
+
+impl Default for PutPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wrapper attaching a [`PutPolicy`] to the inner store, which it returns from
+/// [`Store::policy`] and [`AsyncStore::policy`].
+#[derive(Clone, Debug, Default)]
+pub struct PolicyStore<S> {
+    inner: S,
+    policy: PutPolicy,
+}
+
+impl<S> PolicyStore<S> {
+    pub const fn new(inner: S, policy: PutPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub const fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Store> Store for PolicyStore<S> {
+    type Chunk<'c>
+        = S::Chunk<'c>
+    where
+        Self: 'c;
+    type Error = S::Error;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        self.inner.get(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.policy
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.inner.secret()
+    }
+}
+
+impl<S: StoreContains> StoreContains for PolicyStore<S> {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: StoreIter> StoreIter for PolicyStore<S> {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: StoreRemove> StoreRemove for PolicyStore<S> {
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+impl<S: AsyncStore> AsyncStore for PolicyStore<S> {
+    type Chunk = S::Chunk;
+    type Error = S::Error;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        self.inner.get(hash)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.policy
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.inner.secret()
+    }
+}
+
+impl<S: AsyncStoreContains> AsyncStoreContains for PolicyStore<S> {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: AsyncStoreIter> AsyncStoreIter for PolicyStore<S> {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: AsyncStoreRemove> AsyncStoreRemove for PolicyStore<S> {
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::{BorrowedDataChunk, Bytes, DataChunk};
+
+    use super::{PolicyStore, PutPolicy, LHKEY_LEVEL_MAX_LENGTH, LHKEY_PART_COUNT_LOG2};
+    use crate::{
+        AsyncStore, Chunking, FastCdc, Hkey, HkeyError, InMemoryAsyncStore, InMemoryStore,
+        InMemoryStoreError, LongHkeyExpanded, Store, StoreIter, MAX_SIZE_RAW,
+    };
+
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn small_segments() -> PutPolicy {
+        PutPolicy::new()
+            .with_segment_size(256)
+            .expect("Valid segment size")
+    }
+
+    #[test]
+    fn default_geometry_matches_constants() {
+        let policy = PutPolicy::default();
+
+        assert_eq!(policy.level_length(), 0x10000);
+        assert_eq!(policy.segment_length(0), 0x1000);
+        assert_eq!(policy.segment_length(2), 0x0010_0000);
+        assert_eq!(policy.depth(0, 0x10000), 0);
+        assert_eq!(policy.depth(0, 0x10001), 1);
+        assert_eq!(policy.depth(0, 0x0100_0000), 2);
+        assert_eq!(policy.depth(3, 0x0100_0000), 3);
+        assert_eq!(policy.depth(0, 0xFFFF_FFFF), 4);
+    }
+
+    #[test]
+    fn default_depth_border_values() {
+        let policy = PutPolicy::default();
+        let max_depth = usize::MAX.ilog2() / 4 - 3;
+
+        for depth in 0..max_depth {
+            let cutoff = LHKEY_LEVEL_MAX_LENGTH << (depth * LHKEY_PART_COUNT_LOG2);
+
+            for test in (cutoff - 8)..cutoff {
+                assert_eq!(policy.depth(0, test), depth, "failed under={test:x}");
+            }
+
+            for test in (cutoff + 1)..(cutoff + 8) {
+                assert_eq!(policy.depth(0, test), depth + 1, "failed over={test:x}");
+            }
+        }
+    }
+
+    #[test]
+    fn default_depth_powers() {
+        let policy = PutPolicy::default();
+
+        assert_eq!(policy.depth(0, 0x1), 0);
+        assert_eq!(policy.depth(0, 0x10), 0);
+        assert_eq!(policy.depth(0, 0x100), 0);
+        assert_eq!(policy.depth(0, 0x1000), 0);
+        assert_eq!(policy.depth(0, 0x10000), 0);
+        assert_eq!(policy.depth(0, 0x0010_0000), 1);
+        assert_eq!(policy.depth(0, 0x0100_0000), 2);
+        assert_eq!(policy.depth(0, 0x1000_0000), 3);
+        assert_eq!(policy.depth(0, 0xFFFF_FFFF), 4);
+
+        // disable on 32-bit platforms
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(policy.depth(0, 0x0001_0000_0000), 4);
+            assert_eq!(policy.depth(0, 0x0010_0000_0000), 5);
+            assert_eq!(policy.depth(0, 0x0100_0000_0000), 6);
+            assert_eq!(policy.depth(0, 0x1000_0000_0000), 7);
+            assert_eq!(policy.depth(0, 0x0001_0000_0000_0000), 8);
+            assert_eq!(policy.depth(0, 0x0010_0000_0000_0000), 9);
+            assert_eq!(policy.depth(0, 0x0100_0000_0000_0000), 10);
+            assert_eq!(policy.depth(0, 0x1000_0000_0000_0000), 11);
+            assert_eq!(policy.depth(0, 0xFFFF_FFFF_FFFF_FFFF), 12);
+        }
+
+        // as of writing this comment, longer buffers than 2^64-1 bytes are not supported
+    }
+
+    #[test]
+    fn default_segment_length_powers() {
+        let policy = PutPolicy::default();
+
+        assert_eq!(policy.segment_length(0), 0x1000);
+        assert_eq!(policy.segment_length(1), 0x10000);
+        assert_eq!(policy.segment_length(2), 0x0010_0000);
+        assert_eq!(policy.segment_length(3), 0x0100_0000);
+        assert_eq!(policy.segment_length(4), 0x1000_0000);
+
+        // disable on 32-bit platforms
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(policy.segment_length(5), 0x0001_0000_0000);
+            assert_eq!(policy.segment_length(6), 0x0010_0000_0000);
+            assert_eq!(policy.segment_length(7), 0x0100_0000_0000);
+            assert_eq!(policy.segment_length(8), 0x1000_0000_0000);
+            assert_eq!(policy.segment_length(9), 0x0001_0000_0000_0000);
+            assert_eq!(policy.segment_length(10), 0x0010_0000_0000_0000);
+            assert_eq!(policy.segment_length(11), 0x0100_0000_0000_0000);
+            assert_eq!(policy.segment_length(12), 0x1000_0000_0000_0000);
+        }
+
+        // as of writing this comment, longer buffers than 2^64-1 bytes are not supported
+    }
+
+    #[test]
+    fn smaller_segments_deepen_trees() {
+        let policy = PutPolicy::new()
+            .with_segment_size(256)
+            .expect("Valid segment size");
+
+        assert_eq!(policy.level_length(), 0x1000);
+        assert_eq!(policy.segment_length(1), 0x1000);
+        assert_eq!(policy.depth(0, 0x1000), 0);
+        assert_eq!(policy.depth(0, 0x1001), 1);
+        assert_eq!(policy.depth(0, 0x10001), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(PutPolicy::new()
+            .with_inline_threshold(MAX_SIZE_RAW + 1)
+            .is_err());
+        assert!(PutPolicy::new().with_segment_size(1000).is_err());
+        assert!(PutPolicy::new().with_segment_size(32).is_err());
+        assert!(PutPolicy::new().with_segment_size(8192).is_err());
+    }
+
+    #[test]
+    fn encrypts_instead_of_storing_direct() {
+        let encrypted = BorrowedDataChunk::from_data(&sequential_bytes(1000))
+            .expect("Failed to borrow")
+            .encrypt()
+            .expect("Failed to encrypt");
+        let data = encrypted.data_ref();
+        let store = InMemoryStore::default();
+
+        let direct = store.put(data).expect("Failed to put");
+        let policy = PutPolicy::new().with_store_direct(false);
+        let hkey = store.put_with(data, policy).expect("Failed to put");
+
+        assert!(matches!(direct, Hkey::Direct(_)));
+        assert!(matches!(hkey, Hkey::Encrypted(..)));
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+    }
+
+    #[test]
+    fn inline_threshold_limits_raw_keys() {
+        let policy = PutPolicy::new()
+            .with_inline_threshold(0)
+            .expect("Valid threshold");
+        let store = PolicyStore::new(InMemoryStore::default(), policy);
+
+        let hkey = store.put(b"short").expect("Failed to put");
+
+        assert!(matches!(hkey, Hkey::Encrypted(..)));
+        assert_eq!(store.inner().hashes().expect("Failed to list").len(), 1);
+        assert_eq!(
+            hkey.resolve(&store).expect("Failed to resolve"),
+            b"short".as_slice()
+        );
+
+        let base64 =
+            Hkey::from_base64_slice(&ps_base64::encode(b"short")).expect("Failed to construct");
+
+        assert_eq!(
+            base64.shrink_or_not(&store).expect("Failed to shrink"),
+            None
+        );
+
+        let store = PolicyStore::new(store.into_inner(), policy.with_inline_base64(false));
+
+        assert!(matches!(
+            base64.shrink_or_not(&store).expect("Failed to shrink"),
+            Some(Hkey::Encrypted(..))
+        ));
+    }
+
+    #[test]
+    fn base64_is_reinlined_as_raw() {
+        let store = PolicyStore::new(
+            InMemoryStore::default(),
+            PutPolicy::new().with_inline_base64(false),
+        );
+        let base64 =
+            Hkey::from_base64_slice(&ps_base64::encode(b"short")).expect("Failed to construct");
+
+        let shrunk = base64
+            .shrink_or_not(&store)
+            .expect("Failed to shrink")
+            .expect("Base64 was kept");
+
+        assert!(matches!(shrunk, Hkey::Raw(_)));
+        assert_eq!(
+            shrunk.resolve(&store).expect("Failed to resolve"),
+            b"short".as_slice()
+        );
+    }
+
+    #[test]
+    fn segment_size_shapes_long_keys() {
+        let store = PolicyStore::new(InMemoryStore::default(), small_segments());
+        let data = sequential_bytes(20_000);
+
+        let lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+        assert_eq!(lhkey.depth(), 1);
+        assert_eq!(lhkey.parts().len(), 5);
+        assert_eq!(lhkey.parts()[0].0, 0..4096);
+        assert_eq!(
+            LongHkeyExpanded::from_blob_with(&InMemoryStore::default(), &data, small_segments())
+                .expect("Failed to store"),
+            lhkey
+        );
+
+        let hkey = store.put(&data).expect("Failed to put");
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+
+        let mut expected = data.clone();
+
+        expected[5000..5300].fill(0xAA);
+
+        let updated = lhkey
+            .update(&store, &[0xAA; 300], 5000..5300)
+            .expect("Failed to update");
+
+        assert_eq!(
+            updated,
+            LongHkeyExpanded::from_blob(&store, &expected).expect("Failed to store")
+        );
+        assert_eq!(
+            updated.resolve(&store).expect("Failed to resolve"),
+            expected
+        );
+    }
+
+    #[test]
+    fn resize_and_splice_follow_segment_size() {
+        let store = PolicyStore::new(InMemoryStore::default(), small_segments());
+        let data = sequential_bytes(20_000);
+        let lhkey = LongHkeyExpanded::from_blob(&store, &data).expect("Failed to store");
+
+        let mut expected = data.clone();
+
+        expected.resize(70_000, 0);
+
+        let resized = lhkey.resize(&store, 70_000, 0).expect("Failed to resize");
+        let fresh = LongHkeyExpanded::from_blob(&store, &expected).expect("Failed to store");
+
+        assert_eq!(resized.depth(), 2);
+        assert_eq!(resized.to_string(), fresh.to_string());
+
+        let short = LongHkeyExpanded::from_blob(&store, &data[..3000]).expect("Failed to store");
+        let spliced = short
+            .splice(&store, 1000..1000, &[7; 1000])
+            .expect("Failed to splice");
+
+        assert_eq!(spliced.parts().len(), 16);
+        assert!(spliced.parts().iter().all(|(range, _)| range.len() <= 256));
+    }
+
+    #[test]
+    fn content_defined_segments_must_fit() {
+        let store = PolicyStore::new(InMemoryStore::default(), small_segments());
+        let data = sequential_bytes(20_000);
+        let builder =
+            |cdc| LongHkeyExpanded::builder().with_chunking(Chunking::ContentDefined(cdc));
+
+        assert!(matches!(
+            builder(FastCdc::default()).from_blob(&store, &data),
+            Err(InMemoryStoreError::Hkey(
+                HkeyError::ChunkExceedsSegment { .. }
+            ))
+        ));
+
+        let lhkey = builder(FastCdc::for_policy(&small_segments()))
+            .from_blob(&store, &data)
+            .expect("Failed to store");
+
+        assert_eq!(
+            lhkey.resolve(&store).expect("Failed to resolve").as_ref(),
+            data.as_slice()
+        );
+    }
+
+    #[test]
+    fn async_put_follows_policy() {
+        let store = PolicyStore::new(InMemoryAsyncStore::default(), small_segments());
+        let data = sequential_bytes(20_000);
+
+        let sync =
+            LongHkeyExpanded::from_blob_with(&InMemoryStore::default(), &data, small_segments())
+                .expect("Failed to store")
+                .shrink(&InMemoryStore::default())
+                .expect("Failed to shrink");
+
+        block_on(async {
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            assert_eq!(hkey.to_string(), sync.to_string());
+            assert_eq!(
+                hkey.resolve_async(store.clone())
+                    .await
+                    .expect("Failed to resolve")
+                    .as_ref(),
+                data.as_slice()
+            );
+
+            let hkey = store
+                .put_with(
+                    Bytes::from_static(b"short"),
+                    PutPolicy::new()
+                        .with_inline_threshold(0)
+                        .expect("Valid threshold"),
+                )
+                .await
+                .expect("Failed to put");
+
+            assert!(matches!(hkey, Hkey::Encrypted(..)));
+        });
+    }
+}
@@ -0,0 +1,264 @@
+use ps_datachunk::DataChunk;
+use ps_hash::Hash;
+use ps_promise::Promise;
+
+use crate::{
+    AsyncStore, AsyncStoreContains, AsyncStoreIter, AsyncStoreRemove, HkeyError, PutPolicy, Store,
+    StoreContains, StoreIter, StoreRemove, TenantSecret,
+};
+
+/// Checks that the data of `chunk` hashes to `expected`.
+pub fn verify_chunk<C: DataChunk>(chunk: &C, expected: &Hash) -> Result<(), HkeyError> {
+    let actual = ps_hash::hash(chunk.data_ref())?;
+
+    if actual != *expected {
+        return Err(HkeyError::HashMismatch {
+            expected: *expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// A wrapper recomputing the hash of every chunk fetched from the inner store, and failing
+/// with [`HkeyError::HashMismatch`] if it differs from the requested one.
+///
+/// Since every [`Hkey`](crate::Hkey) is resolved through [`Store::get`] or [`AsyncStore::get`],
+/// wrapping a store is enough to verify every chunk of a resolution.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyingStore<S> {
+    inner: S,
+}
+
+impl<S> VerifyingStore<S> {
+    pub const fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub const fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Store> Store for VerifyingStore<S> {
+    type Chunk<'c>
+        = S::Chunk<'c>
+    where
+        Self: 'c;
+    type Error = S::Error;
+
+    fn get<'a>(&'a self, hash: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+        let chunk = self.inner.get(hash)?;
+
+        verify_chunk(&chunk, hash)?;
+
+        Ok(chunk)
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Result<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.inner.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.inner.secret()
+    }
+}
+
+impl<S: StoreContains> StoreContains for VerifyingStore<S> {
+    fn contains(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: StoreIter> StoreIter for VerifyingStore<S> {
+    fn hashes(&self) -> Result<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: StoreRemove> StoreRemove for VerifyingStore<S> {
+    fn remove(&self, hash: &Hash) -> Result<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+impl<S: AsyncStore> AsyncStore for VerifyingStore<S> {
+    type Chunk = S::Chunk;
+    type Error = S::Error;
+
+    fn get(&self, hash: &Hash) -> Promise<Self::Chunk, Self::Error> {
+        let promise = self.inner.get(hash);
+        let hash = *hash;
+
+        Promise::lazy(async move {
+            let chunk = promise.await?;
+
+            verify_chunk(&chunk, &hash)?;
+
+            Ok(chunk)
+        })
+    }
+
+    fn put_encrypted<C: DataChunk>(&self, chunk: C) -> Promise<(), Self::Error> {
+        self.inner.put_encrypted(chunk)
+    }
+
+    fn policy(&self) -> PutPolicy {
+        self.inner.policy()
+    }
+
+    fn secret(&self) -> Option<TenantSecret> {
+        self.inner.secret()
+    }
+}
+
+impl<S: AsyncStoreContains> AsyncStoreContains for VerifyingStore<S> {
+    fn contains(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.contains(hash)
+    }
+}
+
+impl<S: AsyncStoreIter> AsyncStoreIter for VerifyingStore<S> {
+    fn hashes(&self) -> Promise<Vec<Hash>, Self::Error> {
+        self.inner.hashes()
+    }
+}
+
+impl<S: AsyncStoreRemove> AsyncStoreRemove for VerifyingStore<S> {
+    fn remove(&self, hash: &Hash) -> Promise<bool, Self::Error> {
+        self.inner.remove(hash)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use futures::executor::block_on;
+    use ps_datachunk::{Bytes, DataChunk, OwnedDataChunk};
+    use ps_hash::Hash;
+    use ps_promise::Promise;
+
+    use crate::{
+        AsyncStore, Hkey, HkeyError, InMemoryAsyncStore, InMemoryAsyncStoreError, InMemoryStore,
+        InMemoryStoreError, Store, VerifyingStore,
+    };
+
+    /// A store answering every request with the same chunk.
+    #[derive(Clone)]
+    struct Liar {
+        chunk: OwnedDataChunk,
+    }
+
+    impl Store for Liar {
+        type Chunk<'c> = OwnedDataChunk;
+        type Error = InMemoryStoreError;
+
+        fn get<'a>(&'a self, _: &Hash) -> Result<Self::Chunk<'a>, Self::Error> {
+            Ok(self.chunk.clone())
+        }
+
+        fn put_encrypted<C: DataChunk>(&self, _: C) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl AsyncStore for Liar {
+        type Chunk = OwnedDataChunk;
+        type Error = InMemoryAsyncStoreError;
+
+        fn get(&self, _: &Hash) -> Promise<Self::Chunk, Self::Error> {
+            Promise::resolve(self.chunk.clone())
+        }
+
+        fn put_encrypted<C: DataChunk>(&self, _: C) -> Promise<(), Self::Error> {
+            Promise::resolve(())
+        }
+    }
+
+    fn liar() -> Liar {
+        let chunk = OwnedDataChunk::from_data(vec![7; 1000])
+            .expect("Failed to allocate")
+            .encrypt()
+            .expect("Failed to encrypt")
+            .into_owned();
+
+        Liar { chunk }
+    }
+
+    fn stored_hkey() -> Hkey {
+        InMemoryStore::default()
+            .put(&[1; 1000])
+            .expect("Failed to put")
+    }
+
+    #[test]
+    fn passes_matching_chunks() {
+        let store = VerifyingStore::new(InMemoryStore::default());
+        let data = vec![3; 100_000];
+        let hkey = store.put(&data).expect("Failed to put");
+
+        assert_eq!(hkey.resolve(&store).expect("Failed to resolve"), data);
+    }
+
+    #[test]
+    fn rejects_mismatched_chunks() {
+        let liar = liar();
+        let served = liar.chunk.hash();
+        let hkey = stored_hkey();
+
+        let Hkey::Encrypted(requested, _) = hkey else {
+            panic!("Expected Hkey::Encrypted, got {hkey}");
+        };
+
+        // Without verification, the wrong chunk is decrypted with the wrong key.
+        assert!(hkey.resolve(&liar).is_err());
+
+        let result = hkey.resolve(&VerifyingStore::new(liar));
+
+        assert!(matches!(
+            result,
+            Err(InMemoryStoreError::Hkey(HkeyError::HashMismatch { expected, actual }))
+                if expected == requested && actual == served
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_chunks_async() {
+        let liar = liar();
+        let hkey = stored_hkey();
+
+        block_on(async {
+            let result = hkey.resolve_async(VerifyingStore::new(liar)).await;
+
+            assert!(matches!(
+                result,
+                Err(InMemoryAsyncStoreError::Hkey(
+                    HkeyError::HashMismatch { .. }
+                ))
+            ));
+
+            let store = VerifyingStore::new(InMemoryAsyncStore::default());
+            let data = vec![5; 100_000];
+            let hkey = store
+                .put(Bytes::copy_from_slice(&data))
+                .await
+                .expect("Failed to put");
+
+            assert_eq!(
+                hkey.resolve_async(store.clone())
+                    .await
+                    .expect("Failed to resolve"),
+                data
+            );
+        });
+    }
+}
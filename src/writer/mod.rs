@@ -0,0 +1,319 @@
+use std::{io, mem::take, sync::Arc};
+
+use crate::{
+    constants::MAX_ENCRYPTED_SIZE, long::LHKEY_PART_COUNT, Hkey, LongHkeyExpanded, PutPolicy,
+    Range, Store,
+};
+
+/// Streams bytes into a [`Store`], producing the same [`Hkey`] as [`Store::put`] would for the
+/// concatenation of everything written.
+///
+/// Input is cut into segments of the store's [`PutPolicy::segment_size`], which are stored as
+/// soon as they are known not to be the last one. Every sixteen complete subtrees of a level are combined
+/// into a subtree of the level above, so memory use is bounded by the depth of the tree rather
+/// than by the length of the input.
+pub struct HkeyWriter<'s, S: Store> {
+    store: &'s S,
+    policy: PutPolicy,
+    buffer: Vec<u8>,
+    length: usize,
+    levels: Vec<Vec<Hkey>>,
+}
+
+impl<'s, S: Store> HkeyWriter<'s, S> {
+    pub fn new(store: &'s S) -> Self {
+        Self {
+            store,
+            policy: store.policy(),
+            buffer: Vec::new(),
+            length: 0,
+            levels: Vec::new(),
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Appends `data`, storing every segment that is followed by further input.
+    pub fn push(&mut self, data: &[u8]) -> Result<(), S::Error> {
+        let segment_size = self.policy.segment_size();
+
+        for piece in data.chunks(segment_size) {
+            self.buffer.extend_from_slice(piece);
+            self.length += piece.len();
+
+            // Input that fits into a single chunk is stored as such by `finish`.
+            if self.length <= MAX_ENCRYPTED_SIZE {
+                continue;
+            }
+
+            while self.buffer.len() > segment_size {
+                let hkey = self.store.put(&self.buffer[..segment_size])?;
+
+                self.buffer.drain(..segment_size);
+                self.push_to_level(0, hkey)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores whatever remains buffered and returns the shrunk [`Hkey`] of everything written.
+    pub fn finish(mut self) -> Result<Hkey, S::Error> {
+        if self.length <= MAX_ENCRYPTED_SIZE {
+            return self.store.put(&self.buffer);
+        }
+
+        let remainder_length = self.buffer.len();
+        let mut remainder = self.store.put(&take(&mut self.buffer))?;
+
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+
+        // The final segment lies below every level, so none of them may remain full.
+        let mut level = 0;
+
+        while level < self.levels.len() {
+            if self.levels[level].len() == LHKEY_PART_COUNT {
+                self.carry(level)?;
+            }
+
+            level += 1;
+        }
+
+        let policy = self.policy;
+        let leaves = take(&mut self.levels[0]);
+        let mut size = leaves.len() * level_length(policy, 0) + remainder_length;
+        let mut node = LongHkeyExpanded::new(
+            0,
+            size,
+            ranged(
+                leaves,
+                level_length(policy, 0),
+                Some((remainder_length, remainder)),
+            ),
+        );
+
+        for level in 1..self.levels.len() {
+            let items = take(&mut self.levels[level]);
+
+            if items.is_empty() {
+                continue;
+            }
+
+            let remainder_length = size;
+
+            remainder = node.shrink(self.store)?;
+            size += items.len() * level_length(policy, level);
+            node = LongHkeyExpanded::new(
+                depth_of(level),
+                size,
+                ranged(
+                    items,
+                    level_length(policy, level),
+                    Some((remainder_length, remainder)),
+                ),
+            );
+        }
+
+        node.shrink(self.store)
+    }
+
+    fn push_to_level(&mut self, level: usize, hkey: Hkey) -> Result<(), S::Error> {
+        let mut level = level;
+        let mut hkey = hkey;
+
+        loop {
+            if self.levels.len() <= level {
+                self.levels.resize_with(level + 1, Vec::new);
+            }
+
+            if self.levels[level].len() < LHKEY_PART_COUNT {
+                self.levels[level].push(hkey);
+
+                return Ok(());
+            }
+
+            let items = take(&mut self.levels[level]);
+
+            self.levels[level].push(hkey);
+
+            hkey = combine(self.policy, level, items).shrink(self.store)?;
+            level += 1;
+        }
+    }
+
+    /// Combines the sixteen subtrees of a full level into one subtree of the level above.
+    fn carry(&mut self, level: usize) -> Result<(), S::Error> {
+        let items = take(&mut self.levels[level]);
+        let hkey = combine(self.policy, level, items).shrink(self.store)?;
+
+        self.push_to_level(level + 1, hkey)
+    }
+}
+
+impl<S> io::Write for HkeyWriter<'_, S>
+where
+    S: Store,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.push(buf).map_err(io::Error::other)?;
+
+        Ok(buf.len())
+    }
+
+    /// Segments are only stored once their successor is known, so there is nothing to flush
+    /// before [`HkeyWriter::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds the complete subtree whose parts are the given subtrees of `level`.
+pub(crate) fn combine(policy: PutPolicy, level: usize, items: Vec<Hkey>) -> LongHkeyExpanded {
+    let length = level_length(policy, level);
+
+    LongHkeyExpanded::new(
+        depth_of(level),
+        length * items.len(),
+        ranged(items, length, None),
+    )
+}
+
+/// Returns the depth of the [`LongHkeyExpanded`] whose parts lie on the given level.
+pub(crate) fn depth_of(level: usize) -> u32 {
+    u32::try_from(level).unwrap_or(u32::MAX)
+}
+
+/// Returns the length of a complete subtree on the given level.
+pub(crate) const fn level_length(policy: PutPolicy, level: usize) -> usize {
+    policy.segment_size() << (4 * level)
+}
+
+/// Lays `items` of `length` bytes each end to end, followed by the partial `remainder`, which
+/// begins at the given offset.
+pub(crate) fn ranged(
+    items: Vec<Hkey>,
+    length: usize,
+    remainder: Option<(usize, Hkey)>,
+) -> Arc<[(Range, Hkey)]> {
+    let offset = items.len() * length;
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, hkey)| (index * length..(index + 1) * length, hkey))
+        .chain(remainder.map(|(remainder_length, hkey)| (offset..offset + remainder_length, hkey)))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::io::Write;
+
+    use crate::{HkeyWriter, InMemoryStore, PolicyStore, PutPolicy, Store};
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sequential_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn assert_matches_put(len: usize, write_size: usize) {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(len);
+
+        let mut writer = HkeyWriter::new(&store);
+
+        for piece in data.chunks(write_size) {
+            writer.write_all(piece).expect("Failed to write");
+        }
+
+        assert_eq!(writer.len(), len);
+
+        let hkey = writer.finish().expect("Failed to finish");
+
+        assert_eq!(
+            hkey,
+            store.put(&data).expect("Failed to put"),
+            "length {len}, writes of {write_size}"
+        );
+        assert_eq!(
+            hkey.resolve(&store).expect("Failed to resolve").as_ref(),
+            data.as_slice()
+        );
+    }
+
+    #[test]
+    fn small_inputs_match_put() {
+        for len in [0, 1, 10, 40, 41, 4095, 4096, 4097, 4629, 4630, 4700] {
+            assert_matches_put(len, 1000);
+        }
+    }
+
+    #[test]
+    fn single_level_matches_put() {
+        for len in [8192, 65_535, 65_536, 65_537] {
+            assert_matches_put(len, 4096);
+            assert_matches_put(len, 777);
+        }
+    }
+
+    #[test]
+    fn multiple_levels_match_put() {
+        for len in [
+            70_000,
+            16 * 65_536,
+            16 * 65_536 + 1,
+            17 * 65_536 + 3 * 4096 + 5,
+        ] {
+            assert_matches_put(len, 100_000);
+        }
+    }
+
+    #[test]
+    fn follows_segment_size() {
+        let policy = PutPolicy::new()
+            .with_segment_size(1024)
+            .expect("Valid segment size");
+        let store = PolicyStore::new(InMemoryStore::default(), policy);
+
+        for len in [5000, 16 * 1024 + 1, 300_000] {
+            let data = sequential_bytes(len);
+            let mut writer = HkeyWriter::new(&store);
+
+            writer.write_all(&data).expect("Failed to write");
+
+            assert_eq!(
+                writer.finish().expect("Failed to finish"),
+                store.put(&data).expect("Failed to put"),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn flush_keeps_the_key() {
+        let store = InMemoryStore::default();
+        let data = sequential_bytes(10_000);
+
+        let mut writer = HkeyWriter::new(&store);
+
+        writer.write_all(&data[..5_000]).expect("Failed to write");
+        writer.flush().expect("Failed to flush");
+        writer.write_all(&data[5_000..]).expect("Failed to write");
+
+        assert_eq!(
+            writer.finish().expect("Failed to finish"),
+            store.put(&data).expect("Failed to put")
+        );
+    }
+}